@@ -18,29 +18,31 @@ const routerIdl = ({ IDL }) => {
   });
 
   const Result = IDL.Variant({ 'Ok': IDL.Principal, 'Err': LMSError });
-  const TenantSettings = IDL.Record({
-    'max_students': IDL.Nat32,
-    'max_instructors': IDL.Nat32,
-    'max_courses': IDL.Nat32,
-    'allow_public_enrollment': IDL.Bool,
-    'custom_branding': IDL.Bool,
-  });
-
-  const Tenant = IDL.Record({
+  const PublicTenant = IDL.Record({
     'id': IDL.Text,
     'name': IDL.Text,
     'subdomain': IDL.Text,
     'canister_id': IDL.Text,
-    'admin_ids': IDL.Vec(IDL.Text),
-    'created_at': IDL.Nat64,
-    'updated_at': IDL.Nat64,
     'is_active': IDL.Bool,
-    'settings': TenantSettings,
+    'allow_public_enrollment': IDL.Bool,
+    'created_at': IDL.Nat64,
+  });
+
+  const TenantDirectoryQuery = IDL.Record({
+    'cursor': IDL.Opt(IDL.Text),
+    'limit': IDL.Opt(IDL.Nat32),
+    'search': IDL.Opt(IDL.Text),
+    'active_only': IDL.Bool,
+  });
+
+  const TenantDirectoryPage = IDL.Record({
+    'tenants': IDL.Vec(PublicTenant),
+    'next_cursor': IDL.Opt(IDL.Text),
   });
 
   return IDL.Service({
     'get_tenant_canister': IDL.Func([IDL.Text], [Result], ['query']),
-    'list_tenants': IDL.Func([], [IDL.Vec(Tenant)], ['query']),
+    'list_tenant_directory': IDL.Func([TenantDirectoryQuery], [TenantDirectoryPage], ['query']),
     'health_check': IDL.Func([], [IDL.Text], ['query']),
   });
 };
@@ -54,6 +56,7 @@ const tenantIdl = ({ IDL }) => {
     'InvalidRole': IDL.Text,
     'InternalError': IDL.Text,
     'InitializationError': IDL.Text,
+    'QuotaExceeded': IDL.Text,
   });
 
   const UserRole = IDL.Variant({
@@ -570,8 +573,20 @@ class ApiService {
         await this.init();
       }
       
-      const result = await this.routerActor.list_tenants();
-      return result;
+      // Page through the public directory; full tenant records are operator-only
+      const tenants = [];
+      let cursor = [];
+      do {
+        const page = await this.routerActor.list_tenant_directory({
+          cursor,
+          limit: [100],
+          search: [],
+          active_only: false,
+        });
+        tenants.push(...page.tenants);
+        cursor = page.next_cursor;
+      } while (cursor.length > 0);
+      return tenants;
     } catch (error) {
       console.error('Failed to list tenants:', error);
       throw error;
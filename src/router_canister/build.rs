@@ -2,6 +2,9 @@ use std::env;
 use std::fs;
 use std::path::Path;
 
+// Development fallback only: the router installs the active WASM registry version when one is set
+const EMPTY_TENANT_WASM: &str = "pub const TENANT_WASM: &[u8] = &[];";
+
 fn main() {
     // Path to the compiled tenant canister WASM (relative to the workspace root)
     let tenant_wasm_path = "../../.dfx/local/canisters/tenant_canister/tenant_canister.wasm";
@@ -25,26 +28,15 @@ fn main() {
                 println!("cargo:warning=Successfully embedded tenant WASM: {} bytes", wasm_bytes.len());
             }
             Err(e) => {
-                // If we can't read the WASM file, generate a placeholder
-                let placeholder_code = r#"
-                    pub const TENANT_WASM: &[u8] = &[
-                        0x00, 0x61, 0x73, 0x6d, // magic number
-                        0x01, 0x00, 0x00, 0x00, // version
-                    ];
-                "#;
-                fs::write(&dest_path, placeholder_code).unwrap();
+                // If we can't read the WASM file, embed nothing; provisioning then
+                // requires a module uploaded to the router's WASM registry
+                fs::write(&dest_path, EMPTY_TENANT_WASM).unwrap();
                 println!("cargo:warning=Failed to read tenant WASM: {}", e);
             }
         }
     } else {
-        // Generate a placeholder if the WASM doesn't exist yet
-        let placeholder_code = r#"
-            pub const TENANT_WASM: &[u8] = &[
-                0x00, 0x61, 0x73, 0x6d, // magic number
-                0x01, 0x00, 0x00, 0x00, // version
-            ];
-        "#;
-        fs::write(&dest_path, placeholder_code).unwrap();
-        println!("cargo:warning=Tenant WASM not found at {}, using placeholder", tenant_wasm_path);
+        // Embed nothing if the WASM doesn't exist yet
+        fs::write(&dest_path, EMPTY_TENANT_WASM).unwrap();
+        println!("cargo:warning=Tenant WASM not found at {}, upload a module to the WASM registry instead", tenant_wasm_path);
     }
 }
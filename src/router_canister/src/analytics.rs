@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+use candid::Principal;
+use shared::{Tenant, TenantSummary, LMSResult, current_time};
+use crate::types::{PlatformSummary, SummaryCollection, TenantSummarySample};
+use crate::storage::{with_tenant_registry, with_tenant_summaries};
+
+const NANOS_PER_HOUR: u64 = 60 * 60 * 1_000_000_000;
+/// How often the scheduler collects summaries; also the width of a sample bucket
+pub const COLLECTION_INTERVAL_SECONDS: u64 = 6 * 60 * 60;
+const BUCKET_SIZE: u64 = 6 * NANOS_PER_HOUR;
+const RETENTION: u64 = 90 * 24 * NANOS_PER_HOUR;
+
+/// Ask every active tenant for its summary and store it under the current bucket.
+/// Suspended tenants are skipped because their canisters are stopped.
+pub async fn collect_tenant_summaries() -> SummaryCollection {
+    let bucket_start = bucket_start(current_time());
+    let tenants: Vec<Tenant> = with_tenant_registry(|registry| {
+        registry.borrow()
+            .iter()
+            .map(|(_, tenant)| tenant)
+            .filter(|tenant| tenant.is_active)
+            .collect()
+    });
+
+    let mut collection = SummaryCollection {
+        bucket_start,
+        collected: 0,
+        failures: Vec::new(),
+    };
+    for tenant in tenants {
+        match fetch_summary(&tenant).await {
+            Ok(summary) => {
+                with_tenant_summaries(|summaries| {
+                    summaries.borrow_mut().insert(
+                        sample_key(&tenant.id, bucket_start),
+                        TenantSummarySample { bucket_start, summary },
+                    );
+                });
+                collection.collected += 1;
+            },
+            Err(reason) => collection.failures.push((tenant.id, reason)),
+        }
+    }
+
+    ic_cdk::println!("Collected {} tenant summaries ({} failed)",
+                     collection.collected, collection.failures.len());
+    collection
+}
+
+/// Background job entry point used by the scheduler: collect, then drop samples past retention
+pub async fn run_summary_collection() {
+    collect_tenant_summaries().await;
+
+    let cutoff = current_time().saturating_sub(RETENTION);
+    let expired: Vec<String> = with_tenant_summaries(|summaries| {
+        summaries.borrow()
+            .iter()
+            .filter(|(_, sample)| sample.bucket_start < cutoff)
+            .map(|(key, _)| key)
+            .collect()
+    });
+    with_tenant_summaries(|summaries| {
+        let mut summaries = summaries.borrow_mut();
+        for key in &expired {
+            summaries.remove(key);
+        }
+    });
+}
+
+/// A tenant's samples, oldest first, optionally limited to `[from, to)`
+pub fn get_tenant_analytics(tenant_id: String, from: Option<u64>, to: Option<u64>) -> LMSResult<Vec<TenantSummarySample>> {
+    let prefix = format!("{}#", tenant_id);
+    let from = from.unwrap_or(0);
+    let to = to.unwrap_or(u64::MAX);
+
+    Ok(with_tenant_summaries(|summaries| {
+        summaries.borrow()
+            .range(sample_key(&tenant_id, bucket_start(from))..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, sample)| sample)
+            .filter(|sample| sample.bucket_start >= from && sample.bucket_start < to)
+            .collect()
+    }))
+}
+
+/// The most recent sample of every tenant that has reported
+pub fn get_latest_tenant_summaries() -> LMSResult<Vec<TenantSummarySample>> {
+    let mut latest: BTreeMap<String, TenantSummarySample> = BTreeMap::new();
+    with_tenant_summaries(|summaries| {
+        // Keys sort by tenant, then chronologically, so the last sample seen per tenant wins
+        for (_, sample) in summaries.borrow().iter() {
+            latest.insert(sample.summary.tenant_id.clone(), sample);
+        }
+    });
+    Ok(latest.into_values().collect())
+}
+
+/// The most recent summary a tenant reported
+pub fn latest_tenant_summary(tenant_id: &str) -> Option<TenantSummary> {
+    let prefix = format!("{}#", tenant_id);
+    with_tenant_summaries(|summaries| {
+        summaries.borrow()
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .last()
+            .map(|(_, sample)| sample.summary)
+    })
+}
+
+/// Platform-wide totals per collection bucket, oldest first, optionally limited to `[from, to)`
+pub fn get_platform_analytics(from: Option<u64>, to: Option<u64>) -> LMSResult<Vec<PlatformSummary>> {
+    let from = from.unwrap_or(0);
+    let to = to.unwrap_or(u64::MAX);
+    let samples: Vec<TenantSummarySample> = with_tenant_summaries(|summaries| {
+        summaries.borrow()
+            .iter()
+            .map(|(_, sample)| sample)
+            .filter(|sample| sample.bucket_start >= from && sample.bucket_start < to)
+            .collect()
+    });
+    Ok(aggregate(samples))
+}
+
+fn aggregate(samples: Vec<TenantSummarySample>) -> Vec<PlatformSummary> {
+    let mut buckets: BTreeMap<u64, PlatformSummary> = BTreeMap::new();
+    for sample in samples {
+        let total = buckets.entry(sample.bucket_start).or_insert_with(|| PlatformSummary {
+            bucket_start: sample.bucket_start,
+            ..PlatformSummary::default()
+        });
+        let summary = &sample.summary;
+        total.tenants_reporting += 1;
+        total.students += summary.students as u64;
+        total.instructors += summary.instructors as u64;
+        total.admins += summary.admins as u64;
+        total.inactive_users += summary.inactive_users as u64;
+        total.courses += summary.courses as u64;
+        total.published_courses += summary.published_courses as u64;
+        total.quiz_attempts += summary.quiz_attempts;
+        total.files += summary.files;
+        total.storage_bytes += summary.storage_bytes;
+    }
+    buckets.into_values().collect()
+}
+
+async fn fetch_summary(tenant: &Tenant) -> Result<TenantSummary, String> {
+    let canister_id = Principal::from_text(&tenant.canister_id)
+        .map_err(|_| "Invalid canister ID".to_string())?;
+    let (result,): (LMSResult<TenantSummary>,) = ic_cdk::call(canister_id, "get_tenant_summary", ()).await
+        .map_err(|(code, msg)| format!("Summary call failed: {:?} - {}", code, msg))?;
+    let mut summary = result.map_err(|e| format!("Tenant refused summary: {:?}", e))?;
+    // Older tenants may report a different ID than the registry; the registry is authoritative
+    summary.tenant_id = tenant.id.clone();
+    Ok(summary)
+}
+
+fn bucket_start(time: u64) -> u64 {
+    time - time % BUCKET_SIZE
+}
+
+fn sample_key(tenant_id: &str, bucket_start: u64) -> String {
+    // Zero-padded so keys sort chronologically within a tenant
+    format!("{}#{:020}", tenant_id, bucket_start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(tenant_id: &str, bucket_start: u64, students: u32) -> TenantSummarySample {
+        TenantSummarySample {
+            bucket_start,
+            summary: TenantSummary {
+                tenant_id: tenant_id.to_string(),
+                students,
+                storage_bytes: 10,
+                ..TenantSummary::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let totals = aggregate(vec![
+            sample("harvard", BUCKET_SIZE, 5),
+            sample("mit", BUCKET_SIZE, 7),
+            sample("harvard", 0, 3),
+        ]);
+
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].bucket_start, 0);
+        assert_eq!(totals[0].students, 3);
+        assert_eq!(totals[1].tenants_reporting, 2);
+        assert_eq!(totals[1].students, 12);
+        assert_eq!(totals[1].storage_bytes, 20);
+    }
+}
@@ -1,7 +1,13 @@
 use candid::{candid_method, Principal};
-use ic_cdk::{query, update, init, caller};
-use shared::{Tenant, LMSResult};
-use crate::types::{RouterStats, CycleInfo, TemplateConfig, TenantRegistryInspection, RoutingTableInspection, FullSystemInspection};
+use ic_cdk::{query, update, init, post_upgrade, caller};
+use ic_cdk::api::management_canister::http_request::{HttpResponse as OutcallResponse, TransformArgs};
+use shared::{Tenant, ReleaseChannel, TemplateTrack, SchemaState, BackupBatch, LMSResult};
+use crate::types::{RouterStats, OperatorRole, PlatformOperator, CycleInfo, TemplateConfig, TenantRegistryInspection, RoutingTableInspection, FullSystemInspection, FleetUpgrade, WasmModuleInfo,
+    CyclesMonitorConfig, TenantCyclesStatus, CyclesAlert, TenantSuspension, TenantDecommission, CustomDomain, BaseDomain, SubdomainAlias, SubdomainPolicy,
+    MetricsGranularity, MetricsBucket, TenantRequestTotals, RepairRun, TenantProvisioning, TenantSettingsUpdate,
+    TenantSummarySample, PlatformSummary, SummaryCollection, TenantDirectoryQuery, TenantDirectoryPage,
+    PriceTable, UsageMeter, BillingStatement, StatementQuery, StatementFormat, TenantApplication, TenantApplicationRequest,
+    OwnershipAuditEntry, TenantBackup, TenantRestore, HealthMonitorConfig, TenantHealth, HealthDashboard};
 use crate::storage::{with_router_config, with_tenant_registry, with_template_config};
 
 /// Initialize the router canister
@@ -10,26 +16,282 @@ fn init() {
     with_router_config(|config| {
         let _ = config.borrow_mut().set(true);
     });
+    crate::schema::init_schema();
+    crate::canister_management::store_embedded_module();
+    crate::scheduler::start_scheduler();
     
     ic_cdk::println!("Router canister initialized by: {}", caller());
 }
 
+/// Migrate stable stores whose schema changed in the new build
+#[post_upgrade]
+fn post_upgrade() {
+    crate::schema::run_upgrade_migrations();
+    crate::canister_management::store_embedded_module();
+    crate::scheduler::start_scheduler();
+}
+
+/// Grant a principal the platform operator or support role (controllers only)
+#[update]
+#[candid_method(update)]
+fn add_platform_operator(principal: Principal, role: OperatorRole) -> LMSResult<PlatformOperator> {
+    crate::auth::require_controller()?;
+    crate::auth::add_platform_operator(principal, role)
+}
+
+/// Revoke a principal's platform role (controllers only)
+#[update]
+#[candid_method(update)]
+fn remove_platform_operator(principal: Principal) -> LMSResult<()> {
+    crate::auth::require_controller()?;
+    crate::auth::remove_platform_operator(principal)
+}
+
+/// List registered platform operators
+#[query]
+#[candid_method(query)]
+fn list_platform_operators() -> LMSResult<Vec<PlatformOperator>> {
+    crate::auth::require_support()?;
+    Ok(crate::auth::list_platform_operators())
+}
+
+/// Get the caller's platform role, if any
+#[query]
+#[candid_method(query)]
+fn get_my_operator_role() -> Option<OperatorRole> {
+    crate::auth::role_of(&caller())
+}
+
 /// Configure template canister for tenant provisioning
 #[update]
 #[candid_method(update)]
 fn configure_template(template_canister_id: Principal, version: String) -> LMSResult<()> {
+    crate::auth::require_operator()?;
     crate::template::configure_template(template_canister_id, version)
 }
 
-/// Register a new university and provision its tenant canister (Modern Template-Based Approach)
+/// Register a new university and provision its tenant canister (Modern Template-Based Approach).
+/// The tenant follows the stable channel unless another template track is given.
 #[update]
 #[candid_method(update)]
 async fn register_university(
     subdomain: String,
     university_name: String,
     admin_principal: Principal,
+    template_track: Option<TemplateTrack>,
 ) -> LMSResult<Tenant> {
-    crate::tenant_management::register_university(subdomain, university_name, admin_principal).await
+    crate::auth::require_operator()?;
+    crate::tenant_management::register_university(subdomain, university_name, admin_principal, template_track).await
+}
+
+/// Continue a failed or interrupted university registration from its last completed step
+#[update]
+#[candid_method(update)]
+async fn resume_provisioning(request_id: u64) -> LMSResult<Tenant> {
+    crate::auth::require_operator()?;
+    crate::provisioning::resume_provisioning(request_id).await
+}
+
+/// Abandon an unfinished registration, deleting its canister and releasing its subdomain
+#[update]
+#[candid_method(update)]
+async fn cancel_provisioning(request_id: u64) -> LMSResult<TenantProvisioning> {
+    crate::auth::require_operator()?;
+    crate::provisioning::cancel_provisioning(request_id).await
+}
+
+/// Get a university registration by request ID
+#[query]
+#[candid_method(query)]
+fn get_provisioning(request_id: u64) -> LMSResult<TenantProvisioning> {
+    crate::auth::require_support()?;
+    crate::provisioning::get_provisioning(request_id)
+}
+
+/// List in-flight and failed university registrations
+#[query]
+#[candid_method(query)]
+fn list_provisionings(include_finished: bool) -> LMSResult<Vec<TenantProvisioning>> {
+    crate::auth::require_support()?;
+    Ok(crate::provisioning::list_provisionings(include_finished))
+}
+
+/// Apply for a tenant; the subdomain is only checked again when an operator approves
+#[update]
+#[candid_method(update)]
+fn submit_tenant_application(request: TenantApplicationRequest) -> LMSResult<TenantApplication> {
+    crate::applications::submit_tenant_application(request)
+}
+
+/// Approve a pending tenant application and provision its tenant
+#[update]
+#[candid_method(update)]
+async fn approve_tenant_application(application_id: u64) -> LMSResult<Tenant> {
+    crate::auth::require_operator()?;
+    crate::applications::approve_tenant_application(application_id).await
+}
+
+/// Reject a pending tenant application with a reason shown to the applicant
+#[update]
+#[candid_method(update)]
+fn reject_tenant_application(application_id: u64, reason: String) -> LMSResult<TenantApplication> {
+    crate::auth::require_operator()?;
+    crate::applications::reject_tenant_application(application_id, reason)
+}
+
+/// Get a tenant application (applicant, designated admin or platform staff)
+#[query]
+#[candid_method(query)]
+fn get_tenant_application(application_id: u64) -> LMSResult<TenantApplication> {
+    crate::applications::get_tenant_application(application_id)
+}
+
+/// List the caller's tenant applications
+#[query]
+#[candid_method(query)]
+fn list_my_tenant_applications() -> Vec<TenantApplication> {
+    crate::applications::list_my_tenant_applications()
+}
+
+/// List tenant applications for review
+#[query]
+#[candid_method(query)]
+fn list_tenant_applications(pending_only: bool) -> LMSResult<Vec<TenantApplication>> {
+    crate::auth::require_support()?;
+    Ok(crate::applications::list_tenant_applications(pending_only))
+}
+
+/// Change a tenant's display name; admins change through `transfer_tenant_ownership`
+#[update]
+#[candid_method(update)]
+async fn update_tenant_metadata(tenant_id: String, name: Option<String>) -> LMSResult<Tenant> {
+    crate::auth::require_operator()?;
+    crate::tenant_settings::update_tenant_metadata(tenant_id, name).await
+}
+
+/// Change a tenant's quotas and feature flags and push them to its canister
+#[update]
+#[candid_method(update)]
+async fn update_tenant_settings(tenant_id: String, update: TenantSettingsUpdate) -> LMSResult<Tenant> {
+    crate::auth::require_operator()?;
+    crate::tenant_settings::update_tenant_settings(tenant_id, update).await
+}
+
+/// Push a tenant's stored settings to its canister, which enforces the quotas
+#[update]
+#[candid_method(update)]
+async fn sync_tenant_settings(tenant_id: String) -> LMSResult<()> {
+    crate::auth::require_operator()?;
+    crate::tenant_settings::sync_tenant_settings(tenant_id).await
+}
+
+/// List the controllers of a tenant's canister
+#[update]
+#[candid_method(update)]
+async fn list_tenant_controllers(tenant_id: String) -> LMSResult<Vec<Principal>> {
+    crate::auth::require_support()?;
+    crate::ownership::list_tenant_controllers(tenant_id).await
+}
+
+/// Add a controller to a tenant's canister
+#[update]
+#[candid_method(update)]
+async fn add_tenant_controller(tenant_id: String, controller: Principal) -> LMSResult<Vec<Principal>> {
+    crate::auth::require_operator()?;
+    crate::ownership::add_tenant_controller(tenant_id, controller).await
+}
+
+/// Remove a controller from a tenant's canister
+#[update]
+#[candid_method(update)]
+async fn remove_tenant_controller(tenant_id: String, controller: Principal) -> LMSResult<Vec<Principal>> {
+    crate::auth::require_operator()?;
+    crate::ownership::remove_tenant_controller(tenant_id, controller).await
+}
+
+/// Transfer a tenant to a new admin principal
+#[update]
+#[candid_method(update)]
+async fn transfer_tenant_ownership(tenant_id: String, new_admin: Principal, keep_previous_admin: bool) -> LMSResult<Tenant> {
+    crate::auth::require_operator()?;
+    crate::ownership::transfer_tenant_ownership(tenant_id, new_admin, keep_previous_admin).await
+}
+
+/// List controller and admin changes, newest first
+#[query]
+#[candid_method(query)]
+fn list_ownership_audit(tenant_id: Option<String>) -> LMSResult<Vec<OwnershipAuditEntry>> {
+    crate::auth::require_support()?;
+    Ok(crate::ownership::list_ownership_audit(tenant_id))
+}
+
+/// Start backing up a tenant's data; batches are exported in the background
+#[update]
+#[candid_method(update)]
+fn start_tenant_backup(tenant_id: String) -> LMSResult<TenantBackup> {
+    crate::auth::require_operator()?;
+    crate::backup::start_tenant_backup(tenant_id)
+}
+
+/// Get a tenant backup by ID
+#[query]
+#[candid_method(query)]
+fn get_tenant_backup(backup_id: u64) -> LMSResult<TenantBackup> {
+    crate::auth::require_support()?;
+    crate::backup::get_tenant_backup(backup_id)
+}
+
+/// List tenant backups, newest first
+#[query]
+#[candid_method(query)]
+fn list_tenant_backups(tenant_id: Option<String>) -> LMSResult<Vec<TenantBackup>> {
+    crate::auth::require_support()?;
+    Ok(crate::backup::list_tenant_backups(tenant_id))
+}
+
+/// Download one batch of a backup for off-platform storage
+#[query]
+#[candid_method(query)]
+fn get_backup_batch(backup_id: u64, sequence: u32) -> LMSResult<BackupBatch> {
+    crate::auth::require_operator()?;
+    crate::backup::get_backup_batch(backup_id, sequence)
+}
+
+/// Delete a finished backup and its batches
+#[update]
+#[candid_method(update)]
+fn delete_tenant_backup(backup_id: u64) -> LMSResult<()> {
+    crate::auth::require_operator()?;
+    crate::backup::delete_tenant_backup(backup_id)
+}
+
+/// Restore a backup into a newly provisioned tenant
+#[update]
+#[candid_method(update)]
+async fn restore_tenant_backup(
+    backup_id: u64,
+    subdomain: String,
+    university_name: String,
+    admin_principal: Option<Principal>,
+) -> LMSResult<TenantRestore> {
+    crate::auth::require_operator()?;
+    crate::backup::restore_tenant_backup(backup_id, subdomain, university_name, admin_principal).await
+}
+
+/// Get a backup restore by ID
+#[query]
+#[candid_method(query)]
+fn get_tenant_restore(restore_id: u64) -> LMSResult<TenantRestore> {
+    crate::auth::require_support()?;
+    crate::backup::get_tenant_restore(restore_id)
+}
+
+/// List backup restores, newest first
+#[query]
+#[candid_method(query)]
+fn list_tenant_restores() -> LMSResult<Vec<TenantRestore>> {
+    crate::auth::require_support()?;
+    Ok(crate::backup::list_tenant_restores())
 }
 
 /// Legacy function for compatibility with existing tests
@@ -41,6 +303,7 @@ async fn register_tenant(
     domain: String,
     canister_id: String,
 ) -> LMSResult<Tenant> {
+    crate::auth::require_operator()?;
     crate::tenant_management::register_tenant(id, name, domain, canister_id).await
 }
 
@@ -51,11 +314,19 @@ fn get_tenant_canister(subdomain: String) -> LMSResult<Principal> {
     crate::tenant_management::get_tenant_canister(subdomain)
 }
 
-/// List all registered tenants
+/// List all registered tenants with their full records (operators only; see `list_tenant_directory`)
 #[query]
 #[candid_method(query)]
-fn list_tenants() -> Vec<Tenant> {
-    crate::tenant_management::list_tenants()
+fn list_tenants() -> LMSResult<Vec<Tenant>> {
+    crate::auth::require_operator()?;
+    Ok(crate::tenant_management::list_tenants())
+}
+
+/// Public, paginated directory of tenants without admin principals or settings
+#[query]
+#[candid_method(query)]
+fn list_tenant_directory(query: TenantDirectoryQuery) -> TenantDirectoryPage {
+    crate::directory::list_tenant_directory(query)
 }
 
 /// Get routing table (for debugging)
@@ -129,6 +400,112 @@ fn get_cycle_info() -> CycleInfo {
     }
 }
 
+/// Get a tenant's request metrics (`None` for requests that did not resolve to a tenant)
+#[query]
+#[candid_method(query)]
+fn get_tenant_metrics(tenant_id: Option<String>, granularity: MetricsGranularity, from: Option<u64>, to: Option<u64>) -> Vec<MetricsBucket> {
+    crate::metrics::get_tenant_metrics(tenant_id, granularity, from, to)
+}
+
+/// Get per-tenant request totals since the given time
+#[query]
+#[candid_method(query)]
+fn get_request_totals(since: u64) -> Vec<TenantRequestTotals> {
+    crate::metrics::get_request_totals(since)
+}
+
+/// Get the tenant cycles monitor configuration
+#[query]
+#[candid_method(query)]
+fn get_cycles_monitor_config() -> CyclesMonitorConfig {
+    crate::cycles::get_cycles_monitor_config()
+}
+
+/// Configure the tenant cycles monitor thresholds and schedule
+#[update]
+#[candid_method(update)]
+fn configure_cycles_monitor(config: CyclesMonitorConfig) -> LMSResult<CyclesMonitorConfig> {
+    crate::auth::require_operator()?;
+    crate::cycles::configure_cycles_monitor(config)
+}
+
+/// Check all tenant balances now and top up those below the threshold
+#[update]
+#[candid_method(update)]
+async fn run_cycles_check() -> LMSResult<Vec<TenantCyclesStatus>> {
+    crate::auth::require_operator()?;
+    Ok(crate::cycles::check_all_tenants().await)
+}
+
+/// Get the cycles status and top-up history of a tenant
+#[query]
+#[candid_method(query)]
+fn get_tenant_cycles(tenant_id: String) -> LMSResult<TenantCyclesStatus> {
+    crate::cycles::get_tenant_cycles(tenant_id)
+}
+
+/// List the cycles status of all monitored tenants
+#[query]
+#[candid_method(query)]
+fn list_tenant_cycles() -> Vec<TenantCyclesStatus> {
+    crate::cycles::list_tenant_cycles()
+}
+
+/// List low-balance alerts
+#[query]
+#[candid_method(query)]
+fn list_cycles_alerts(include_acknowledged: bool) -> Vec<CyclesAlert> {
+    crate::cycles::list_cycles_alerts(include_acknowledged)
+}
+
+/// Acknowledge a low-balance alert
+#[update]
+#[candid_method(update)]
+fn acknowledge_cycles_alert(alert_id: u64) -> LMSResult<()> {
+    crate::auth::require_support()?;
+    crate::cycles::acknowledge_cycles_alert(alert_id)
+}
+
+/// Get the tenant health monitor configuration
+#[query]
+#[candid_method(query)]
+fn get_health_monitor_config() -> HealthMonitorConfig {
+    crate::health::get_health_monitor_config()
+}
+
+/// Configure how often tenants are probed and after how many failures they are unhealthy
+#[update]
+#[candid_method(update)]
+fn configure_health_monitor(config: HealthMonitorConfig) -> LMSResult<HealthMonitorConfig> {
+    crate::auth::require_operator()?;
+    crate::health::configure_health_monitor(config)
+}
+
+/// Probe every tenant canister now
+#[update]
+#[candid_method(update)]
+async fn run_health_check() -> LMSResult<Vec<TenantHealth>> {
+    crate::auth::require_support()?;
+    Ok(crate::health::check_all_tenants().await)
+}
+
+/// Get the latest health check of a tenant
+#[query]
+#[candid_method(query)]
+fn get_tenant_health(tenant_id: String) -> LMSResult<TenantHealth> {
+    crate::auth::require_support()?;
+    crate::health::get_tenant_health(tenant_id)
+}
+
+/// Health counts and every tenant's latest check, unhealthy tenants first.
+/// The public `/status` page shows a redacted view of the same data.
+#[query]
+#[candid_method(query)]
+fn get_health_dashboard() -> LMSResult<HealthDashboard> {
+    crate::auth::require_support()?;
+    Ok(crate::health::get_health_dashboard())
+}
+
 /// Get template configuration
 #[query]
 #[candid_method(query)]
@@ -140,9 +517,108 @@ fn get_template_config() -> TemplateConfig {
 #[update]
 #[candid_method(update)]
 fn auto_configure_template() -> LMSResult<String> {
+    crate::auth::require_operator()?;
     crate::template::auto_configure_template()
 }
 
+/// Start a chunked upload of a tenant WASM module version
+#[update]
+#[candid_method(update)]
+fn begin_wasm_upload(version: String, sha256: String, total_size: u64) -> LMSResult<WasmModuleInfo> {
+    crate::auth::require_operator()?;
+    crate::wasm_registry::begin_wasm_upload(version, sha256, total_size)
+}
+
+/// Upload the next chunk of a tenant WASM module
+#[update]
+#[candid_method(update)]
+fn upload_wasm_chunk(version: String, chunk_index: u32, data: Vec<u8>) -> LMSResult<WasmModuleInfo> {
+    crate::auth::require_operator()?;
+    crate::wasm_registry::upload_wasm_chunk(version, chunk_index, data)
+}
+
+/// Verify and store a fully uploaded tenant WASM module
+#[update]
+#[candid_method(update)]
+fn finalize_wasm_upload(version: String) -> LMSResult<WasmModuleInfo> {
+    crate::auth::require_operator()?;
+    crate::wasm_registry::finalize_wasm_upload(version)
+}
+
+/// Make an uploaded tenant WASM version the one used for provisioning and upgrades
+#[update]
+#[candid_method(update)]
+fn activate_wasm_version(version: String) -> LMSResult<WasmModuleInfo> {
+    crate::auth::require_operator()?;
+    crate::wasm_registry::activate_wasm_version(version)
+}
+
+/// Point the beta channel at an uploaded tenant WASM version, or clear it to follow stable
+#[update]
+#[candid_method(update)]
+fn set_beta_version(version: Option<String>) -> LMSResult<Option<String>> {
+    crate::auth::require_operator()?;
+    crate::wasm_registry::set_beta_version(version)
+}
+
+/// Move a tenant to a release channel or pin it to a version, upgrading its canister if needed
+#[update]
+#[candid_method(update)]
+async fn set_tenant_template_track(tenant_id: String, track: TemplateTrack) -> LMSResult<Tenant> {
+    crate::auth::require_operator()?;
+    crate::template::set_tenant_template_track(tenant_id, track).await
+}
+
+/// List uploaded tenant WASM versions
+#[query]
+#[candid_method(query)]
+fn list_wasm_versions() -> Vec<WasmModuleInfo> {
+    crate::wasm_registry::list_wasm_versions()
+}
+
+/// Roll a channel's current tenant WASM out to the tenants following it (stable by default)
+#[update]
+#[candid_method(update)]
+async fn start_fleet_upgrade(
+    canary_count: u32,
+    batch_size: u32,
+    halt_on_error: bool,
+    channel: Option<ReleaseChannel>,
+) -> LMSResult<FleetUpgrade> {
+    crate::auth::require_operator()?;
+    crate::upgrade::start_fleet_upgrade(canary_count, batch_size, halt_on_error, channel).await
+}
+
+/// Stop a running fleet upgrade, such as one interrupted by a trap or a router upgrade
+#[update]
+#[candid_method(update)]
+fn abort_fleet_upgrade(upgrade_id: u64) -> LMSResult<FleetUpgrade> {
+    crate::auth::require_operator()?;
+    crate::upgrade::abort_fleet_upgrade(upgrade_id)
+}
+
+/// Roll tenants upgraded by a fleet upgrade back to their previous module
+#[update]
+#[candid_method(update)]
+async fn rollback_fleet_upgrade(upgrade_id: u64) -> LMSResult<FleetUpgrade> {
+    crate::auth::require_operator()?;
+    crate::upgrade::rollback_fleet_upgrade(upgrade_id).await
+}
+
+/// Get a fleet upgrade with its per-tenant records
+#[query]
+#[candid_method(query)]
+fn get_fleet_upgrade(upgrade_id: u64) -> LMSResult<FleetUpgrade> {
+    crate::upgrade::get_fleet_upgrade(upgrade_id)
+}
+
+/// List all fleet upgrades
+#[query]
+#[candid_method(query)]
+fn list_fleet_upgrades() -> Vec<FleetUpgrade> {
+    crate::upgrade::list_fleet_upgrades()
+}
+
 /// Verify if caller is a controller of a tenant canister
 #[query]
 #[candid_method(query)]
@@ -153,8 +629,9 @@ async fn verify_controller_access(tenant_id: String) -> LMSResult<bool> {
 /// Comprehensive inspection of tenant registry
 #[query]
 #[candid_method(query)]
-fn inspect_tenant_registry() -> TenantRegistryInspection {
-    crate::inspection::inspect_tenant_registry()
+fn inspect_tenant_registry() -> LMSResult<TenantRegistryInspection> {
+    crate::auth::require_operator()?;
+    Ok(crate::inspection::inspect_tenant_registry())
 }
 
 /// Comprehensive inspection of routing table
@@ -167,58 +644,377 @@ fn inspect_routing_table() -> RoutingTableInspection {
 /// Full system inspection combining both registries
 #[query]
 #[candid_method(query)]
-fn inspect_full_system() -> FullSystemInspection {
-    crate::inspection::inspect_full_system()
+fn inspect_full_system() -> LMSResult<FullSystemInspection> {
+    crate::auth::require_operator()?;
+    Ok(crate::inspection::inspect_full_system())
+}
+
+/// Propose (dry run) or apply fixes for inconsistencies between the registry, routes and canisters
+#[update]
+#[candid_method(update)]
+async fn repair_system(apply: bool) -> LMSResult<RepairRun> {
+    if apply {
+        crate::auth::require_operator()?;
+    } else {
+        crate::auth::require_support()?;
+    }
+    crate::repair::repair_system(apply).await
+}
+
+/// Get a recorded repair run
+#[query]
+#[candid_method(query)]
+fn get_repair_run(run_id: u64) -> LMSResult<RepairRun> {
+    crate::repair::get_repair_run(run_id)
+}
+
+/// List recorded repair runs, newest first
+#[query]
+#[candid_method(query)]
+fn list_repair_runs() -> Vec<RepairRun> {
+    crate::repair::list_repair_runs()
+}
+
+/// Collect usage summaries from every active tenant now instead of waiting for the scheduler
+#[update]
+#[candid_method(update)]
+async fn collect_tenant_summaries() -> LMSResult<SummaryCollection> {
+    crate::auth::require_operator()?;
+    Ok(crate::analytics::collect_tenant_summaries().await)
+}
+
+/// Get a tenant's usage summaries over time
+#[query]
+#[candid_method(query)]
+fn get_tenant_analytics(tenant_id: String, from: Option<u64>, to: Option<u64>) -> LMSResult<Vec<TenantSummarySample>> {
+    crate::auth::require_support()?;
+    crate::analytics::get_tenant_analytics(tenant_id, from, to)
+}
+
+/// Get the latest usage summary of every tenant
+#[query]
+#[candid_method(query)]
+fn get_latest_tenant_summaries() -> LMSResult<Vec<TenantSummarySample>> {
+    crate::auth::require_support()?;
+    crate::analytics::get_latest_tenant_summaries()
+}
+
+/// Get platform-wide usage totals over time
+#[query]
+#[candid_method(query)]
+fn get_platform_analytics(from: Option<u64>, to: Option<u64>) -> LMSResult<Vec<PlatformSummary>> {
+    crate::auth::require_support()?;
+    crate::analytics::get_platform_analytics(from, to)
+}
+
+/// Get the prices applied to new billing statements
+#[query]
+#[candid_method(query)]
+fn get_billing_prices() -> LMSResult<PriceTable> {
+    crate::auth::require_support()?;
+    Ok(crate::billing::get_billing_prices())
+}
+
+/// Replace the prices applied to new billing statements
+#[update]
+#[candid_method(update)]
+fn set_billing_prices(prices: PriceTable) -> LMSResult<PriceTable> {
+    crate::auth::require_operator()?;
+    crate::billing::set_billing_prices(prices)
+}
+
+/// Get a tenant's metered usage for a month ("YYYY-MM", the current month by default)
+#[query]
+#[candid_method(query)]
+fn get_tenant_usage(tenant_id: String, period: Option<String>) -> LMSResult<UsageMeter> {
+    crate::auth::require_support()?;
+    crate::billing::get_tenant_usage(tenant_id, period)
+}
+
+/// Issue any missing statements for a closed month
+#[update]
+#[candid_method(update)]
+fn generate_statements(period: String) -> LMSResult<Vec<BillingStatement>> {
+    crate::auth::require_operator()?;
+    crate::billing::generate_statements(period)
+}
+
+/// Get a billing statement by ID
+#[query]
+#[candid_method(query)]
+fn get_statement(statement_id: String) -> LMSResult<BillingStatement> {
+    crate::auth::require_support()?;
+    crate::billing::get_statement(statement_id)
+}
+
+/// List billing statements, newest month first
+#[query]
+#[candid_method(query)]
+fn list_statements(query: StatementQuery) -> LMSResult<Vec<BillingStatement>> {
+    crate::auth::require_support()?;
+    crate::billing::list_statements(query)
+}
+
+/// Export billing statements as CSV or JSON
+#[query]
+#[candid_method(query)]
+fn export_statements(query: StatementQuery, format: StatementFormat) -> LMSResult<String> {
+    crate::auth::require_support()?;
+    crate::billing::export_statements(query, format)
+}
+
+/// Record the payment of a billing statement
+#[update]
+#[candid_method(update)]
+fn mark_statement_paid(statement_id: String, reference: Option<String>) -> LMSResult<BillingStatement> {
+    crate::auth::require_operator()?;
+    crate::billing::mark_statement_paid(statement_id, reference)
 }
 
 /// Log tenant registry to console (for IC replica logs)
 #[update]
 #[candid_method(update)]
-fn log_tenant_registry() -> String {
-    crate::inspection::log_tenant_registry()
+fn log_tenant_registry() -> LMSResult<String> {
+    crate::auth::require_operator()?;
+    Ok(crate::inspection::log_tenant_registry())
 }
 
 /// Log routing table to console (for IC replica logs)
 #[update]
 #[candid_method(update)]
-fn log_routing_table() -> String {
-    crate::inspection::log_routing_table()
+fn log_routing_table() -> LMSResult<String> {
+    crate::auth::require_support()?;
+    Ok(crate::inspection::log_routing_table())
 }
 
 /// Log full system state to console
 #[update]
 #[candid_method(update)]
-fn log_full_system() -> String {
-    crate::inspection::log_full_system()
+fn log_full_system() -> LMSResult<String> {
+    crate::auth::require_support()?;
+    Ok(crate::inspection::log_full_system())
 }
 
 /// Remove a tenant and its routing entry (admin function)
 #[update]
 #[candid_method(update)]
 fn remove_tenant(tenant_id: String) -> LMSResult<()> {
+    crate::auth::require_operator()?;
     crate::tenant_management::remove_tenant(tenant_id)
 }
 
+/// Suspend a tenant: stop its canister and serve a maintenance response
+#[update]
+#[candid_method(update)]
+async fn suspend_tenant(tenant_id: String, reason: String) -> LMSResult<Tenant> {
+    crate::auth::require_operator()?;
+    crate::lifecycle::suspend_tenant(tenant_id, reason).await
+}
+
+/// Reactivate a suspended tenant
+#[update]
+#[candid_method(update)]
+async fn reactivate_tenant(tenant_id: String) -> LMSResult<Tenant> {
+    crate::auth::require_operator()?;
+    crate::lifecycle::reactivate_tenant(tenant_id).await
+}
+
+/// Get the latest suspension record of a tenant
+#[query]
+#[candid_method(query)]
+fn get_tenant_suspension(tenant_id: String) -> LMSResult<TenantSuspension> {
+    crate::lifecycle::get_tenant_suspension(tenant_id)
+}
+
+/// Decommission a tenant: back up its data and take it offline; the canister is deleted after the grace period
+#[update]
+#[candid_method(update)]
+async fn schedule_decommission(tenant_id: String, grace_period_days: Option<u32>) -> LMSResult<TenantDecommission> {
+    crate::auth::require_operator()?;
+    crate::decommission::schedule_decommission(tenant_id, grace_period_days).await
+}
+
+/// Cancel a scheduled decommission during its grace period
+#[update]
+#[candid_method(update)]
+async fn cancel_decommission(tenant_id: String) -> LMSResult<TenantDecommission> {
+    crate::auth::require_operator()?;
+    crate::decommission::cancel_decommission(tenant_id).await
+}
+
+/// Withdraw a decommissioned tenant's cycles and delete its canister
+#[update]
+#[candid_method(update)]
+async fn complete_decommission(tenant_id: String, force: bool) -> LMSResult<TenantDecommission> {
+    crate::auth::require_operator()?;
+    crate::decommission::complete_decommission(tenant_id, force).await
+}
+
+/// Get the decommission record of a tenant
+#[query]
+#[candid_method(query)]
+fn get_decommission(tenant_id: String) -> LMSResult<TenantDecommission> {
+    crate::auth::require_support()?;
+    crate::decommission::get_decommission(tenant_id)
+}
+
+/// List all decommission records, including tombstones
+#[query]
+#[candid_method(query)]
+fn list_decommissions() -> LMSResult<Vec<TenantDecommission>> {
+    crate::auth::require_support()?;
+    Ok(crate::decommission::list_decommissions())
+}
+
+/// Get the candid-encoded data export of a tenant decommissioned before backups were used
+#[query]
+#[candid_method(query)]
+fn get_decommission_export(tenant_id: String) -> LMSResult<Vec<u8>> {
+    crate::auth::require_operator()?;
+    crate::decommission::get_decommission_export(tenant_id)
+}
+
+/// Accept cycles returned by a tenant canister being decommissioned
+#[update]
+#[candid_method(update)]
+fn receive_cycles() -> u128 {
+    let available = ic_cdk::api::call::msg_cycles_available128();
+    ic_cdk::api::call::msg_cycles_accept128(available)
+}
+
+/// Get the reserved names and blocked words applied to new subdomains
+#[query]
+#[candid_method(query)]
+fn get_subdomain_policy() -> SubdomainPolicy {
+    crate::subdomains::get_subdomain_policy()
+}
+
+/// Replace the reserved names and blocked words applied to new subdomains
+#[update]
+#[candid_method(update)]
+fn configure_subdomain_policy(policy: SubdomainPolicy) -> LMSResult<SubdomainPolicy> {
+    crate::auth::require_operator()?;
+    crate::subdomains::configure_subdomain_policy(policy)
+}
+
+/// Check whether a subdomain could be registered, returning the reason if not
+#[query]
+#[candid_method(query)]
+fn check_subdomain_available(subdomain: String) -> LMSResult<()> {
+    crate::subdomains::check_subdomain_available(subdomain)
+}
+
+/// Rename a tenant's subdomain, keeping the old one as a redirecting alias for `alias_days`
+#[update]
+#[candid_method(update)]
+fn rename_subdomain(tenant_id: String, new_subdomain: String, alias_days: Option<u32>) -> LMSResult<Tenant> {
+    crate::auth::require_operator()?;
+    crate::subdomains::rename_subdomain(tenant_id, new_subdomain, alias_days)
+}
+
+/// List subdomain aliases, optionally for a single tenant
+#[query]
+#[candid_method(query)]
+fn list_subdomain_aliases(tenant_id: Option<String>) -> Vec<SubdomainAlias> {
+    crate::subdomains::list_subdomain_aliases(tenant_id)
+}
+
+/// Release a subdomain alias before it expires
+#[update]
+#[candid_method(update)]
+fn remove_subdomain_alias(subdomain: String) -> LMSResult<()> {
+    crate::auth::require_operator()?;
+    crate::subdomains::remove_subdomain_alias(subdomain)
+}
+
+/// Get the platform base domains used for subdomain routing
+#[query]
+#[candid_method(query)]
+fn get_base_domains() -> Vec<BaseDomain> {
+    crate::domains::get_base_domains()
+}
+
+/// Replace the platform base domains used for subdomain routing
+#[update]
+#[candid_method(update)]
+fn configure_base_domains(domains: Vec<BaseDomain>) -> LMSResult<Vec<BaseDomain>> {
+    crate::auth::require_operator()?;
+    crate::domains::configure_base_domains(domains)
+}
+
+/// Claim a custom hostname for a tenant and get its verification token
+#[update]
+#[candid_method(update)]
+async fn add_custom_domain(tenant_id: String, hostname: String) -> LMSResult<CustomDomain> {
+    crate::auth::require_operator()?;
+    crate::domains::add_custom_domain(tenant_id, hostname).await
+}
+
+/// Check that a custom domain serves its verification token and start routing it
+#[update]
+#[candid_method(update)]
+async fn verify_custom_domain(hostname: String) -> LMSResult<CustomDomain> {
+    crate::auth::require_support()?;
+    crate::domains::verify_custom_domain(hostname).await
+}
+
+/// Remove a custom domain mapping
+#[update]
+#[candid_method(update)]
+fn remove_custom_domain(hostname: String) -> LMSResult<()> {
+    crate::auth::require_operator()?;
+    crate::domains::remove_custom_domain(hostname)
+}
+
+/// Get a custom domain mapping
+#[query]
+#[candid_method(query)]
+fn get_custom_domain(hostname: String) -> LMSResult<CustomDomain> {
+    crate::domains::get_custom_domain(hostname)
+}
+
+/// List custom domains, optionally for a single tenant
+#[query]
+#[candid_method(query)]
+fn list_custom_domains(tenant_id: Option<String>) -> Vec<CustomDomain> {
+    crate::domains::list_custom_domains(tenant_id)
+}
+
+/// Transform for domain verification HTTPS outcalls (not part of the public interface)
+#[query]
+fn transform_domain_challenge(args: TransformArgs) -> OutcallResponse {
+    crate::domains::transform_domain_challenge(args)
+}
+
 /// Clear all tenant data (admin function - use with caution!)
 #[update]
 #[candid_method(update)]
-fn clear_all_tenants() -> String {
-    crate::tenant_management::clear_all_tenants()
+fn clear_all_tenants() -> LMSResult<String> {
+    crate::auth::require_operator()?;
+    Ok(crate::tenant_management::clear_all_tenants())
+}
+
+/// Schema version of each stable store and progress of its latest migration
+#[query]
+#[candid_method(query)]
+fn get_schema_status() -> LMSResult<SchemaState> {
+    crate::auth::require_support()?;
+    Ok(crate::schema::get_schema_status())
 }
 
-/// Migrate tenant data to new format (development function)
+/// Continue unfinished schema migrations now instead of waiting for the scheduler
 #[update]
 #[candid_method(update)]
-pub fn migrate_tenant_data() -> LMSResult<String> {
-    // For now, just return a placeholder response
-    // In a real implementation, this would handle data migration
-    Ok("Migration functionality not yet implemented".to_string())
+fn run_schema_migration() -> LMSResult<SchemaState> {
+    crate::auth::require_operator()?;
+    Ok(crate::schema::continue_migrations())
 }
 
 /// Clear all tenant data (nuclear option for testing)
 #[update]
 #[candid_method(update)]
 pub fn clear_tenant_registry() -> LMSResult<String> {
+    crate::auth::require_operator()?;
     use crate::storage::with_tenant_registry;
     
     with_tenant_registry(|registry| {
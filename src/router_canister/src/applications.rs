@@ -0,0 +1,296 @@
+use candid::Principal;
+use ic_cdk::caller;
+use shared::{Tenant, LMSError, LMSResult, current_time};
+use shared::utils::is_valid_email;
+use crate::types::{
+    ApplicationStatus, ProvisioningStatus, TenantApplication, TenantApplicationRequest
+};
+use crate::storage::with_tenant_applications;
+use crate::subdomains::{is_subdomain_taken, validate_subdomain};
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+// How long an application waits for a decision before it lapses
+const REVIEW_DAYS: u64 = 14;
+const MAX_NAME_LENGTH: usize = 100;
+const MAX_EMAIL_LENGTH: usize = 254;
+const MAX_REASON_LENGTH: usize = 500;
+pub const EXPIRY_INTERVAL_SECONDS: u64 = 60 * 60;
+
+/// Submit a signup application; the admin defaults to the submitting principal.
+/// Signup is public and costs nothing, so a pending application does not hold its subdomain:
+/// availability is checked again when an operator approves it.
+pub fn submit_tenant_application(request: TenantApplicationRequest) -> LMSResult<TenantApplication> {
+    let submitter = caller();
+    if submitter == Principal::anonymous() {
+        return Err(LMSError::AccessDenied("Sign in before applying for a tenant".to_string()));
+    }
+
+    let request = normalize_request(request);
+    validate_request(&request).map_err(LMSError::ValidationError)?;
+    validate_subdomain(&request.subdomain)?;
+    if is_subdomain_taken(&request.subdomain) {
+        return Err(LMSError::AlreadyExists(format!("Subdomain '{}' is already in use", request.subdomain)));
+    }
+
+    let now = current_time();
+    let own_pending = list_applications(|application| application.submitted_by == submitter && is_pending(application, now));
+    if !own_pending.is_empty() {
+        return Err(LMSError::AlreadyExists("You already have a pending application".to_string()));
+    }
+
+    let application = TenantApplication {
+        id: next_application_id(),
+        university_name: request.university_name,
+        subdomain: request.subdomain,
+        contact_name: request.contact_name,
+        contact_email: request.contact_email,
+        admin_principal: request.admin_principal.unwrap_or(submitter),
+        submitted_by: submitter,
+        submitted_at: now,
+        reserved_until: now + REVIEW_DAYS * NANOS_PER_DAY,
+        status: ApplicationStatus::Pending,
+        decided_by: None,
+        decided_at: None,
+        provisioning_id: None,
+        tenant_id: None,
+    };
+    save_application(&application);
+
+    ic_cdk::println!("Tenant application {} submitted for {} by {}", application.id, application.subdomain, submitter);
+    Ok(application)
+}
+
+/// Approve a pending application and provision its tenant. A provisioning that fails part-way
+/// keeps the application approved; it is then resumed or cancelled like any other provisioning.
+pub async fn approve_tenant_application(application_id: u64) -> LMSResult<Tenant> {
+    let mut application = get_application(application_id)?;
+    let now = current_time();
+    require_pending(&application, now)?;
+
+    // Applications do not hold their subdomain, so it may have been taken since submission
+    if is_subdomain_taken(&application.subdomain) {
+        return Err(LMSError::AlreadyExists(format!("Subdomain '{}' is already in use", application.subdomain)));
+    }
+
+    let operator = caller();
+    application.status = ApplicationStatus::Approved;
+    application.decided_by = Some(operator);
+    application.decided_at = Some(now);
+    save_application(&application);
+
+    let provisioning = crate::provisioning::create_provisioning(
+        application.subdomain.clone(),
+        application.university_name.clone(),
+        application.admin_principal,
+        None,
+        None,
+    );
+    let provisioning = match provisioning {
+        Ok(provisioning) => provisioning,
+        Err(e) => {
+            application.status = ApplicationStatus::Pending;
+            application.decided_by = None;
+            application.decided_at = None;
+            save_application(&application);
+            return Err(e);
+        }
+    };
+
+    application.provisioning_id = Some(provisioning.request_id);
+    save_application(&application);
+    ic_cdk::println!("Tenant application {} approved by {} (provisioning {})",
+                     application_id, operator, provisioning.request_id);
+
+    let tenant = crate::provisioning::resume_provisioning(provisioning.request_id).await?;
+    let mut application = get_application(application_id)?;
+    application.tenant_id = Some(tenant.id.clone());
+    save_application(&application);
+    Ok(tenant)
+}
+
+/// Reject a pending application
+pub fn reject_tenant_application(application_id: u64, reason: String) -> LMSResult<TenantApplication> {
+    let reason = reason.trim().to_string();
+    if reason.is_empty() || reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(LMSError::ValidationError(format!(
+            "Reason must be between 1 and {} characters", MAX_REASON_LENGTH
+        )));
+    }
+
+    let mut application = get_application(application_id)?;
+    let now = current_time();
+    require_pending(&application, now)?;
+
+    application.status = ApplicationStatus::Rejected(reason);
+    application.decided_by = Some(caller());
+    application.decided_at = Some(now);
+    save_application(&application);
+
+    ic_cdk::println!("Tenant application {} rejected by {}", application_id, caller());
+    Ok(application)
+}
+
+/// Get an application; visible to its submitter, its designated admin and platform staff
+pub fn get_tenant_application(application_id: u64) -> LMSResult<TenantApplication> {
+    let application = get_application(application_id)?;
+    let viewer = caller();
+    if viewer != application.submitted_by
+        && viewer != application.admin_principal
+        && crate::auth::role_of(&viewer).is_none()
+    {
+        return Err(LMSError::AccessDenied("Not allowed to view this application".to_string()));
+    }
+    Ok(with_tenant_id(application))
+}
+
+/// Applications submitted by, or naming as admin, the caller
+pub fn list_my_tenant_applications() -> Vec<TenantApplication> {
+    let viewer = caller();
+    list_applications(|application| application.submitted_by == viewer || application.admin_principal == viewer)
+        .into_iter()
+        .map(with_tenant_id)
+        .collect()
+}
+
+/// List applications for review, optionally only those still awaiting a decision
+pub fn list_tenant_applications(pending_only: bool) -> Vec<TenantApplication> {
+    let now = current_time();
+    list_applications(|application| !pending_only || is_pending(application, now))
+        .into_iter()
+        .map(with_tenant_id)
+        .collect()
+}
+
+/// Background job entry point used by the scheduler
+pub async fn expire_stale_applications() {
+    let now = current_time();
+    let expired = list_applications(|application| {
+        application.status == ApplicationStatus::Pending && application.reserved_until <= now
+    });
+    if expired.is_empty() {
+        return;
+    }
+
+    for mut application in expired.iter().cloned() {
+        application.status = ApplicationStatus::Expired;
+        save_application(&application);
+    }
+    ic_cdk::println!("Expired {} undecided tenant applications", expired.len());
+}
+
+fn normalize_request(request: TenantApplicationRequest) -> TenantApplicationRequest {
+    TenantApplicationRequest {
+        university_name: request.university_name.trim().to_string(),
+        subdomain: request.subdomain.trim().to_lowercase(),
+        contact_name: request.contact_name.trim().to_string(),
+        contact_email: request.contact_email.trim().to_lowercase(),
+        admin_principal: request.admin_principal,
+    }
+}
+
+/// Check the free-text fields of a normalized request
+fn validate_request(request: &TenantApplicationRequest) -> Result<(), String> {
+    for (field, value) in [("University name", &request.university_name), ("Contact name", &request.contact_name)] {
+        if value.is_empty() || value.chars().count() > MAX_NAME_LENGTH {
+            return Err(format!("{} must be between 1 and {} characters", field, MAX_NAME_LENGTH));
+        }
+    }
+    if request.contact_email.len() > MAX_EMAIL_LENGTH || !is_valid_email(&request.contact_email) {
+        return Err("Contact email is not a valid email address".to_string());
+    }
+    if request.admin_principal == Some(Principal::anonymous()) {
+        return Err("The anonymous principal cannot administer a tenant".to_string());
+    }
+    Ok(())
+}
+
+fn require_pending(application: &TenantApplication, now: u64) -> LMSResult<()> {
+    match application.status {
+        ApplicationStatus::Pending if application.reserved_until > now => Ok(()),
+        ApplicationStatus::Pending => Err(LMSError::ValidationError(format!(
+            "Application {} has expired", application.id
+        ))),
+        _ => Err(LMSError::ValidationError(format!(
+            "Application {} has already been decided", application.id
+        ))),
+    }
+}
+
+fn is_pending(application: &TenantApplication, now: u64) -> bool {
+    application.status == ApplicationStatus::Pending && application.reserved_until > now
+}
+
+// A provisioning resumed outside the approval call completes without updating the application
+fn with_tenant_id(mut application: TenantApplication) -> TenantApplication {
+    if application.tenant_id.is_none() {
+        if let Some(provisioning) = application.provisioning_id
+            .and_then(|id| crate::provisioning::get_provisioning(id).ok())
+            .filter(|provisioning| provisioning.status == ProvisioningStatus::Completed)
+        {
+            application.tenant_id = Some(provisioning.tenant_id);
+        }
+    }
+    application
+}
+
+fn get_application(application_id: u64) -> LMSResult<TenantApplication> {
+    with_tenant_applications(|applications| {
+        applications.borrow()
+            .get(&application_id)
+            .ok_or_else(|| LMSError::NotFound(format!("Application {} not found", application_id)))
+    })
+}
+
+fn list_applications(filter: impl Fn(&TenantApplication) -> bool) -> Vec<TenantApplication> {
+    with_tenant_applications(|applications| {
+        applications.borrow()
+            .iter()
+            .map(|(_, application)| application)
+            .filter(|application| filter(application))
+            .collect()
+    })
+}
+
+fn save_application(application: &TenantApplication) {
+    with_tenant_applications(|applications| {
+        applications.borrow_mut().insert(application.id, application.clone());
+    });
+}
+
+fn next_application_id() -> u64 {
+    with_tenant_applications(|applications| {
+        applications.borrow().last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> TenantApplicationRequest {
+        TenantApplicationRequest {
+            university_name: "  Example University ".to_string(),
+            subdomain: " Example ".to_string(),
+            contact_name: "Ada Lovelace".to_string(),
+            contact_email: "Ada@Example.edu ".to_string(),
+            admin_principal: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_request() {
+        let normalized = normalize_request(request());
+        assert_eq!(normalized.subdomain, "example");
+        assert_eq!(normalized.contact_email, "ada@example.edu");
+        assert!(validate_request(&normalized).is_ok());
+
+        let blank_name = TenantApplicationRequest { university_name: "   ".to_string(), ..request() };
+        assert!(validate_request(&normalize_request(blank_name)).is_err());
+
+        let bad_email = TenantApplicationRequest { contact_email: "nobody".to_string(), ..request() };
+        assert!(validate_request(&normalize_request(bad_email)).is_err());
+
+        let anonymous_admin = TenantApplicationRequest { admin_principal: Some(Principal::anonymous()), ..request() };
+        assert!(validate_request(&normalize_request(anonymous_admin)).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use candid::Principal;
+use ic_cdk::caller;
+use shared::{LMSError, LMSResult, current_time};
+use crate::types::{OperatorRole, PlatformOperator};
+use crate::storage::with_platform_operators;
+
+/// Role of a principal on the router; canister controllers always act as operators
+pub fn role_of(principal: &Principal) -> Option<OperatorRole> {
+    let registered = with_platform_operators(|operators| operators.borrow().get(principal))
+        .map(|operator| operator.role);
+    resolve_role(ic_cdk::api::is_controller(principal), registered)
+}
+
+/// Guard for provisioning, upgrade and destructive endpoints
+pub fn require_operator() -> LMSResult<Principal> {
+    let caller_principal = caller();
+    ensure_operator(caller_principal, role_of(&caller_principal))
+}
+
+/// Guard for day-to-day operational endpoints (support staff or operators)
+pub fn require_support() -> LMSResult<Principal> {
+    let caller_principal = caller();
+    ensure_support(caller_principal, role_of(&caller_principal))
+}
+
+fn resolve_role(is_controller: bool, registered: Option<OperatorRole>) -> Option<OperatorRole> {
+    if is_controller {
+        Some(OperatorRole::Operator)
+    } else {
+        registered
+    }
+}
+
+fn ensure_operator(principal: Principal, role: Option<OperatorRole>) -> LMSResult<Principal> {
+    match role {
+        Some(OperatorRole::Operator) => Ok(principal),
+        _ => Err(LMSError::AccessDenied(format!(
+            "Principal {} is not a platform operator", principal
+        ))),
+    }
+}
+
+fn ensure_support(principal: Principal, role: Option<OperatorRole>) -> LMSResult<Principal> {
+    match role {
+        Some(_) => Ok(principal),
+        None => Err(LMSError::AccessDenied(format!(
+            "Principal {} is not platform staff", principal
+        ))),
+    }
+}
+
+/// Guard for managing the operator list itself
+pub fn require_controller() -> LMSResult<Principal> {
+    let caller_principal = caller();
+    if ic_cdk::api::is_controller(&caller_principal) {
+        Ok(caller_principal)
+    } else {
+        Err(LMSError::AccessDenied(
+            "Only router controllers can manage platform operators".to_string()
+        ))
+    }
+}
+
+/// Grant a principal the operator or support role (replaces any existing role)
+pub fn add_platform_operator(principal: Principal, role: OperatorRole) -> LMSResult<PlatformOperator> {
+    if principal == Principal::anonymous() {
+        return Err(LMSError::ValidationError("The anonymous principal cannot be an operator".to_string()));
+    }
+
+    let operator = PlatformOperator {
+        principal,
+        role,
+        added_by: caller(),
+        added_at: current_time(),
+    };
+    with_platform_operators(|operators| {
+        operators.borrow_mut().insert(principal, operator.clone());
+    });
+
+    ic_cdk::println!("Granted {:?} role to {}", role, principal);
+    Ok(operator)
+}
+
+/// Revoke a principal's operator or support role
+pub fn remove_platform_operator(principal: Principal) -> LMSResult<()> {
+    with_platform_operators(|operators| operators.borrow_mut().remove(&principal))
+        .ok_or_else(|| LMSError::NotFound(format!("{} is not a platform operator", principal)))?;
+
+    ic_cdk::println!("Revoked platform role of {}", principal);
+    Ok(())
+}
+
+/// List all explicitly registered operators (controllers are not listed)
+pub fn list_platform_operators() -> Vec<PlatformOperator> {
+    with_platform_operators(|operators| {
+        operators.borrow().iter().map(|(_, operator)| operator).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_of_controllers_and_registered_staff() {
+        assert_eq!(resolve_role(true, None), Some(OperatorRole::Operator));
+        assert_eq!(resolve_role(true, Some(OperatorRole::Support)), Some(OperatorRole::Operator));
+        assert_eq!(resolve_role(false, Some(OperatorRole::Support)), Some(OperatorRole::Support));
+        assert_eq!(resolve_role(false, None), None);
+    }
+
+    #[test]
+    fn test_support_is_denied_operator_endpoints() {
+        let principal = Principal::from_slice(&[1]);
+
+        assert!(matches!(ensure_operator(principal, Some(OperatorRole::Support)), Err(LMSError::AccessDenied(_))));
+        assert_eq!(ensure_support(principal, Some(OperatorRole::Support)).unwrap(), principal);
+        assert_eq!(ensure_operator(principal, Some(OperatorRole::Operator)).unwrap(), principal);
+        assert_eq!(ensure_support(principal, Some(OperatorRole::Operator)).unwrap(), principal);
+    }
+
+    #[test]
+    fn test_unknown_principal_is_denied() {
+        let principal = Principal::from_slice(&[2]);
+
+        assert!(matches!(ensure_operator(principal, None), Err(LMSError::AccessDenied(_))));
+        assert!(matches!(ensure_support(principal, None), Err(LMSError::AccessDenied(_))));
+    }
+}
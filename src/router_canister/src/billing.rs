@@ -0,0 +1,474 @@
+use candid::Principal;
+use ic_cdk::api::management_canister::main::{canister_status, CanisterIdRecord};
+use ic_cdk::caller;
+use shared::{Tenant, LMSError, LMSResult, current_time};
+use crate::types::{
+    BillingStatement, PriceTable, StatementFormat, StatementLine, StatementQuery, StatementStatus, UsageMeter
+};
+use crate::storage::{
+    with_billing_prices, with_billing_statements, with_decommissions, with_tenant_cycles, with_tenant_registry,
+    with_usage_meters
+};
+use crate::cycles::nat_to_u128;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * NANOS_PER_SECOND;
+/// How often the scheduler samples every tenant's usage
+pub const METERING_INTERVAL_SECONDS: u64 = 60 * 60;
+// Longer gaps (e.g. while the router was being upgraded) are not billed as usage
+const MAX_SAMPLE_GAP: u64 = 6 * 60 * 60 * NANOS_PER_SECOND;
+const TRILLION: u128 = 1_000_000_000_000;
+const GIB: u128 = 1 << 30;
+const MAX_REFERENCE_LENGTH: usize = 200;
+
+/// A tenant's readings at one sample
+struct UsageReading {
+    cycles_balance: u128,
+    topped_up: u128,
+    memory_bytes: u64,
+    storage_bytes: u64,
+    active_users: u32,
+}
+
+/// Background job entry point used by the scheduler: sample every tenant, then issue
+/// statements for the previous month once it has closed
+pub async fn run_usage_metering() {
+    let now = current_time();
+    let tenants: Vec<Tenant> = with_tenant_registry(|registry| {
+        registry.borrow().iter().map(|(_, tenant)| tenant).collect()
+    });
+
+    let mut failures = 0;
+    for tenant in &tenants {
+        if let Err(reason) = meter_tenant(tenant, now).await {
+            ic_cdk::println!("Usage metering failed for {}: {}", tenant.id, reason);
+            failures += 1;
+        }
+    }
+    ic_cdk::println!("Metered usage of {} tenants ({} failed)", tenants.len() - failures, failures);
+
+    let issued = issue_statements(&previous_period(&period_of(now)));
+    if !issued.is_empty() {
+        ic_cdk::println!("Issued {} billing statements", issued.len());
+    }
+}
+
+/// Get the prices applied to new statements
+pub fn get_billing_prices() -> PriceTable {
+    with_billing_prices(|prices| prices.borrow().get().clone())
+}
+
+/// Replace the prices applied to new statements; issued statements keep their prices
+pub fn set_billing_prices(prices: PriceTable) -> LMSResult<PriceTable> {
+    let valid_currency = prices.currency.len() == 3 && prices.currency.chars().all(|c| c.is_ascii_uppercase());
+    if !valid_currency {
+        return Err(LMSError::ValidationError("Currency must be a three-letter ISO 4217 code".to_string()));
+    }
+
+    with_billing_prices(|stored| {
+        stored.borrow_mut().set(prices.clone())
+            .map_err(|_| LMSError::InternalError("Failed to store billing prices".to_string()))
+    })?;
+    ic_cdk::println!("Billing prices updated by {}", caller());
+    Ok(prices)
+}
+
+/// A tenant's metered usage for a month, the current one by default
+pub fn get_tenant_usage(tenant_id: String, period: Option<String>) -> LMSResult<UsageMeter> {
+    let period = match period {
+        Some(period) => {
+            parse_period(&period)?;
+            period
+        },
+        None => period_of(current_time()),
+    };
+    with_usage_meters(|meters| meters.borrow().get(&meter_key(&period, &tenant_id)))
+        .ok_or_else(|| LMSError::NotFound(format!("No usage recorded for {} in {}", tenant_id, period)))
+}
+
+/// Issue statements for a closed month. Tenants that already have one are skipped, so this
+/// only fills gaps left by the scheduler.
+pub fn generate_statements(period: String) -> LMSResult<Vec<BillingStatement>> {
+    parse_period(&period)?;
+    if period >= period_of(current_time()) {
+        return Err(LMSError::ValidationError(format!("{} has not closed yet", period)));
+    }
+    Ok(issue_statements(&period))
+}
+
+/// Get a statement by ID
+pub fn get_statement(statement_id: String) -> LMSResult<BillingStatement> {
+    with_billing_statements(|statements| statements.borrow().get(&statement_id))
+        .ok_or_else(|| LMSError::NotFound(format!("Statement {} not found", statement_id)))
+}
+
+/// Statements matching the query, newest month first
+pub fn list_statements(query: StatementQuery) -> LMSResult<Vec<BillingStatement>> {
+    if let Some(period) = &query.period {
+        parse_period(period)?;
+    }
+    let prefix = query.period.as_ref().map(|period| format!("{}#", period)).unwrap_or_default();
+
+    let mut statements: Vec<BillingStatement> = with_billing_statements(|statements| {
+        statements.borrow()
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, statement)| statement)
+            .filter(|statement| query.tenant_id.as_ref().is_none_or(|id| &statement.tenant_id == id))
+            .filter(|statement| !query.unpaid_only || statement.status == StatementStatus::Issued)
+            .collect()
+    });
+    statements.reverse();
+    Ok(statements)
+}
+
+/// Statements matching the query as CSV (one row per statement) or JSON
+pub fn export_statements(query: StatementQuery, format: StatementFormat) -> LMSResult<String> {
+    let statements = list_statements(query)?;
+    match format {
+        StatementFormat::Json => serde_json::to_string_pretty(&statements)
+            .map_err(|e| LMSError::InternalError(format!("Failed to encode statements: {}", e))),
+        StatementFormat::Csv => Ok(statements_csv(&statements)),
+    }
+}
+
+/// Record a payment against an issued statement
+pub fn mark_statement_paid(statement_id: String, reference: Option<String>) -> LMSResult<BillingStatement> {
+    if reference.as_ref().is_some_and(|reference| reference.len() > MAX_REFERENCE_LENGTH) {
+        return Err(LMSError::ValidationError(format!(
+            "Payment reference must be at most {} characters", MAX_REFERENCE_LENGTH
+        )));
+    }
+
+    let mut statement = get_statement(statement_id)?;
+    if statement.status == StatementStatus::Paid {
+        return Err(LMSError::ValidationError(format!("Statement {} is already paid", statement.id)));
+    }
+    statement.status = StatementStatus::Paid;
+    statement.paid_at = Some(current_time());
+    statement.payment_reference = reference;
+    with_billing_statements(|statements| {
+        statements.borrow_mut().insert(statement.id.clone(), statement.clone());
+    });
+
+    ic_cdk::println!("Statement {} marked paid by {}", statement.id, caller());
+    Ok(statement)
+}
+
+async fn meter_tenant(tenant: &Tenant, now: u64) -> Result<(), String> {
+    let canister_id = Principal::from_text(&tenant.canister_id)
+        .map_err(|_| "Invalid canister ID".to_string())?;
+    let (status,) = canister_status(CanisterIdRecord { canister_id }).await
+        .map_err(|(code, msg)| format!("Failed to get canister status: {:?} - {}", code, msg))?;
+
+    // Storage and users come from the latest summary; suspended tenants keep their last one
+    let summary = crate::analytics::latest_tenant_summary(&tenant.id).unwrap_or_default();
+    let reading = UsageReading {
+        cycles_balance: nat_to_u128(&status.cycles),
+        topped_up: with_tenant_cycles(|cycles| cycles.borrow().get(&tenant.id))
+            .map(|cycles| cycles.total_topped_up)
+            .unwrap_or(0),
+        memory_bytes: nat_to_u128(&status.memory_size) as u64,
+        storage_bytes: summary.storage_bytes,
+        active_users: summary.students + summary.instructors + summary.admins,
+    };
+
+    let period = period_of(now);
+    let mut meter = load_meter(&period, &tenant.id);
+    record_sample(&mut meter, &reading, now);
+    with_usage_meters(|meters| {
+        meters.borrow_mut().insert(meter_key(&period, &tenant.id), meter);
+    });
+    Ok(())
+}
+
+/// The tenant's meter for `period`, or a new one that continues from last month's readings
+fn load_meter(period: &str, tenant_id: &str) -> UsageMeter {
+    with_usage_meters(|meters| {
+        let meters = meters.borrow();
+        if let Some(meter) = meters.get(&meter_key(period, tenant_id)) {
+            return meter;
+        }
+        let previous = meters.get(&meter_key(&previous_period(period), tenant_id)).unwrap_or_default();
+        UsageMeter {
+            tenant_id: tenant_id.to_string(),
+            period: period.to_string(),
+            last_cycles_balance: previous.last_cycles_balance,
+            last_topped_up: previous.last_topped_up,
+            last_memory_bytes: previous.last_memory_bytes,
+            last_storage_bytes: previous.last_storage_bytes,
+            last_sampled_at: previous.last_sampled_at,
+            ..UsageMeter::default()
+        }
+    })
+}
+
+/// Add the usage since the previous sample. Memory and storage are billed at the level
+/// they were at during the interval, i.e. the previous reading.
+fn record_sample(meter: &mut UsageMeter, reading: &UsageReading, now: u64) {
+    if meter.last_sampled_at > 0 {
+        // Top-ups raise the balance, so add them back before taking the difference
+        let topped_up = reading.topped_up.saturating_sub(meter.last_topped_up);
+        let burned = (meter.last_cycles_balance + topped_up).saturating_sub(reading.cycles_balance);
+        meter.cycles_burned += burned;
+
+        let elapsed = (now.saturating_sub(meter.last_sampled_at).min(MAX_SAMPLE_GAP) / NANOS_PER_SECOND) as u128;
+        meter.memory_byte_seconds += meter.last_memory_bytes as u128 * elapsed;
+        meter.storage_byte_seconds += meter.last_storage_bytes as u128 * elapsed;
+    }
+
+    meter.peak_active_users = meter.peak_active_users.max(reading.active_users);
+    meter.samples += 1;
+    meter.last_cycles_balance = reading.cycles_balance;
+    meter.last_topped_up = reading.topped_up;
+    meter.last_memory_bytes = reading.memory_bytes;
+    meter.last_storage_bytes = reading.storage_bytes;
+    meter.last_sampled_at = now;
+}
+
+/// Price every unbilled meter of `period` with the current price table
+fn issue_statements(period: &str) -> Vec<BillingStatement> {
+    let Ok((start, end)) = period_bounds(period) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}#", period);
+    let meters: Vec<(String, UsageMeter)> = with_usage_meters(|meters| {
+        meters.borrow()
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .collect()
+    });
+
+    let prices = get_billing_prices();
+    let now = current_time();
+    let mut issued = Vec::new();
+    for (id, meter) in meters {
+        if with_billing_statements(|statements| statements.borrow().contains_key(&id)) {
+            continue;
+        }
+
+        let (lines, total) = price_usage(&meter, &prices, (end - start) / NANOS_PER_SECOND);
+        let statement = BillingStatement {
+            id: id.clone(),
+            tenant_id: meter.tenant_id.clone(),
+            tenant_name: tenant_name(&meter.tenant_id),
+            period: period.to_string(),
+            currency: prices.currency.clone(),
+            lines,
+            total,
+            usage: meter,
+            issued_at: now,
+            status: StatementStatus::Issued,
+            paid_at: None,
+            payment_reference: None,
+        };
+        with_billing_statements(|statements| {
+            statements.borrow_mut().insert(id, statement.clone());
+        });
+        issued.push(statement);
+    }
+    issued
+}
+
+fn price_usage(meter: &UsageMeter, prices: &PriceTable, period_seconds: u64) -> (Vec<StatementLine>, u64) {
+    let gib_month = GIB * period_seconds.max(1) as u128;
+    let lines = vec![
+        StatementLine {
+            description: "Base fee".to_string(),
+            quantity: "1".to_string(),
+            amount: prices.base_fee,
+        },
+        StatementLine {
+            description: "Cycles burned (trillions)".to_string(),
+            quantity: fixed3(meter.cycles_burned, TRILLION),
+            amount: scaled_amount(meter.cycles_burned, prices.per_trillion_cycles, TRILLION),
+        },
+        StatementLine {
+            description: "Canister memory (GiB-months)".to_string(),
+            quantity: fixed3(meter.memory_byte_seconds, gib_month),
+            amount: scaled_amount(meter.memory_byte_seconds, prices.per_gib_memory_month, gib_month),
+        },
+        StatementLine {
+            description: "File storage (GiB-months)".to_string(),
+            quantity: fixed3(meter.storage_byte_seconds, gib_month),
+            amount: scaled_amount(meter.storage_byte_seconds, prices.per_gib_storage_month, gib_month),
+        },
+        StatementLine {
+            description: "Active users (monthly peak)".to_string(),
+            quantity: meter.peak_active_users.to_string(),
+            amount: (meter.peak_active_users as u64).saturating_mul(prices.per_active_user),
+        },
+    ];
+    let total = lines.iter().fold(0u64, |total, line| total.saturating_add(line.amount));
+    (lines, total)
+}
+
+/// `quantity * unit_price / unit`, rounded to the nearest minor unit
+fn scaled_amount(quantity: u128, unit_price: u64, unit: u128) -> u64 {
+    let amount = quantity.saturating_mul(unit_price as u128).saturating_add(unit / 2) / unit;
+    u64::try_from(amount).unwrap_or(u64::MAX)
+}
+
+/// `value / unit` with three decimals
+fn fixed3(value: u128, unit: u128) -> String {
+    let thousandths = value.saturating_mul(1000).saturating_add(unit / 2) / unit;
+    format!("{}.{:03}", thousandths / 1000, thousandths % 1000)
+}
+
+fn statements_csv(statements: &[BillingStatement]) -> String {
+    let mut csv = String::from(
+        "id,tenant_id,tenant_name,period,currency,total,status,issued_at,paid_at,payment_reference,\
+         cycles_burned,memory_byte_seconds,storage_byte_seconds,peak_active_users\n"
+    );
+    for statement in statements {
+        let fields = [
+            statement.id.clone(),
+            statement.tenant_id.clone(),
+            statement.tenant_name.clone(),
+            statement.period.clone(),
+            statement.currency.clone(),
+            statement.total.to_string(),
+            format!("{:?}", statement.status),
+            statement.issued_at.to_string(),
+            statement.paid_at.map(|paid_at| paid_at.to_string()).unwrap_or_default(),
+            statement.payment_reference.clone().unwrap_or_default(),
+            statement.usage.cycles_burned.to_string(),
+            statement.usage.memory_byte_seconds.to_string(),
+            statement.usage.storage_byte_seconds.to_string(),
+            statement.usage.peak_active_users.to_string(),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Decommissioned tenants have left the registry but keep their name on the decommission record
+fn tenant_name(tenant_id: &str) -> String {
+    with_tenant_registry(|registry| registry.borrow().get(&tenant_id.to_string()))
+        .map(|tenant| tenant.name)
+        .or_else(|| {
+            with_decommissions(|records| records.borrow().get(&tenant_id.to_string()))
+                .map(|record| record.tenant.name)
+        })
+        .unwrap_or_else(|| tenant_id.to_string())
+}
+
+fn meter_key(period: &str, tenant_id: &str) -> String {
+    format!("{}#{}", period, tenant_id)
+}
+
+/// Calendar month (UTC) of a timestamp, as "YYYY-MM"
+fn period_of(time: u64) -> String {
+    let (year, month) = civil_month((time / NANOS_PER_DAY) as i64);
+    format!("{:04}-{:02}", year, month)
+}
+
+fn previous_period(period: &str) -> String {
+    match parse_period(period) {
+        Ok((year, 1)) => format!("{:04}-12", year - 1),
+        Ok((year, month)) => format!("{:04}-{:02}", year, month - 1),
+        Err(_) => String::new(),
+    }
+}
+
+/// Start and end of a month in nanoseconds
+fn period_bounds(period: &str) -> LMSResult<(u64, u64)> {
+    let (year, month) = parse_period(period)?;
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    let start = days_from_civil(year, month).max(0) as u64 * NANOS_PER_DAY;
+    let end = days_from_civil(next_year, next_month).max(0) as u64 * NANOS_PER_DAY;
+    Ok((start, end))
+}
+
+fn parse_period(period: &str) -> LMSResult<(i64, u32)> {
+    let invalid = || LMSError::ValidationError(format!("Invalid period '{}', expected YYYY-MM", period));
+    let (year, month) = period.split_once('-').ok_or_else(invalid)?;
+    if year.len() != 4 || month.len() != 2 {
+        return Err(invalid());
+    }
+    let year: i64 = year.parse().map_err(|_| invalid())?;
+    let month: u32 = month.parse().map_err(|_| invalid())?;
+    if !(1..=12).contains(&month) {
+        return Err(invalid());
+    }
+    Ok((year, month))
+}
+
+// Civil calendar conversions for days since the Unix epoch (proleptic Gregorian calendar)
+fn civil_month(days: i64) -> (i64, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month)
+}
+
+fn days_from_civil(year: i64, month: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_periods() {
+        // 2024-02-29T12:00:00Z
+        let leap_day = 1_709_208_000 * NANOS_PER_SECOND;
+        assert_eq!(period_of(leap_day), "2024-02");
+        assert_eq!(previous_period("2024-01"), "2023-12");
+
+        let (start, end) = period_bounds("2024-02").unwrap();
+        assert_eq!(period_of(start), "2024-02");
+        assert_eq!(period_of(end), "2024-03");
+        assert_eq!((end - start) / NANOS_PER_DAY, 29);
+        assert!(parse_period("2024-13").is_err());
+    }
+
+    #[test]
+    fn test_meter_and_price() {
+        let hour = 60 * 60 * NANOS_PER_SECOND;
+        let reading = |cycles_balance, topped_up| UsageReading {
+            cycles_balance,
+            topped_up,
+            memory_bytes: GIB as u64,
+            storage_bytes: 0,
+            active_users: 3,
+        };
+
+        let mut meter = UsageMeter::default();
+        record_sample(&mut meter, &reading(5 * TRILLION, 0), hour);
+        assert_eq!(meter.cycles_burned, 0);
+        // Burned 2T, but a 1T top-up landed in between
+        record_sample(&mut meter, &reading(4 * TRILLION, TRILLION), 2 * hour);
+        assert_eq!(meter.cycles_burned, 2 * TRILLION);
+        assert_eq!(meter.memory_byte_seconds, GIB * 3600);
+
+        let prices = PriceTable { base_fee: 500, per_trillion_cycles: 150, per_gib_memory_month: 720, per_active_user: 10, ..PriceTable::default() };
+        // A 720-hour month holding 1 GiB for one hour is 1/720 of a GiB-month
+        let (lines, total) = price_usage(&meter, &prices, 720 * 3600);
+        assert_eq!(lines[1].quantity, "2.000");
+        assert_eq!(lines[2].amount, 1);
+        assert_eq!(total, 500 + 300 + 1 + 30);
+    }
+}
@@ -1,10 +1,13 @@
 use candid::Principal;
 use ic_cdk::api::management_canister::main::{
     create_canister as mgmt_create_canister, delete_canister as mgmt_delete_canister, install_code,
-    CreateCanisterArgument, CanisterSettings, CanisterIdRecord,
+    canister_status, start_canister as mgmt_start_canister, stop_canister as mgmt_stop_canister,
+    update_settings, CreateCanisterArgument, CanisterSettings, CanisterIdRecord, UpdateSettingsArgument,
     InstallCodeArgument, CanisterInstallMode
 };
 use ic_cdk::{id, caller};
+use sha2::{Digest, Sha256};
+use crate::storage::with_tenant_modules;
 
 // Include the tenant WASM bytes at compile time
 include!(concat!(env!("OUT_DIR"), "/tenant_wasm.rs"));
@@ -49,16 +52,30 @@ pub async fn delete_canister(canister_id: Principal) -> Result<(), String> {
     }
 }
 
+/// Stop a canister so it no longer processes messages
+pub async fn stop_canister(canister_id: Principal) -> Result<(), String> {
+    match mgmt_stop_canister(CanisterIdRecord { canister_id }).await {
+        Ok(_) => Ok(()),
+        Err((code, msg)) => Err(format!("Stop canister failed: {:?} - {}", code, msg)),
+    }
+}
+
+/// Start a previously stopped canister
+pub async fn start_canister(canister_id: Principal) -> Result<(), String> {
+    match mgmt_start_canister(CanisterIdRecord { canister_id }).await {
+        Ok(_) => Ok(()),
+        Err((code, msg)) => Err(format!("Start canister failed: {:?} - {}", code, msg)),
+    }
+}
+
 /// Install canister code from embedded tenant WASM
 pub async fn install_from_template(
     canister_id: Principal,
     _template_canister_id: Principal, // Prefixed with _ to avoid unused warning
+    tenant_wasm: Vec<u8>,
     admin_principal: Principal,
     tenant_id: String,
 ) -> Result<(), String> {
-    // Use the embedded tenant WASM
-    let tenant_wasm = get_tenant_wasm();
-    
     // Log the WASM size for debugging
     ic_cdk::println!("Installing tenant WASM: {} bytes", tenant_wasm.len());
     
@@ -72,7 +89,7 @@ pub async fn install_from_template(
     let install_args = InstallCodeArgument {
         mode: CanisterInstallMode::Install,
         canister_id,
-        wasm_module: tenant_wasm,
+        wasm_module: tenant_wasm.clone(),
         arg: init_args,
     };
     
@@ -80,14 +97,126 @@ pub async fn install_from_template(
         Ok(_) => {
             ic_cdk::println!("Successfully installed tenant WASM on canister {} with tenant_id: {} and admin: {}", 
                            canister_id, tenant_id, admin_principal);
+            // Keep the module so a later fleet upgrade can roll back to it
+            store_tenant_module(&tenant_wasm);
             Ok(())
         },
         Err((code, msg)) => Err(format!("Install template failed: {:?} - {}", code, msg)),
     }
 }
 
-/// Get the tenant WASM bytes (either embedded or fetched from template)
-fn get_tenant_wasm() -> Vec<u8> {
-    // Use the tenant WASM embedded at compile time
-    TENANT_WASM.to_vec()
+/// Upgrade an existing tenant canister to the given WASM module, preserving stable memory
+pub async fn upgrade_canister_code(canister_id: Principal, wasm_module: Vec<u8>) -> Result<(), String> {
+    let upgrade_args = candid::encode_args(())
+        .map_err(|e| format!("Failed to encode upgrade args: {}", e))?;
+    
+    let install_args = InstallCodeArgument {
+        mode: CanisterInstallMode::Upgrade(None),
+        canister_id,
+        wasm_module,
+        arg: upgrade_args,
+    };
+    
+    match install_code(install_args).await {
+        Ok(_) => {
+            ic_cdk::println!("Successfully upgraded tenant canister {}", canister_id);
+            Ok(())
+        },
+        Err((code, msg)) => Err(format!("Upgrade failed: {:?} - {}", code, msg)),
+    }
+}
+
+/// Get the hex-encoded SHA-256 hash of the module installed on a canister
+pub async fn get_module_hash(canister_id: Principal) -> Result<Option<String>, String> {
+    match canister_status(CanisterIdRecord { canister_id }).await {
+        Ok((status,)) => Ok(status.module_hash.map(|hash| hex_encode(&hash))),
+        Err((code, msg)) => Err(format!("Failed to get canister status: {:?} - {}", code, msg)),
+    }
+}
+
+/// Get the current controllers of a canister
+pub async fn get_controllers(canister_id: Principal) -> Result<Vec<Principal>, String> {
+    match canister_status(CanisterIdRecord { canister_id }).await {
+        Ok((status,)) => Ok(status.settings.controllers),
+        Err((code, msg)) => Err(format!("Failed to get canister status: {:?} - {}", code, msg)),
+    }
+}
+
+/// Replace the controllers of a canister, leaving its other settings unchanged
+pub async fn set_controllers(canister_id: Principal, controllers: Vec<Principal>) -> Result<(), String> {
+    let args = UpdateSettingsArgument {
+        canister_id,
+        settings: CanisterSettings {
+            controllers: Some(controllers),
+            ..CanisterSettings::default()
+        },
+    };
+    match update_settings(args).await {
+        Ok(_) => Ok(()),
+        Err((code, msg)) => Err(format!("Update settings failed: {:?} - {}", code, msg)),
+    }
+}
+
+/// Compute the hex-encoded SHA-256 hash of a WASM module (same hash the IC reports)
+pub fn module_hash_hex(wasm_module: &[u8]) -> String {
+    hex_encode(&Sha256::digest(wasm_module))
+}
+
+/// Store a tenant module keyed by its hash and return the hash
+pub fn store_tenant_module(wasm_module: &[u8]) -> String {
+    let hash = module_hash_hex(wasm_module);
+    with_tenant_modules(|modules| {
+        let mut modules = modules.borrow_mut();
+        if !modules.contains_key(&hash) {
+            modules.insert(hash.clone(), wasm_module.to_vec());
+        }
+    });
+    hash
+}
+
+/// Keep the module embedded in this build, so tenants installed from it can be rolled back to it
+/// after a later router build embeds another one
+pub fn store_embedded_module() {
+    if !is_placeholder_module(TENANT_WASM) {
+        store_tenant_module(TENANT_WASM);
+    }
+}
+
+/// Look up a previously stored tenant module by hash
+pub fn get_stored_tenant_module(hash: &str) -> Option<Vec<u8>> {
+    with_tenant_modules(|modules| modules.borrow().get(&hash.to_string()))
+}
+
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Get the tenant WASM bytes: a registry module by hash, or the module embedded at build time.
+/// Refuses placeholder modules and registry modules whose bytes no longer match their hash.
+pub fn get_tenant_module(module_hash: Option<&str>) -> Result<Vec<u8>, String> {
+    let tenant_wasm = match module_hash {
+        Some(hash) => {
+            let module = get_stored_tenant_module(hash)
+                .ok_or_else(|| format!("Tenant module {} is not stored", hash))?;
+            let actual_hash = module_hash_hex(&module);
+            if actual_hash != hash {
+                return Err(format!("Tenant module hash mismatch: expected {}, got {}", hash, actual_hash));
+            }
+            module
+        },
+        None => TENANT_WASM.to_vec(),
+    };
+    
+    if is_placeholder_module(&tenant_wasm) {
+        return Err("No tenant WASM available: upload and activate a tenant module version".to_string());
+    }
+    Ok(tenant_wasm)
+}
+
+/// Check whether a module is empty or not a (possibly gzipped) WASM binary
+pub fn is_placeholder_module(wasm_module: &[u8]) -> bool {
+    const WASM_MAGIC: &[u8] = b"\0asm";
+    const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+    
+    wasm_module.len() <= 8 || !(wasm_module.starts_with(WASM_MAGIC) || wasm_module.starts_with(GZIP_MAGIC))
 }
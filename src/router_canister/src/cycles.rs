@@ -0,0 +1,207 @@
+use candid::{Nat, Principal};
+use ic_cdk::api::management_canister::main::{canister_status, deposit_cycles, CanisterIdRecord};
+use shared::{LMSError, LMSResult, current_time};
+use crate::types::{CyclesAlert, CyclesMonitorConfig, CyclesTopUp, TenantCyclesStatus};
+use crate::storage::{with_cycles_alerts, with_cycles_monitor_config, with_tenant_cycles, with_tenant_registry};
+
+// Keep only the most recent top-ups per tenant
+const MAX_TOP_UP_HISTORY: usize = 50;
+const MIN_CHECK_INTERVAL_SECONDS: u64 = 60;
+
+/// Get the cycles monitor configuration
+pub fn get_cycles_monitor_config() -> CyclesMonitorConfig {
+    with_cycles_monitor_config(|config| config.borrow().get().clone())
+}
+
+/// Update the cycles monitor configuration
+pub fn configure_cycles_monitor(config: CyclesMonitorConfig) -> LMSResult<CyclesMonitorConfig> {
+    if config.check_interval_seconds < MIN_CHECK_INTERVAL_SECONDS {
+        return Err(LMSError::ValidationError(format!(
+            "Check interval must be at least {} seconds", MIN_CHECK_INTERVAL_SECONDS
+        )));
+    }
+
+    if config.top_up_amount == 0 {
+        return Err(LMSError::ValidationError("Top-up amount must be greater than zero".to_string()));
+    }
+
+    with_cycles_monitor_config(|stored| {
+        stored.borrow_mut().set(config.clone()).map_err(|_| {
+            LMSError::InternalError("Failed to store cycles monitor configuration".to_string())
+        })
+    })?;
+
+    ic_cdk::println!("Cycles monitor configured: min balance {}, top-up {}",
+                     config.min_tenant_balance, config.top_up_amount);
+    Ok(config)
+}
+
+/// Background job entry point used by the scheduler
+pub async fn run_cycles_monitor() {
+    let statuses = check_all_tenants().await;
+    ic_cdk::println!("Cycles monitor checked {} tenants", statuses.len());
+}
+
+/// Check every tenant's balance and top up those below the configured threshold.
+/// Tenants being decommissioned are skipped so they are not topped up before deletion.
+pub async fn check_all_tenants() -> Vec<TenantCyclesStatus> {
+    let config = get_cycles_monitor_config();
+    let tenants: Vec<(String, String)> = with_tenant_registry(|registry| {
+        registry.borrow().iter()
+            .filter(|(id, _)| !crate::decommission::is_decommissioning(id))
+            .map(|(id, tenant)| (id, tenant.canister_id))
+            .collect()
+    });
+
+    let mut statuses = Vec::new();
+    for (tenant_id, canister_id) in tenants {
+        let canister_id = match Principal::from_text(&canister_id) {
+            Ok(principal) => principal,
+            Err(_) => continue,
+        };
+        statuses.push(check_tenant(&config, tenant_id, canister_id).await);
+    }
+    statuses
+}
+
+/// Check a single tenant, top it up if needed and record the outcome
+async fn check_tenant(config: &CyclesMonitorConfig, tenant_id: String, canister_id: Principal) -> TenantCyclesStatus {
+    let mut status = with_tenant_cycles(|cycles| cycles.borrow().get(&tenant_id))
+        .unwrap_or_else(|| TenantCyclesStatus {
+            tenant_id: tenant_id.clone(),
+            canister_id,
+            last_balance: 0,
+            idle_cycles_burned_per_day: 0,
+            last_checked: 0,
+            last_error: None,
+            total_topped_up: 0,
+            top_ups: Vec::new(),
+        });
+    status.canister_id = canister_id;
+    status.last_checked = current_time();
+
+    match canister_status(CanisterIdRecord { canister_id }).await {
+        Ok((canister,)) => {
+            status.last_balance = nat_to_u128(&canister.cycles);
+            status.idle_cycles_burned_per_day = nat_to_u128(&canister.idle_cycles_burned_per_day);
+            status.last_error = None;
+        },
+        Err((code, msg)) => {
+            status.last_error = Some(format!("Failed to get canister status: {:?} - {}", code, msg));
+            save_status(&status);
+            return status;
+        }
+    }
+
+    if status.last_balance < config.min_tenant_balance {
+        let router_balance = ic_cdk::api::canister_balance128();
+        if router_balance.saturating_sub(config.top_up_amount) < config.router_reserve {
+            raise_alert(&status, format!(
+                "Balance {} below minimum {}; router balance {} too low to top up",
+                status.last_balance, config.min_tenant_balance, router_balance
+            ));
+        } else {
+            match deposit_cycles(CanisterIdRecord { canister_id }, config.top_up_amount).await {
+                Ok(_) => {
+                    status.top_ups.push(CyclesTopUp {
+                        amount: config.top_up_amount,
+                        balance_before: status.last_balance,
+                        topped_up_at: current_time(),
+                    });
+                    if status.top_ups.len() > MAX_TOP_UP_HISTORY {
+                        status.top_ups.remove(0);
+                    }
+                    status.total_topped_up = status.total_topped_up.saturating_add(config.top_up_amount);
+                    status.last_balance = status.last_balance.saturating_add(config.top_up_amount);
+                    ic_cdk::println!("Topped up tenant {} with {} cycles", tenant_id, config.top_up_amount);
+                },
+                Err((code, msg)) => {
+                    let error = format!("Top-up failed: {:?} - {}", code, msg);
+                    raise_alert(&status, format!("Balance {} below minimum {}; {}",
+                                                 status.last_balance, config.min_tenant_balance, error));
+                    status.last_error = Some(error);
+                }
+            }
+        }
+    }
+
+    save_status(&status);
+    status
+}
+
+/// Get the cycles status of a tenant
+pub fn get_tenant_cycles(tenant_id: String) -> LMSResult<TenantCyclesStatus> {
+    with_tenant_cycles(|cycles| {
+        cycles.borrow()
+            .get(&tenant_id)
+            .ok_or_else(|| LMSError::NotFound(format!("No cycles data for tenant '{}'", tenant_id)))
+    })
+}
+
+/// List the cycles status of every monitored tenant
+pub fn list_tenant_cycles() -> Vec<TenantCyclesStatus> {
+    with_tenant_cycles(|cycles| {
+        cycles.borrow().iter().map(|(_, status)| status).collect()
+    })
+}
+
+/// List low-balance alerts, newest first
+pub fn list_cycles_alerts(include_acknowledged: bool) -> Vec<CyclesAlert> {
+    with_cycles_alerts(|alerts| {
+        alerts.borrow()
+            .iter()
+            .rev()
+            .map(|(_, alert)| alert)
+            .filter(|alert| include_acknowledged || !alert.acknowledged)
+            .collect()
+    })
+}
+
+/// Mark an alert as handled
+pub fn acknowledge_cycles_alert(alert_id: u64) -> LMSResult<()> {
+    with_cycles_alerts(|alerts| {
+        let mut alerts = alerts.borrow_mut();
+        match alerts.get(&alert_id) {
+            Some(mut alert) => {
+                alert.acknowledged = true;
+                alerts.insert(alert_id, alert);
+                Ok(())
+            },
+            None => Err(LMSError::NotFound(format!("Alert {} not found", alert_id))),
+        }
+    })
+}
+
+/// Record an alert unless the tenant still has one nobody has acknowledged,
+/// so a tenant that stays low does not add an alert on every check
+fn raise_alert(status: &TenantCyclesStatus, message: String) {
+    ic_cdk::println!("CYCLES ALERT for tenant {}: {}", status.tenant_id, message);
+
+    with_cycles_alerts(|alerts| {
+        let mut alerts = alerts.borrow_mut();
+        let open = alerts.iter().any(|(_, alert)| alert.tenant_id == status.tenant_id && !alert.acknowledged);
+        if open {
+            return;
+        }
+        let id = alerts.last_key_value().map(|(id, _)| id + 1).unwrap_or(1);
+        alerts.insert(id, CyclesAlert {
+            id,
+            tenant_id: status.tenant_id.clone(),
+            canister_id: status.canister_id,
+            balance: status.last_balance,
+            message,
+            raised_at: current_time(),
+            acknowledged: false,
+        });
+    });
+}
+
+fn save_status(status: &TenantCyclesStatus) {
+    with_tenant_cycles(|cycles| {
+        cycles.borrow_mut().insert(status.tenant_id.clone(), status.clone());
+    });
+}
+
+pub fn nat_to_u128(value: &Nat) -> u128 {
+    u128::try_from(&value.0).unwrap_or(u128::MAX)
+}
@@ -0,0 +1,355 @@
+use candid::Principal;
+use ic_cdk::api::management_canister::main::{canister_status, CanisterIdRecord};
+use ic_cdk::caller;
+use shared::{LMSError, LMSResult, current_time};
+use crate::types::{BackupStatus, DecommissionStatus, DecommissionStep, TenantBackup, TenantDecommission};
+use crate::storage::{with_decommission_exports, with_decommissions, with_tenant_cycles};
+use crate::lifecycle::{get_tenant, parse_canister_id, set_tenant_active};
+use crate::canister_management;
+use crate::cycles::nat_to_u128;
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+const DEFAULT_GRACE_PERIOD_DAYS: u32 = 7;
+const MAX_GRACE_PERIOD_DAYS: u32 = 90;
+// Cycles left on the tenant while it processes the withdrawal, on top of 30 days of idle burn
+const WITHDRAWAL_BUFFER: u128 = 10_000_000_000;
+
+/// Start decommissioning a tenant: stop routing to it and back up its stores in batches.
+/// The canister is stopped once the backup completes and only deleted after the grace period,
+/// and only if its data still matches that backup.
+pub async fn schedule_decommission(tenant_id: String, grace_period_days: Option<u32>) -> LMSResult<TenantDecommission> {
+    let grace_period_days = grace_period_days.unwrap_or(DEFAULT_GRACE_PERIOD_DAYS);
+    if grace_period_days > MAX_GRACE_PERIOD_DAYS {
+        return Err(LMSError::ValidationError(format!(
+            "Grace period cannot exceed {} days", MAX_GRACE_PERIOD_DAYS
+        )));
+    }
+
+    if let Some(existing) = with_decommissions(|records| records.borrow().get(&tenant_id)) {
+        if is_open(&existing.status) {
+            return Err(LMSError::AlreadyExists(format!("Tenant '{}' is already being decommissioned", tenant_id)));
+        }
+    }
+
+    let tenant = get_tenant(&tenant_id)?;
+    let canister_id = parse_canister_id(&tenant)?;
+    let mut steps = Vec::new();
+
+    // A suspended tenant's canister is stopped and must run while the backup exports it
+    canister_management::start_canister(canister_id).await
+        .map_err(LMSError::InternalError)?;
+
+    // Re-read the tenant, since it may have changed while the canister was starting
+    let tenant = get_tenant(&tenant_id)?;
+    let backup = match crate::backup::create_backup(&tenant) {
+        Ok(backup) => backup,
+        Err(e) => {
+            if !tenant.is_active {
+                let _ = canister_management::stop_canister(canister_id).await;
+            }
+            return Err(e);
+        }
+    };
+    steps.push(step("backup", Ok(format!("Started backup {}", backup.id))));
+
+    set_tenant_active(&tenant_id, false)?;
+
+    let now = current_time();
+    let record = TenantDecommission {
+        tenant: tenant.clone(),
+        was_active: tenant.is_active,
+        requested_by: caller(),
+        requested_at: now,
+        purge_after: now + grace_period_days as u64 * NANOS_PER_DAY,
+        status: DecommissionStatus::Scheduled,
+        export_size: 0,
+        export_sha256: String::new(),
+        cycles_recovered: 0,
+        steps,
+        completed_at: None,
+        backup_id: Some(backup.id),
+        stopped_at: None,
+    };
+    save_decommission(&record);
+
+    ic_cdk::println!("Scheduled decommission of tenant {} (canister: {}) in {} days, backup {}",
+                     tenant_id, canister_id, grace_period_days, backup.id);
+    Ok(record)
+}
+
+/// Undo a scheduled decommission during its grace period
+pub async fn cancel_decommission(tenant_id: String) -> LMSResult<TenantDecommission> {
+    let mut record = get_decommission(tenant_id.clone())?;
+    if record.status != DecommissionStatus::Scheduled {
+        return Err(LMSError::ValidationError(format!(
+            "Decommission of tenant '{}' can no longer be cancelled", tenant_id
+        )));
+    }
+
+    // Tenants that were suspended before decommissioning stay suspended
+    let canister_id = parse_canister_id(&record.tenant)?;
+    if record.was_active {
+        canister_management::start_canister(canister_id).await
+            .map_err(LMSError::InternalError)?;
+        set_tenant_active(&tenant_id, true)?;
+    } else if record.stopped_at.is_none() {
+        // Still running for the backup, which is kept as a regular backup
+        canister_management::stop_canister(canister_id).await
+            .map_err(LMSError::InternalError)?;
+    }
+
+    with_decommission_exports(|exports| {
+        exports.borrow_mut().remove(&tenant_id);
+    });
+
+    record.status = DecommissionStatus::Cancelled;
+    record.completed_at = Some(current_time());
+    record.steps.push(step("cancel", Ok(format!("Cancelled by {}", caller()))));
+    save_decommission(&record);
+
+    ic_cdk::println!("Cancelled decommission of tenant {}", tenant_id);
+    Ok(record)
+}
+
+/// Delete the tenant canister once its grace period has passed.
+/// Remaining cycles are withdrawn to the router first; `force` skips the grace period.
+/// A failed decommission can be retried by calling this again.
+pub async fn complete_decommission(tenant_id: String, force: bool) -> LMSResult<TenantDecommission> {
+    let mut record = get_decommission(tenant_id.clone())?;
+    match &record.status {
+        DecommissionStatus::Scheduled => {
+            if !force && current_time() < record.purge_after {
+                return Err(LMSError::ValidationError(format!(
+                    "Grace period for tenant '{}' has not ended", tenant_id
+                )));
+            }
+        },
+        DecommissionStatus::Failed(_) => {},
+        _ => {
+            return Err(LMSError::ValidationError(format!(
+                "Decommission of tenant '{}' is not pending", tenant_id
+            )));
+        }
+    }
+
+    // Records from before backups were used only have the single-call export
+    let backup = match record.backup_id {
+        Some(backup_id) => {
+            let backup = crate::backup::get_tenant_backup(backup_id)?;
+            if backup.status != BackupStatus::Completed {
+                return Err(LMSError::ValidationError(format!(
+                    "Backup {} of tenant '{}' has not completed", backup_id, tenant_id
+                )));
+            }
+            Some(backup)
+        },
+        None => None,
+    };
+
+    record.status = DecommissionStatus::Purging;
+    save_decommission(&record);
+
+    let canister_id = parse_canister_id(&record.tenant)?;
+    match purge_canister(&mut record, canister_id, backup.as_ref()).await {
+        Ok(()) => {
+            // Routing and registry rows go last so a failed purge can still be retried
+            if get_tenant(&tenant_id).is_ok() {
+                crate::tenant_management::remove_tenant(tenant_id.clone())?;
+            }
+            with_tenant_cycles(|cycles| {
+                cycles.borrow_mut().remove(&tenant_id);
+            });
+            crate::domains::remove_tenant_domains(&tenant_id);
+            crate::subdomains::remove_tenant_aliases(&tenant_id);
+            record.steps.push(step("remove_tenant", Ok("Registry, routing and domain entries removed".to_string())));
+            record.status = DecommissionStatus::Deleted;
+            record.completed_at = Some(current_time());
+            save_decommission(&record);
+
+            ic_cdk::println!("Decommissioned tenant {} (canister: {}), recovered {} cycles",
+                             tenant_id, canister_id, record.cycles_recovered);
+            Ok(record)
+        },
+        Err(reason) => {
+            ic_cdk::println!("Decommission of tenant {} failed: {}", tenant_id, reason);
+            let mismatched = record.steps.last().map(|last| last.step == "verify_backup" && !last.succeeded).unwrap_or(false);
+            if mismatched {
+                // The data changed after the backup; take a new one and purge once it completes
+                let retry = crate::backup::create_backup(&record.tenant);
+                record.steps.push(step("backup", retry.as_ref()
+                    .map(|backup| format!("Started backup {}", backup.id))
+                    .map_err(|e| format!("{:?}", e))));
+                if let Ok(retry) = retry {
+                    record.backup_id = Some(retry.id);
+                    record.stopped_at = None;
+                    record.status = DecommissionStatus::Scheduled;
+                    save_decommission(&record);
+                    return Err(LMSError::InternalError(reason));
+                }
+            }
+            record.status = DecommissionStatus::Failed(reason.clone());
+            save_decommission(&record);
+            Err(LMSError::InternalError(reason))
+        }
+    }
+}
+
+/// Background job entry point used by the scheduler: stops canisters whose backup has
+/// completed, retries failed backups and purges tenants past their grace period
+pub async fn run_decommission_jobs() {
+    let now = current_time();
+    let scheduled: Vec<TenantDecommission> = with_decommissions(|records| {
+        records.borrow()
+            .iter()
+            .filter(|(_, record)| record.status == DecommissionStatus::Scheduled)
+            .map(|(_, record)| record)
+            .collect()
+    });
+
+    for record in scheduled {
+        let tenant_id = record.tenant.id.clone();
+        if record.backup_id.is_some() && record.stopped_at.is_none() {
+            if let Err(e) = advance_backup(&tenant_id).await {
+                ic_cdk::println!("Decommission backup of tenant {} failed: {:?}", tenant_id, e);
+            }
+        }
+        if record.purge_after <= now {
+            let _ = complete_decommission(tenant_id, false).await;
+        }
+    }
+}
+
+/// Stop the canister once its backup has completed, or start a new backup if it failed
+async fn advance_backup(tenant_id: &str) -> LMSResult<()> {
+    // Re-read the record, since earlier jobs awaited tenant calls
+    let mut record = get_decommission(tenant_id.to_string())?;
+    let Some(backup_id) = record.backup_id else { return Ok(()) };
+    if record.status != DecommissionStatus::Scheduled || record.stopped_at.is_some() {
+        return Ok(());
+    }
+
+    let backup = crate::backup::get_tenant_backup(backup_id)?;
+    match &backup.status {
+        BackupStatus::Running => return Ok(()),
+        BackupStatus::Completed => {
+            let canister_id = parse_canister_id(&record.tenant)?;
+            let stopped = canister_management::stop_canister(canister_id).await;
+            record.steps.push(step("stop_canister", stopped.clone().map(|_| format!(
+                "Canister stopped after backup {} completed", backup_id
+            ))));
+            if stopped.is_ok() {
+                record.stopped_at = Some(current_time());
+            }
+        },
+        BackupStatus::Failed(reason) => {
+            record.steps.push(step("backup", Err(format!("Backup {} failed: {}", backup_id, reason))));
+            let retry = crate::backup::create_backup(&record.tenant);
+            record.steps.push(step("backup", retry.as_ref()
+                .map(|backup| format!("Started backup {}", backup.id))
+                .map_err(|e| format!("{:?}", e))));
+            if let Ok(retry) = retry {
+                record.backup_id = Some(retry.id);
+            }
+        },
+    }
+    save_decommission(&record);
+    Ok(())
+}
+
+/// Whether a backup still guards a tenant that has not been deleted yet
+pub(crate) fn is_backup_pending_purge(backup_id: u64) -> bool {
+    list_decommissions().iter().any(|record| {
+        record.backup_id == Some(backup_id)
+            && is_open(&record.status)
+    })
+}
+
+/// Whether a tenant is being decommissioned and has not been deleted or cancelled
+pub(crate) fn is_decommissioning(tenant_id: &str) -> bool {
+    with_decommissions(|records| records.borrow().get(&tenant_id.to_string()))
+        .is_some_and(|record| is_open(&record.status))
+}
+
+fn is_open(status: &DecommissionStatus) -> bool {
+    matches!(status, DecommissionStatus::Scheduled | DecommissionStatus::Purging | DecommissionStatus::Failed(_))
+}
+
+/// Get the decommission record of a tenant
+pub fn get_decommission(tenant_id: String) -> LMSResult<TenantDecommission> {
+    with_decommissions(|records| {
+        records.borrow()
+            .get(&tenant_id)
+            .ok_or_else(|| LMSError::NotFound(format!("No decommission for tenant '{}'", tenant_id)))
+    })
+}
+
+/// List all decommission records, including tombstones of deleted tenants
+pub fn list_decommissions() -> Vec<TenantDecommission> {
+    with_decommissions(|records| {
+        records.borrow().iter().map(|(_, record)| record).collect()
+    })
+}
+
+/// Get the candid-encoded `TenantDataExport` of a decommission scheduled before backups were used.
+/// Newer decommissions keep the tenant's data in the backup named by `backup_id`.
+pub fn get_decommission_export(tenant_id: String) -> LMSResult<Vec<u8>> {
+    with_decommission_exports(|exports| exports.borrow().get(&tenant_id))
+        .ok_or_else(|| LMSError::NotFound(format!("No export stored for tenant '{}'", tenant_id)))
+}
+
+/// Withdraw the tenant's cycles and delete its canister, logging each step on the record.
+/// With a backup, the canister is only deleted if its data still matches it.
+async fn purge_canister(record: &mut TenantDecommission, canister_id: Principal, backup: Option<&TenantBackup>) -> Result<(), String> {
+    let result = canister_management::start_canister(canister_id).await;
+    record.steps.push(step("start_canister", result.clone().map(|_| "Canister started for withdrawal".to_string())));
+    result?;
+
+    if let Some(backup) = backup {
+        let result = crate::backup::verify_backup(backup, canister_id).await;
+        record.steps.push(step("verify_backup", result.clone().map(|_| format!("Data matches backup {}", backup.id))));
+        // The canister is left running so a new backup can be taken
+        result?;
+    }
+
+    let result = withdraw_cycles(canister_id).await;
+    record.steps.push(step("withdraw_cycles", result.clone().map(|amount| format!("Recovered {} cycles", amount))));
+    // Stop again even if the withdrawal failed so the tenant stays offline until a retry
+    let stop_result = canister_management::stop_canister(canister_id).await;
+    record.steps.push(step("stop_canister", stop_result.clone().map(|_| "Canister stopped".to_string())));
+    record.cycles_recovered = record.cycles_recovered.saturating_add(result?);
+    stop_result?;
+
+    let result = canister_management::delete_canister(canister_id).await;
+    record.steps.push(step("delete_canister", result.clone().map(|_| "Canister deleted".to_string())));
+    result
+}
+
+async fn withdraw_cycles(canister_id: Principal) -> Result<u128, String> {
+    let (status,) = canister_status(CanisterIdRecord { canister_id }).await
+        .map_err(|(code, msg)| format!("Failed to get canister status: {:?} - {}", code, msg))?;
+    let idle_burn = nat_to_u128(&status.idle_cycles_burned_per_day);
+    let reserve = idle_burn.saturating_mul(30).saturating_add(WITHDRAWAL_BUFFER);
+
+    let (result,): (LMSResult<u128>,) = ic_cdk::call(canister_id, "return_cycles", (reserve,)).await
+        .map_err(|(code, msg)| format!("Withdrawal call failed: {:?} - {}", code, msg))?;
+    result.map_err(|e| format!("Tenant refused withdrawal: {:?}", e))
+}
+
+fn step(name: &str, outcome: Result<String, String>) -> DecommissionStep {
+    let (succeeded, message) = match outcome {
+        Ok(message) => (true, message),
+        Err(message) => (false, message),
+    };
+    DecommissionStep {
+        step: name.to_string(),
+        succeeded,
+        message,
+        at: current_time(),
+    }
+}
+
+fn save_decommission(record: &TenantDecommission) {
+    with_decommissions(|records| {
+        records.borrow_mut().insert(record.tenant.id.clone(), record.clone());
+    });
+}
@@ -0,0 +1,108 @@
+use std::ops::Bound;
+use shared::Tenant;
+use crate::types::{PublicTenant, TenantDirectoryPage, TenantDirectoryQuery};
+use crate::storage::with_tenant_registry;
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// A page of the public tenant directory, ordered by tenant ID
+pub fn list_tenant_directory(query: TenantDirectoryQuery) -> TenantDirectoryPage {
+    let start = match &query.cursor {
+        Some(cursor) => Bound::Excluded(cursor.clone()),
+        None => Bound::Unbounded,
+    };
+
+    with_tenant_registry(|registry| {
+        let registry = registry.borrow();
+        let tenants = registry.range((start, Bound::Unbounded)).map(|(_, tenant)| tenant);
+        directory_page(tenants, &query)
+    })
+}
+
+fn directory_page(tenants: impl Iterator<Item = Tenant>, query: &TenantDirectoryQuery) -> TenantDirectoryPage {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+    let search = query.search.as_ref()
+        .map(|search| search.trim().to_lowercase())
+        .filter(|search| !search.is_empty());
+
+    // Take one extra match to learn whether another page exists
+    let mut matches: Vec<PublicTenant> = tenants
+        .filter(|tenant| !query.active_only || tenant.is_active)
+        .filter(|tenant| search.as_ref().map(|search| matches_search(tenant, search)).unwrap_or(true))
+        .take(limit + 1)
+        .map(public_view)
+        .collect();
+
+    let next_cursor = if matches.len() > limit {
+        matches.truncate(limit);
+        matches.last().map(|tenant| tenant.id.clone())
+    } else {
+        None
+    };
+
+    TenantDirectoryPage { tenants: matches, next_cursor }
+}
+
+fn matches_search(tenant: &Tenant, search: &str) -> bool {
+    tenant.subdomain.to_lowercase().starts_with(search) || tenant.name.to_lowercase().contains(search)
+}
+
+fn public_view(tenant: Tenant) -> PublicTenant {
+    PublicTenant {
+        id: tenant.id,
+        name: tenant.name,
+        subdomain: tenant.subdomain,
+        canister_id: tenant.canister_id,
+        is_active: tenant.is_active,
+        allow_public_enrollment: tenant.settings.allow_public_enrollment,
+        created_at: tenant.created_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::TenantSettings;
+
+    fn tenant(id: &str, name: &str, subdomain: &str, is_active: bool) -> Tenant {
+        Tenant {
+            id: id.to_string(),
+            name: name.to_string(),
+            subdomain: subdomain.to_string(),
+            canister_id: "aaaaa-aa".to_string(),
+            admin_ids: vec!["2vxsx-fae".to_string()],
+            created_at: 0,
+            updated_at: 0,
+            is_active,
+            settings: TenantSettings::default(),
+            template_version: None,
+            module_hash: None,
+            template_track: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_directory_page() {
+        let tenants = vec![
+            tenant("tenant_1", "Harvard University", "harvard", true),
+            tenant("tenant_2", "MIT", "mit", false),
+            tenant("tenant_3", "Stanford University", "stanford", true),
+            tenant("tenant_4", "Yale", "yale", true),
+        ];
+
+        let query = TenantDirectoryQuery { limit: Some(2), active_only: true, ..TenantDirectoryQuery::default() };
+        let page = directory_page(tenants.clone().into_iter(), &query);
+        let ids: Vec<&str> = page.tenants.iter().map(|tenant| tenant.id.as_str()).collect();
+        assert_eq!(ids, vec!["tenant_1", "tenant_3"]);
+        assert_eq!(page.next_cursor.as_deref(), Some("tenant_3"));
+
+        let query = TenantDirectoryQuery { search: Some("University".to_string()), ..TenantDirectoryQuery::default() };
+        let page = directory_page(tenants.clone().into_iter(), &query);
+        assert_eq!(page.tenants.len(), 2);
+        assert_eq!(page.next_cursor, None);
+
+        let query = TenantDirectoryQuery { search: Some("ya".to_string()), ..TenantDirectoryQuery::default() };
+        assert_eq!(directory_page(tenants.into_iter(), &query).tenants[0].subdomain, "yale");
+    }
+}
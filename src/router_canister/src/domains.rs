@@ -0,0 +1,349 @@
+use ic_cdk::api::management_canister::http_request::{
+    http_request, CanisterHttpRequestArgument, HttpMethod, HttpResponse as OutcallResponse, TransformArgs, TransformContext,
+};
+use ic_cdk::api::management_canister::main::raw_rand;
+use ic_cdk::caller;
+use shared::{LMSError, LMSResult, current_time};
+use crate::types::{BaseDomain, BaseDomainConfig, CustomDomain, CustomDomainStatus};
+use crate::storage::{with_base_domains, with_custom_domains, with_tenant_registry};
+use crate::canister_management;
+
+/// Path on the custom domain that must serve the verification token
+pub const CHALLENGE_PATH: &str = "/.well-known/lms-domain-verification";
+
+// Deepest tenant subdomain nesting a base domain may allow
+const MAX_WILDCARD_DEPTH: u8 = 3;
+const CHALLENGE_MAX_RESPONSE_BYTES: u64 = 1024;
+const CHALLENGE_CYCLES: u128 = 2_000_000_000;
+
+/// Claim a hostname for a tenant; it is only routed once ownership has been verified
+pub async fn add_custom_domain(tenant_id: String, hostname: String) -> LMSResult<CustomDomain> {
+    let hostname = normalize_hostname(&hostname)
+        .ok_or_else(|| LMSError::ValidationError(format!("Invalid hostname '{}'", hostname)))?;
+
+    if is_platform_domain(&hostname) {
+        return Err(LMSError::ValidationError(format!("'{}' is a platform domain", hostname)));
+    }
+
+    let tenant_exists = with_tenant_registry(|registry| registry.borrow().contains_key(&tenant_id));
+    if !tenant_exists {
+        return Err(LMSError::NotFound(format!("Tenant '{}' not found", tenant_id)));
+    }
+
+    if let Ok(existing) = get_custom_domain(hostname.clone()) {
+        return Err(LMSError::AlreadyExists(format!(
+            "Domain '{}' is already mapped to tenant '{}'", hostname, existing.tenant_id
+        )));
+    }
+
+    let (random_bytes,) = raw_rand().await
+        .map_err(|(code, msg)| LMSError::InternalError(format!("Failed to generate token: {:?} - {}", code, msg)))?;
+
+    let domain = CustomDomain {
+        hostname: hostname.clone(),
+        tenant_id,
+        verification_token: canister_management::hex_encode(&random_bytes),
+        status: CustomDomainStatus::PendingVerification,
+        created_by: caller(),
+        created_at: current_time(),
+        last_checked: None,
+        verified_at: None,
+    };
+    save_domain(&domain);
+
+    ic_cdk::println!("Added custom domain {} for tenant {}", hostname, domain.tenant_id);
+    Ok(domain)
+}
+
+/// Fetch the challenge path over HTTPS and mark the domain verified if it serves the token
+pub async fn verify_custom_domain(hostname: String) -> LMSResult<CustomDomain> {
+    let mut domain = get_custom_domain(hostname)?;
+
+    let request = CanisterHttpRequestArgument {
+        url: format!("https://{}{}", domain.hostname, CHALLENGE_PATH),
+        max_response_bytes: Some(CHALLENGE_MAX_RESPONSE_BYTES),
+        method: HttpMethod::GET,
+        headers: vec![],
+        body: None,
+        transform: Some(TransformContext::from_name("transform_domain_challenge".to_string(), vec![])),
+    };
+
+    let outcome = match http_request(request, CHALLENGE_CYCLES).await {
+        Ok((response,)) if response.status != 200u16 => {
+            Err(format!("Challenge path returned HTTP {}", response.status))
+        },
+        Ok((response,)) => {
+            let body = String::from_utf8_lossy(&response.body);
+            if body.trim() == domain.verification_token {
+                Ok(())
+            } else {
+                Err("Challenge path did not return the verification token".to_string())
+            }
+        },
+        Err((code, msg)) => Err(format!("Challenge request failed: {:?} - {}", code, msg)),
+    };
+
+    let now = current_time();
+    domain.last_checked = Some(now);
+    match outcome {
+        Ok(()) => {
+            domain.status = CustomDomainStatus::Verified;
+            domain.verified_at = Some(now);
+            ic_cdk::println!("Verified custom domain {} for tenant {}", domain.hostname, domain.tenant_id);
+        },
+        Err(reason) => {
+            // Keep the token so the tenant can fix their setup and retry
+            ic_cdk::println!("Verification of {} failed: {}", domain.hostname, reason);
+            domain.status = CustomDomainStatus::Failed(reason);
+        }
+    }
+    save_domain(&domain);
+    Ok(domain)
+}
+
+/// Strip headers from the challenge response so every replica sees the same bytes
+pub fn transform_domain_challenge(args: TransformArgs) -> OutcallResponse {
+    OutcallResponse {
+        status: args.response.status,
+        headers: vec![],
+        body: args.response.body,
+    }
+}
+
+/// Remove a custom domain mapping
+pub fn remove_custom_domain(hostname: String) -> LMSResult<()> {
+    let hostname = normalize_hostname(&hostname).unwrap_or(hostname);
+    with_custom_domains(|domains| domains.borrow_mut().remove(&hostname))
+        .ok_or_else(|| LMSError::NotFound(format!("Domain '{}' not found", hostname)))?;
+
+    ic_cdk::println!("Removed custom domain {}", hostname);
+    Ok(())
+}
+
+/// Remove every custom domain of a tenant
+pub fn remove_tenant_domains(tenant_id: &str) {
+    let hostnames: Vec<String> = list_custom_domains(Some(tenant_id.to_string()))
+        .into_iter()
+        .map(|domain| domain.hostname)
+        .collect();
+    with_custom_domains(|domains| {
+        let mut domains = domains.borrow_mut();
+        for hostname in hostnames {
+            domains.remove(&hostname);
+        }
+    });
+}
+
+/// Get a custom domain by hostname
+pub fn get_custom_domain(hostname: String) -> LMSResult<CustomDomain> {
+    let hostname = normalize_hostname(&hostname).unwrap_or(hostname);
+    with_custom_domains(|domains| {
+        domains.borrow()
+            .get(&hostname)
+            .ok_or_else(|| LMSError::NotFound(format!("Domain '{}' not found", hostname)))
+    })
+}
+
+/// List custom domains, optionally only those of one tenant
+pub fn list_custom_domains(tenant_id: Option<String>) -> Vec<CustomDomain> {
+    with_custom_domains(|domains| {
+        domains.borrow()
+            .iter()
+            .map(|(_, domain)| domain)
+            .filter(|domain| tenant_id.as_ref().map(|id| &domain.tenant_id == id).unwrap_or(true))
+            .collect()
+    })
+}
+
+/// Tenant ID for a verified custom domain, given a Host header value
+pub fn resolve_custom_domain(host: &str) -> Option<String> {
+    let hostname = normalize_hostname(host)?;
+    with_custom_domains(|domains| domains.borrow().get(&hostname))
+        .filter(|domain| domain.status == CustomDomainStatus::Verified)
+        .map(|domain| domain.tenant_id)
+}
+
+/// Verification token to serve for a challenge request on a claimed domain that is not yet verified
+pub fn challenge_token(host: &str) -> Option<String> {
+    let hostname = normalize_hostname(host)?;
+    with_custom_domains(|domains| domains.borrow().get(&hostname))
+        .filter(|domain| domain.status != CustomDomainStatus::Verified)
+        .map(|domain| domain.verification_token)
+}
+
+fn save_domain(domain: &CustomDomain) {
+    with_custom_domains(|domains| {
+        domains.borrow_mut().insert(domain.hostname.clone(), domain.clone());
+    });
+}
+
+/// Hostnames on or under a base domain belong to the platform and cannot be claimed
+fn is_platform_domain(hostname: &str) -> bool {
+    get_base_domains().iter().any(|base| is_within(hostname, &base.domain))
+}
+
+fn is_within(hostname: &str, domain: &str) -> bool {
+    hostname == domain || hostname.ends_with(&format!(".{}", domain))
+}
+
+/// Get the platform base domains
+pub fn get_base_domains() -> Vec<BaseDomain> {
+    with_base_domains(|config| config.borrow().get().domains.clone())
+}
+
+/// Replace the platform base domains used for subdomain routing
+pub fn configure_base_domains(domains: Vec<BaseDomain>) -> LMSResult<Vec<BaseDomain>> {
+    let mut normalized: Vec<BaseDomain> = Vec::new();
+    for mut base in domains {
+        base.domain = normalize_hostname(&base.domain)
+            .ok_or_else(|| LMSError::ValidationError(format!("Invalid base domain '{}'", base.domain)))?;
+
+        if base.wildcard_depth > MAX_WILDCARD_DEPTH {
+            return Err(LMSError::ValidationError(format!(
+                "Wildcard depth of '{}' cannot exceed {}", base.domain, MAX_WILDCARD_DEPTH
+            )));
+        }
+
+        if base.ports.contains(&0) {
+            return Err(LMSError::ValidationError(format!("Invalid port 0 for '{}'", base.domain)));
+        }
+
+        if normalized.iter().any(|existing| existing.domain == base.domain) {
+            return Err(LMSError::ValidationError(format!("Duplicate base domain '{}'", base.domain)));
+        }
+
+        let claimed = list_custom_domains(None).into_iter()
+            .find(|custom| is_within(&custom.hostname, &base.domain));
+        if let Some(custom) = claimed {
+            return Err(LMSError::ValidationError(format!(
+                "'{}' is already a custom domain of tenant '{}'", custom.hostname, custom.tenant_id
+            )));
+        }
+
+        base.ports.sort_unstable();
+        base.ports.dedup();
+        normalized.push(base);
+    }
+
+    with_base_domains(|config| {
+        config.borrow_mut().set(BaseDomainConfig {
+            domains: normalized.clone(),
+            updated_at: current_time(),
+        }).map_err(|_| LMSError::InternalError("Failed to store base domains".to_string()))
+    })?;
+
+    ic_cdk::println!("Configured {} base domains", normalized.len());
+    Ok(normalized)
+}
+
+/// Tenant subdomain named by a Host header value under the configured base domains
+pub fn tenant_from_platform_host(host: &str) -> Option<String> {
+    match_base_domain(&get_base_domains(), host)
+}
+
+/// Find the most specific base domain covering `host` and return the tenant label in front of it
+fn match_base_domain(domains: &[BaseDomain], host: &str) -> Option<String> {
+    split_platform_host(domains, host).map(|(_, tenant, _)| tenant)
+}
+
+/// Split a platform host around its tenant label: `(leading labels, tenant, base domain and port)`
+fn split_platform_host(domains: &[BaseDomain], host: &str) -> Option<(String, String, String)> {
+    let host = host.trim().to_lowercase();
+    let (hostname, port) = match host.split_once(':') {
+        Some((hostname, port)) => (hostname, Some(port.parse::<u16>().ok()?)),
+        None => (host.as_str(), None),
+    };
+    let hostname = hostname.trim_end_matches('.');
+
+    let base = domains.iter()
+        .filter(|base| hostname.ends_with(&format!(".{}", base.domain)))
+        .max_by_key(|base| base.domain.len())?;
+
+    if let Some(port) = port {
+        if !base.ports.contains(&port) {
+            return None;
+        }
+    }
+
+    let prefix = &hostname[..hostname.len() - base.domain.len() - 1];
+    let labels: Vec<&str> = prefix.split('.').collect();
+    if labels.len() > base.wildcard_depth as usize || labels.iter().any(|label| label.is_empty()) {
+        return None;
+    }
+
+    let tenant = labels.last()?.to_string();
+    let leading = &prefix[..prefix.len() - tenant.len()];
+    let trailing = match port {
+        Some(port) => format!(".{}:{}", base.domain, port),
+        None => format!(".{}", base.domain),
+    };
+    Some((leading.to_string(), tenant, trailing))
+}
+
+/// Rewrite a platform Host header value to point at a different tenant subdomain
+pub fn rewrite_platform_host(host: &str, subdomain: &str) -> Option<String> {
+    let (leading, _, trailing) = split_platform_host(&get_base_domains(), host)?;
+    Some(format!("{}{}{}", leading, subdomain, trailing))
+}
+
+/// Lowercase a hostname and strip any port and trailing dot; `None` if it is not a valid DNS name
+pub fn normalize_hostname(host: &str) -> Option<String> {
+    let host = host.trim().to_lowercase();
+    let host = host.split(':').next()?.trim_end_matches('.');
+    if host.is_empty() || host.len() > 253 {
+        return None;
+    }
+
+    let labels: Vec<&str> = host.split('.').collect();
+    if labels.len() < 2 {
+        return None;
+    }
+
+    let valid_labels = labels.iter().all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    let valid_tld = labels.last().map(|tld| tld.chars().all(|c| c.is_ascii_alphabetic())).unwrap_or(false);
+
+    if valid_labels && valid_tld {
+        Some(host.to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{match_base_domain, normalize_hostname, split_platform_host};
+    use crate::types::BaseDomainConfig;
+
+    #[test]
+    fn test_match_base_domain() {
+        let domains = BaseDomainConfig::default().domains;
+        assert_eq!(match_base_domain(&domains, "harvard.lms.app"), Some("harvard".to_string()));
+        assert_eq!(match_base_domain(&domains, "mit.lms.localhost:4943"), Some("mit".to_string()));
+        assert_eq!(match_base_domain(&domains, "mit.lms.localhost:9999"), None);
+        assert_eq!(match_base_domain(&domains, "www.harvard.lms.app"), None);
+        assert_eq!(match_base_domain(&domains, "lms.app"), None);
+        assert_eq!(match_base_domain(&domains, "rrkah-fqaaa-aaaaa-aaaaq-cai.icp0.io"), None);
+        assert_eq!(match_base_domain(&domains, "learn.university.edu"), None);
+        assert_eq!(
+            split_platform_host(&domains, "old.lms.localhost:4943"),
+            Some((String::new(), "old".to_string(), ".lms.localhost:4943".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_normalize_hostname() {
+        assert_eq!(normalize_hostname("Learn.University.EDU"), Some("learn.university.edu".to_string()));
+        assert_eq!(normalize_hostname("learn.university.edu:443"), Some("learn.university.edu".to_string()));
+        assert_eq!(normalize_hostname("learn.university.edu."), Some("learn.university.edu".to_string()));
+        assert_eq!(normalize_hostname("localhost"), None);
+        assert_eq!(normalize_hostname("-bad.university.edu"), None);
+        assert_eq!(normalize_hostname("learn.university.123"), None);
+        assert_eq!(normalize_hostname("learn..edu"), None);
+    }
+}
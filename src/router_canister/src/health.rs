@@ -0,0 +1,301 @@
+use candid::Principal;
+use ic_cdk::api::management_canister::main::{canister_status, CanisterIdRecord, CanisterStatusType};
+use shared::{Tenant, LMSError, LMSResult, current_time};
+use crate::types::{HealthDashboard, HealthMonitorConfig, HealthState, TenantHealth};
+use crate::storage::{with_health_monitor_config, with_tenant_health, with_tenant_registry};
+use crate::canister_management::hex_encode;
+use crate::cycles::nat_to_u128;
+
+const MIN_CHECK_INTERVAL_SECONDS: u64 = 60;
+const NANOS_PER_MILLI: u64 = 1_000_000;
+
+/// Get the health monitor configuration
+pub fn get_health_monitor_config() -> HealthMonitorConfig {
+    with_health_monitor_config(|config| config.borrow().get().clone())
+}
+
+/// Update the health monitor configuration
+pub fn configure_health_monitor(config: HealthMonitorConfig) -> LMSResult<HealthMonitorConfig> {
+    if config.check_interval_seconds < MIN_CHECK_INTERVAL_SECONDS {
+        return Err(LMSError::ValidationError(format!(
+            "Check interval must be at least {} seconds", MIN_CHECK_INTERVAL_SECONDS
+        )));
+    }
+
+    if config.failure_threshold == 0 {
+        return Err(LMSError::ValidationError("Failure threshold must be at least 1".to_string()));
+    }
+
+    with_health_monitor_config(|stored| {
+        stored.borrow_mut().set(config.clone()).map_err(|_| {
+            LMSError::InternalError("Failed to store health monitor configuration".to_string())
+        })
+    })?;
+
+    ic_cdk::println!("Health monitor configured: every {}s, unhealthy after {} failures",
+                     config.check_interval_seconds, config.failure_threshold);
+    Ok(config)
+}
+
+/// Background job entry point used by the scheduler
+pub async fn run_health_monitor() {
+    let results = check_all_tenants().await;
+    let unhealthy = results.iter().filter(|health| health.state == HealthState::Unhealthy).count();
+    ic_cdk::println!("Health monitor checked {} tenants, {} unhealthy", results.len(), unhealthy);
+}
+
+/// Probe every registered tenant and record the outcome
+pub async fn check_all_tenants() -> Vec<TenantHealth> {
+    let config = get_health_monitor_config();
+    let tenants: Vec<Tenant> = with_tenant_registry(|registry| {
+        registry.borrow().iter().map(|(_, tenant)| tenant).collect()
+    });
+    prune_removed_tenants(&tenants);
+
+    let mut results = Vec::new();
+    for tenant in tenants {
+        let canister_id = match Principal::from_text(&tenant.canister_id) {
+            Ok(principal) => principal,
+            Err(_) => continue,
+        };
+        results.push(check_tenant(&config, tenant, canister_id).await);
+    }
+    results
+}
+
+/// Call the tenant's `health_check` and read its canister status
+async fn check_tenant(config: &HealthMonitorConfig, tenant: Tenant, canister_id: Principal) -> TenantHealth {
+    let mut health = with_tenant_health(|health| health.borrow().get(&tenant.id))
+        .unwrap_or_else(|| TenantHealth {
+            tenant_id: tenant.id.clone(),
+            subdomain: tenant.subdomain.clone(),
+            canister_id,
+            state: HealthState::Healthy,
+            consecutive_failures: 0,
+            latency_ms: None,
+            memory_size: None,
+            cycles: None,
+            module_hash: None,
+            last_error: None,
+            last_checked: 0,
+            last_healthy: None,
+            unhealthy_since: None,
+        });
+    health.subdomain = tenant.subdomain;
+    health.canister_id = canister_id;
+
+    // A suspended tenant's canister is stopped on purpose, so probing it would only count failures
+    if !tenant.is_active {
+        health.state = HealthState::Suspended;
+        health.consecutive_failures = 0;
+        health.unhealthy_since = None;
+        health.last_checked = current_time();
+        save_health(&health);
+        return health;
+    }
+
+    let started = current_time();
+    let probe: Result<(String,), _> = ic_cdk::call(canister_id, "health_check", ()).await;
+    let mut error = match probe {
+        Ok(_) => {
+            health.latency_ms = Some(current_time().saturating_sub(started) / NANOS_PER_MILLI);
+            None
+        },
+        Err((code, msg)) => {
+            health.latency_ms = None;
+            Some(format!("health_check failed: {:?} - {}", code, msg))
+        }
+    };
+
+    match canister_status(CanisterIdRecord { canister_id }).await {
+        Ok((status,)) => {
+            health.memory_size = Some(nat_to_u128(&status.memory_size));
+            health.cycles = Some(nat_to_u128(&status.cycles));
+            health.module_hash = status.module_hash.map(|hash| hex_encode(&hash));
+            if error.is_none() && status.status != CanisterStatusType::Running {
+                error = Some(format!("Canister is {:?}", status.status));
+            }
+        },
+        Err((code, msg)) => {
+            error.get_or_insert(format!("Failed to get canister status: {:?} - {}", code, msg));
+        }
+    }
+
+    let was_unhealthy = health.state == HealthState::Unhealthy;
+    apply_check(&mut health, error, config.failure_threshold, current_time());
+    if health.state == HealthState::Unhealthy && !was_unhealthy {
+        ic_cdk::println!("HEALTH ALERT: tenant {} is unhealthy after {} failed checks: {}",
+                         health.tenant_id, health.consecutive_failures, health.last_error.as_deref().unwrap_or_default());
+    }
+
+    save_health(&health);
+    health
+}
+
+/// Get the latest health check of a tenant
+pub fn get_tenant_health(tenant_id: String) -> LMSResult<TenantHealth> {
+    with_tenant_health(|health| {
+        health.borrow()
+            .get(&tenant_id)
+            .ok_or_else(|| LMSError::NotFound(format!("No health data for tenant '{}'", tenant_id)))
+    })
+}
+
+/// Counts by state and every tenant's latest check, unhealthy tenants first
+pub fn get_health_dashboard() -> HealthDashboard {
+    let mut tenants: Vec<TenantHealth> = with_tenant_health(|health| {
+        health.borrow().iter().map(|(_, health)| health).collect()
+    });
+    let registered = with_tenant_registry(|registry| registry.borrow().len());
+
+    let mut dashboard = HealthDashboard {
+        last_checked: tenants.iter().map(|health| health.last_checked).max(),
+        ..HealthDashboard::default()
+    };
+    for health in &tenants {
+        match health.state {
+            HealthState::Healthy => dashboard.healthy += 1,
+            HealthState::Degraded => dashboard.degraded += 1,
+            HealthState::Unhealthy => dashboard.unhealthy += 1,
+            HealthState::Suspended => dashboard.suspended += 1,
+        }
+    }
+    dashboard.unchecked = registered.saturating_sub(tenants.len() as u64);
+
+    tenants.sort_by_key(|health| (severity(health.state), health.tenant_id.clone()));
+    dashboard.tenants = tenants;
+    dashboard
+}
+
+/// Public HTML summary served at `/status`; it names tenants by subdomain, which is DNS-safe
+pub fn render_status_page(dashboard: &HealthDashboard) -> String {
+    let overall = if dashboard.unhealthy > 0 {
+        "Some universities are unavailable"
+    } else if dashboard.degraded > 0 {
+        "Some universities are degraded"
+    } else {
+        "All systems operational"
+    };
+
+    let rows: String = dashboard.tenants.iter()
+        .map(|health| format!("        <tr><td>{}</td><td class=\"{:?}\">{:?}</td></tr>\n", health.subdomain, health.state, health.state))
+        .collect();
+    let checked = dashboard.last_checked
+        .map(|time| format!("Last checked at {} (ns since epoch)", time))
+        .unwrap_or_else(|| "No checks have run yet".to_string());
+
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Decentralized LMS Status</title>
+    <style>
+        body {{ font-family: Arial, sans-serif; max-width: 800px; margin: 50px auto; padding: 20px; }}
+        table {{ border-collapse: collapse; width: 100%; }}
+        td {{ padding: 6px 10px; border-bottom: 1px solid #e5e5e5; }}
+        .Healthy {{ color: #2e7d32; }}
+        .Degraded {{ color: #ef6c00; }}
+        .Unhealthy {{ color: #c62828; }}
+        .Suspended {{ color: #757575; }}
+    </style>
+</head>
+<body>
+    <h1>{}</h1>
+    <p>{} healthy, {} degraded, {} unhealthy, {} suspended</p>
+    <table>
+{}    </table>
+    <p>{}</p>
+</body>
+</html>"#,
+        overall, dashboard.healthy, dashboard.degraded, dashboard.unhealthy, dashboard.suspended, rows, checked)
+}
+
+/// Record one check: any failure counts towards the threshold, a pass clears it
+fn apply_check(health: &mut TenantHealth, error: Option<String>, failure_threshold: u32, now: u64) {
+    health.last_checked = now;
+    match error {
+        None => {
+            health.state = HealthState::Healthy;
+            health.consecutive_failures = 0;
+            health.last_error = None;
+            health.last_healthy = Some(now);
+            health.unhealthy_since = None;
+        },
+        Some(error) => {
+            health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+            health.last_error = Some(error);
+            if health.consecutive_failures >= failure_threshold {
+                health.state = HealthState::Unhealthy;
+                health.unhealthy_since.get_or_insert(now);
+            } else {
+                health.state = HealthState::Degraded;
+            }
+        }
+    }
+}
+
+fn severity(state: HealthState) -> u8 {
+    match state {
+        HealthState::Unhealthy => 0,
+        HealthState::Degraded => 1,
+        HealthState::Healthy => 2,
+        HealthState::Suspended => 3,
+    }
+}
+
+/// Drop the health of tenants that have left the registry
+fn prune_removed_tenants(tenants: &[Tenant]) {
+    with_tenant_health(|health| {
+        let removed: Vec<String> = health.borrow()
+            .iter()
+            .map(|(tenant_id, _)| tenant_id)
+            .filter(|tenant_id| !tenants.iter().any(|tenant| &tenant.id == tenant_id))
+            .collect();
+        let mut health = health.borrow_mut();
+        for tenant_id in removed {
+            health.remove(&tenant_id);
+        }
+    });
+}
+
+fn save_health(health: &TenantHealth) {
+    with_tenant_health(|stored| {
+        stored.borrow_mut().insert(health.tenant_id.clone(), health.clone());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_check_flags_after_threshold() {
+        let mut health = TenantHealth {
+            tenant_id: "tenant_1".to_string(),
+            subdomain: "harvard".to_string(),
+            canister_id: Principal::anonymous(),
+            state: HealthState::Healthy,
+            consecutive_failures: 0,
+            latency_ms: None,
+            memory_size: None,
+            cycles: None,
+            module_hash: None,
+            last_error: None,
+            last_checked: 0,
+            last_healthy: None,
+            unhealthy_since: None,
+        };
+
+        apply_check(&mut health, Some("timeout".to_string()), 3, 1);
+        apply_check(&mut health, Some("timeout".to_string()), 3, 2);
+        assert_eq!(health.state, HealthState::Degraded);
+
+        apply_check(&mut health, Some("timeout".to_string()), 3, 3);
+        apply_check(&mut health, Some("timeout".to_string()), 3, 4);
+        assert_eq!(health.state, HealthState::Unhealthy);
+        assert_eq!(health.unhealthy_since, Some(3));
+
+        apply_check(&mut health, None, 3, 5);
+        assert_eq!(health.state, HealthState::Healthy);
+        assert_eq!((health.consecutive_failures, health.last_healthy, health.unhealthy_since), (0, Some(5), None));
+    }
+}
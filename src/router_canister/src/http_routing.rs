@@ -1,9 +1,12 @@
 use crate::storage::with_tenant_registry;
 use candid::{CandidType, Deserialize, Principal};
+use shared::{Tenant, LMSError, LMSResult, utils};
 use ic_cdk::{query, update};
 use std::collections::HashMap;
 use serde::Serialize;
 
+const STATUS_PATH: &str = "/status";
+
 // Use proper IC HTTP types
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct HttpRequest {
@@ -19,7 +22,7 @@ pub struct HttpHeader {
     pub value: String,
 }
 
-#[derive(CandidType, Serialize, Clone, Debug)]
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
 pub struct HttpResponse {
     pub status_code: u16,
     pub headers: Vec<HttpHeader>,
@@ -28,7 +31,7 @@ pub struct HttpResponse {
     pub upgrade: Option<bool>,
 }
 
-#[derive(CandidType, Serialize, Clone, Debug)]
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
 pub enum StreamingStrategy {
     Callback {
         token: String,
@@ -37,40 +40,87 @@ pub enum StreamingStrategy {
 }
 
 /// HTTP request handler for subdomain routing
-/// This function processes incoming HTTP requests and routes them to appropriate tenant canisters
+/// Queries can neither make inter-canister calls nor persist request metrics, so every
+/// routed request, including redirects and errors, is upgraded to `http_request_update`
 #[query]
 pub fn http_request(req: HttpRequest) -> HttpResponse {
     ic_cdk::println!("Received HTTP request: method={}, url={}", req.method, req.url);
     
+    if let Some(response) = serve_domain_challenge(&req) {
+        return response;
+    }
+    
+    if let Some(response) = serve_status_page(&req) {
+        return response;
+    }
+
+    create_upgrade_response()
+}
+
+/// HTTP update handler that forwards requests to the tenant canister
+#[update]
+pub async fn http_request_update(req: HttpRequest) -> HttpResponse {
+    ic_cdk::println!("Received HTTP update request: method={}, url={}", req.method, req.url);
+    
+    let tenant_id = match resolve_tenant_id(&req) {
+        Ok(id) => id,
+        Err(response) => {
+            crate::metrics::record_request(None, response.status_code, response.status_code >= 400);
+            return response;
+        }
+    };
+
+    // Count under the registry ID so subdomain, alias and header lookups share one series
+    let metrics_tenant = find_tenant(&tenant_id).ok().map(|tenant| tenant.id);
+
+    if let Some(response) = alias_redirect(&req, &tenant_id) {
+        crate::metrics::record_request(metrics_tenant.as_deref(), response.status_code, false);
+        return response;
+    }
+
+    // Route to tenant canister
+    let (response, routing_failed) = match route_to_tenant(&tenant_id, req).await {
+        Ok(response) => {
+            ic_cdk::println!("Successfully routed to tenant: {}", tenant_id);
+            (response, false)
+        },
+        Err(response) => {
+            ic_cdk::println!("Failed to route to tenant {}: HTTP {}", tenant_id, response.status_code);
+            (response, true)
+        }
+    };
+
+    crate::metrics::record_request(metrics_tenant.as_deref(), response.status_code, routing_failed);
+    response
+}
+
+/// Extract and validate the tenant ID, or return the response to serve instead
+fn resolve_tenant_id(req: &HttpRequest) -> Result<String, HttpResponse> {
     // Extract tenant ID from request
-    let tenant_id = match extract_tenant_from_request(&req) {
+    let tenant_id = match extract_tenant_from_request(req) {
         Some(id) => {
             ic_cdk::println!("Extracted tenant ID: {}", id);
             id
         },
         None => {
             ic_cdk::println!("No tenant ID found, serving default response");
-            return serve_default_response();
+            return Err(serve_default_response());
         }
     };
 
+    // Reserved names (www, api, ...) belong to the platform unless a tenant already holds them
+    if crate::subdomains::is_platform_name(&tenant_id) {
+        ic_cdk::println!("Reserved name {}, serving default response", tenant_id);
+        return Err(serve_default_response());
+    }
+
     // Validate tenant ID format
     if !is_valid_tenant_id(&tenant_id) {
         ic_cdk::println!("Invalid tenant ID format: {}", tenant_id);
-        return create_error_response(400, "Invalid tenant identifier");
+        return Err(create_error_response(400, "Invalid tenant identifier"));
     }
 
-    // Route to tenant canister
-    match route_to_tenant(&tenant_id, req) {
-        Ok(response) => {
-            ic_cdk::println!("Successfully routed to tenant: {}", tenant_id);
-            response
-        },
-        Err(error) => {
-            ic_cdk::println!("Failed to route to tenant {}: {}", tenant_id, error);
-            create_error_response(404, &format!("Tenant '{}' not found", tenant_id))
-        }
-    }
+    Ok(tenant_id)
 }
 
 /// Extract tenant ID from HTTP request
@@ -95,48 +145,73 @@ fn extract_tenant_from_request(req: &HttpRequest) -> Option<String> {
 }
 
 /// Extract tenant ID from Host header
+/// Verified custom domains take precedence over the platform subdomain rules
 fn extract_from_host_header(req: &HttpRequest) -> Option<String> {
-    for header in &req.headers {
-        if header.name.to_lowercase() == "host" {
-            return parse_tenant_from_hostname(&header.value);
-        }
-    }
-    None
+    let host = find_host_header(req)?;
+    crate::domains::resolve_custom_domain(host)
+        .or_else(|| parse_tenant_from_hostname(host))
 }
 
-/// Parse tenant ID from hostname
-fn parse_tenant_from_hostname(hostname: &str) -> Option<String> {
-    let hostname = hostname.to_lowercase();
-    
-    // Handle local development: tenant.lms.localhost:4943
-    if hostname.contains("lms.localhost") {
-        let parts: Vec<&str> = hostname.split('.').collect();
-        if parts.len() >= 3 && parts[1] == "lms" && parts[2].starts_with("localhost") {
-            let tenant_id = parts[0].to_string();
-            if !tenant_id.is_empty() && tenant_id != "lms" {
-                return Some(tenant_id);
-            }
-        }
+fn find_host_header(req: &HttpRequest) -> Option<&str> {
+    req.headers.iter()
+        .find(|header| header.name.to_lowercase() == "host")
+        .map(|header| header.value.as_str())
+}
+
+/// Answer a custom-domain ownership challenge for a domain awaiting verification
+fn serve_domain_challenge(req: &HttpRequest) -> Option<HttpResponse> {
+    let path = req.url.split('?').next().unwrap_or_default();
+    if path != crate::domains::CHALLENGE_PATH {
+        return None;
     }
-    
-    // Handle production: tenant.lms.app
-    if hostname.contains("lms.app") {
-        let parts: Vec<&str> = hostname.split('.').collect();
-        if parts.len() >= 3 && parts[1] == "lms" && parts[2] == "app" {
-            let tenant_id = parts[0].to_string();
-            if !tenant_id.is_empty() && tenant_id != "lms" {
-                return Some(tenant_id);
-            }
-        }
+
+    let token = crate::domains::challenge_token(find_host_header(req)?)?;
+    Some(HttpResponse {
+        status_code: 200,
+        headers: vec![
+            HttpHeader {
+                name: "Content-Type".to_string(),
+                value: "text/plain".to_string(),
+            },
+        ],
+        body: token.into_bytes(),
+        streaming_strategy: None,
+        upgrade: Some(false),
+    })
+}
+
+/// Serve the platform health page at `/status` on hosts that do not belong to a tenant
+fn serve_status_page(req: &HttpRequest) -> Option<HttpResponse> {
+    let path = req.url.split('?').next().unwrap_or_default();
+    if path != STATUS_PATH {
+        return None;
     }
-    
-    // Handle IC canister URLs with query parameters
-    if hostname.contains("ic0.app") || hostname.contains("icp0.io") {
-        // Will be handled by URL path extraction
+    if extract_tenant_from_request(req).is_some_and(|tenant_id| !crate::subdomains::is_platform_name(&tenant_id)) {
         return None;
     }
-    
-    None
+
+    let page = crate::health::render_status_page(&crate::health::get_health_dashboard());
+    Some(HttpResponse {
+        status_code: 200,
+        headers: vec![
+            HttpHeader {
+                name: "Content-Type".to_string(),
+                value: "text/html".to_string(),
+            },
+            HttpHeader {
+                name: "Cache-Control".to_string(),
+                value: "no-store".to_string(),
+            },
+        ],
+        body: page.into_bytes(),
+        streaming_strategy: None,
+        upgrade: Some(false),
+    })
+}
+
+/// Parse tenant ID from hostname using the configured platform base domains
+fn parse_tenant_from_hostname(hostname: &str) -> Option<String> {
+    crate::domains::tenant_from_platform_host(hostname)
 }
 
 /// Extract tenant ID from X-Tenant-ID header
@@ -173,14 +248,25 @@ fn extract_from_url_path(url: &str) -> Option<String> {
 }
 
 /// Route request to appropriate tenant canister
-fn route_to_tenant(tenant_id: &str, mut req: HttpRequest) -> Result<HttpResponse, String> {
-    // Get tenant canister ID from routing table
-    let tenant_canister_id = get_tenant_canister_id(tenant_id)?;
+/// Routing failures are returned as the error response to serve
+async fn route_to_tenant(tenant_id: &str, mut req: HttpRequest) -> Result<HttpResponse, HttpResponse> {
+    let tenant = find_tenant(tenant_id).map_err(|error| {
+        ic_cdk::println!("{}", error);
+        create_error_response(404, &format!("Tenant '{}' not found", tenant_id))
+    })?;
+    
+    // Suspended tenants have their canister stopped
+    if !tenant.is_active {
+        return Ok(create_maintenance_response());
+    }
+    
+    let tenant_canister_id = tenant.canister_id.parse::<Principal>()
+        .map_err(|_| create_error_response(502, "Tenant canister misconfigured"))?;
     
-    // Add tenant context to request headers
+    // Add tenant context to request headers (the tenant canister checks it against its own ID)
     req.headers.push(HttpHeader {
         name: "X-Tenant-ID".to_string(),
-        value: tenant_id.to_string(),
+        value: tenant.id.clone(),
     });
     
     req.headers.push(HttpHeader {
@@ -189,52 +275,86 @@ fn route_to_tenant(tenant_id: &str, mut req: HttpRequest) -> Result<HttpResponse
     });
 
     // Forward request to tenant canister
-    forward_to_tenant_canister(&tenant_canister_id, req)
+    forward_to_tenant_canister(&tenant_canister_id, req).await.map_err(|error| {
+        ic_cdk::println!("Forwarding to {} failed: {}", tenant_canister_id, error);
+        create_error_response(502, "Tenant canister unavailable")
+    })
 }
 
-/// Get tenant canister ID from tenant registry
-fn get_tenant_canister_id(tenant_id: &str) -> Result<Principal, String> {
+/// Get tenant from the registry by tenant ID, falling back to its subdomain and then to a subdomain alias
+fn find_tenant(tenant_id: &str) -> Result<Tenant, String> {
+    let alias_target = crate::subdomains::get_active_alias(tenant_id).map(|alias| alias.tenant_id);
     with_tenant_registry(|registry| {
         let registry = registry.borrow();
         registry.get(&tenant_id.to_string())
-            .map(|tenant| tenant.canister_id.parse::<Principal>()
-                 .map_err(|_| "Invalid canister ID format".to_string()))
-            .unwrap_or_else(|| Err(format!("Tenant '{}' not found in registry", tenant_id)))
+            .or_else(|| registry.iter().map(|(_, tenant)| tenant).find(|tenant| tenant.subdomain == tenant_id))
+            .or_else(|| alias_target.and_then(|id| registry.get(&id)))
+            .ok_or_else(|| format!("Tenant '{}' not found in registry", tenant_id))
     })
 }
 
-/// Forward request to tenant canister
-fn forward_to_tenant_canister(canister_id: &Principal, _req: HttpRequest) -> Result<HttpResponse, String> {
-    // Note: In a real implementation, this would use ic_cdk::call to forward
-    // the request to the tenant canister. For now, we'll return a placeholder.
-    
-    // TODO: Implement actual inter-canister HTTP forwarding
-    // This requires using the management canister's http_request method
-    
-    ic_cdk::println!("Forwarding request to tenant canister: {}", canister_id);
-    
-    // For now, return a success response indicating routing worked
-    Ok(HttpResponse {
-        status_code: 200,
+/// Permanently redirect a host on a renamed tenant's former subdomain to its current one
+fn alias_redirect(req: &HttpRequest, subdomain: &str) -> Option<HttpResponse> {
+    let alias = crate::subdomains::get_active_alias(subdomain)?;
+    let current_subdomain = with_tenant_registry(|registry| registry.borrow().get(&alias.tenant_id))?.subdomain;
+    let new_host = crate::domains::rewrite_platform_host(find_host_header(req)?, &current_subdomain)?;
+
+    Some(HttpResponse {
+        status_code: 301,
         headers: vec![
             HttpHeader {
-                name: "Content-Type".to_string(),
-                value: "application/json".to_string(),
-            },
-            HttpHeader {
-                name: "X-Routed-To".to_string(),
-                value: canister_id.to_string(),
+                name: "Location".to_string(),
+                value: format!("//{}{}", new_host, req.url),
             },
         ],
-        body: format!(
-            r#"{{"message": "Request routed to tenant canister", "tenant_canister": "{}", "status": "success"}}"#,
-            canister_id
-        ).into_bytes(),
+        body: vec![],
         streaming_strategy: None,
         upgrade: Some(false),
     })
 }
 
+/// Forward request to tenant canister
+/// Calls the tenant's `http_request` and follows its upgrade to `http_request_update`
+async fn forward_to_tenant_canister(canister_id: &Principal, req: HttpRequest) -> Result<HttpResponse, String> {
+    ic_cdk::println!("Forwarding request to tenant canister: {}", canister_id);
+    
+    let (mut response,): (HttpResponse,) = ic_cdk::call(*canister_id, "http_request", (req.clone(),))
+        .await
+        .map_err(|(code, msg)| format!("http_request call failed: {:?} - {}", code, msg))?;
+    
+    if response.upgrade == Some(true) {
+        let (upgraded,): (HttpResponse,) = ic_cdk::call(*canister_id, "http_request_update", (req,))
+            .await
+            .map_err(|(code, msg)| format!("http_request_update call failed: {:?} - {}", code, msg))?;
+        response = upgraded;
+    }
+    
+    response.headers.push(HttpHeader {
+        name: "X-Routed-To".to_string(),
+        value: canister_id.to_string(),
+    });
+    
+    // Streaming callbacks point at the tenant canister and cannot be relayed by the router
+    Ok(HttpResponse {
+        status_code: response.status_code,
+        headers: response.headers,
+        body: response.body,
+        streaming_strategy: None,
+        upgrade: None,
+    })
+}
+
+/// Ask the HTTP gateway to retry the request as an update call
+fn create_upgrade_response() -> HttpResponse {
+    HttpResponse {
+        status_code: 200,
+        headers: vec![],
+        body: vec![],
+        streaming_strategy: None,
+        upgrade: Some(true),
+    }
+}
+
 /// Serve default response for requests without tenant context
 fn serve_default_response() -> HttpResponse {
     let body = r#"
@@ -278,6 +398,16 @@ fn serve_default_response() -> HttpResponse {
     }
 }
 
+/// Create maintenance response for suspended tenants
+fn create_maintenance_response() -> HttpResponse {
+    let mut response = create_error_response(503, "This university is temporarily unavailable for maintenance");
+    response.headers.push(HttpHeader {
+        name: "Retry-After".to_string(),
+        value: "3600".to_string(),
+    });
+    response
+}
+
 /// Create error response
 fn create_error_response(status_code: u16, message: &str) -> HttpResponse {
     let body = format!(
@@ -299,20 +429,15 @@ fn create_error_response(status_code: u16, message: &str) -> HttpResponse {
     }
 }
 
-/// Validate tenant ID format
+/// Validate tenant ID format: a DNS-safe subdomain, or a generated `tenant_<number>` ID
 fn is_valid_tenant_id(tenant_id: &str) -> bool {
-    if tenant_id.is_empty() || tenant_id.len() > 63 || tenant_id.len() < 3 {
-        return false;
-    }
-    
-    // Must start and end with alphanumeric
-    let chars: Vec<char> = tenant_id.chars().collect();
-    if !chars[0].is_alphanumeric() || !chars[chars.len() - 1].is_alphanumeric() {
-        return false;
+    if utils::is_valid_subdomain(tenant_id) {
+        return true;
     }
-    
-    // Can contain alphanumeric, hyphens, and underscores
-    tenant_id.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+
+    tenant_id.strip_prefix("tenant_")
+        .map(|suffix| !suffix.is_empty() && suffix.len() <= 32 && suffix.chars().all(|c| c.is_ascii_digit()))
+        .unwrap_or(false)
 }
 
 /// Get routing statistics
@@ -321,28 +446,33 @@ pub fn get_routing_stats() -> HashMap<String, u64> {
     let mut stats = HashMap::new();
     
     with_tenant_registry(|registry| {
-        let registry = registry.borrow();
-        stats.insert("total_tenants".to_string(), registry.len());
-        
-        // Add per-tenant request counts (would need to track these)
-        for (tenant_id, _) in registry.iter() {
-            stats.insert(format!("tenant_{}_requests", tenant_id), 0); // Placeholder
-        }
+        stats.insert("total_tenants".to_string(), registry.borrow().len());
     });
     
+    // Request counts over the retained daily buckets
+    for totals in crate::metrics::get_request_totals(0) {
+        let key = match totals.tenant_id {
+            Some(tenant_id) => format!("tenant_{}_requests", tenant_id),
+            None => "unrouted_requests".to_string(),
+        };
+        stats.insert(key, totals.metrics.requests);
+    }
+    
     stats
 }
 
 /// Update routing table entry
 #[update]
-pub fn update_routing_entry(tenant_id: String, canister_id: String) -> Result<(), String> {
+pub fn update_routing_entry(tenant_id: String, canister_id: String) -> LMSResult<()> {
+    crate::auth::require_operator()?;
+    
     if !is_valid_tenant_id(&tenant_id) {
-        return Err("Invalid tenant ID format".to_string());
+        return Err(LMSError::ValidationError("Invalid tenant ID format".to_string()));
     }
     
     // Validate canister ID format
     let _canister_principal = canister_id.parse::<Principal>()
-        .map_err(|_| "Invalid canister ID format")?;
+        .map_err(|_| LMSError::ValidationError("Invalid canister ID format".to_string()))?;
     
     with_tenant_registry(|registry| {
         let mut registry = registry.borrow_mut();
@@ -352,7 +482,7 @@ pub fn update_routing_entry(tenant_id: String, canister_id: String) -> Result<()
             registry.insert(tenant_id.clone(), tenant);
             Ok(())
         } else {
-            Err(format!("Tenant '{}' not found", tenant_id))
+            Err(LMSError::NotFound(format!("Tenant '{}' not found", tenant_id)))
         }
     })
 }
@@ -7,17 +7,53 @@ mod template;
 mod canister_management;
 mod tenant_management;
 mod inspection;
+mod upgrade;
+mod wasm_registry;
+mod cycles;
+mod scheduler;
+mod lifecycle;
+mod decommission;
+mod auth;
+mod domains;
+mod subdomains;
+mod metrics;
+mod repair;
+mod provisioning;
+mod tenant_settings;
+mod analytics;
+mod directory;
+mod schema;
+mod billing;
+mod applications;
+mod ownership;
+mod backup;
+mod health;
 mod api;
 mod http_routing;
 
 // Re-export public types for external use
 pub use types::{
     TemplateConfig, RouterStats, CycleInfo, 
-    TenantRegistryInspection, RoutingTableInspection, FullSystemInspection
+    TenantRegistryInspection, RoutingTableInspection, FullSystemInspection,
+    FleetUpgrade, FleetUpgradeStatus, TenantUpgradeRecord, TenantUpgradeStatus,
+    WasmModuleInfo, WasmUploadStatus,
+    CyclesMonitorConfig, TenantCyclesStatus, CyclesTopUp, CyclesAlert,
+    TenantSuspension, TenantDecommission, DecommissionStatus, DecommissionStep,
+    OperatorRole, PlatformOperator, CustomDomain, CustomDomainStatus, BaseDomain,
+    SubdomainAlias, SubdomainPolicy,
+    MetricsGranularity, RequestMetrics, MetricsBucket, TenantRequestTotals,
+    RepairAction, RepairActionRecord, RepairRun,
+    ProvisioningStep, ProvisioningStatus, TenantProvisioning, TenantSettingsUpdate,
+    TenantSummarySample, PlatformSummary, SummaryCollection,
+    PublicTenant, TenantDirectoryQuery, TenantDirectoryPage,
+    PriceTable, UsageMeter, StatementStatus, StatementLine, BillingStatement, StatementQuery, StatementFormat,
+    ApplicationStatus, TenantApplication, TenantApplicationRequest,
+    OwnershipChange, OwnershipAuditEntry, BackupStatus, TenantBackup, RestoreStatus, TenantRestore,
+    HealthMonitorConfig, HealthState, TenantHealth, HealthDashboard
 };
 
 // Re-export HTTP routing functions
-pub use http_routing::{http_request, get_routing_stats, update_routing_entry};
+pub use http_routing::{http_request, http_request_update, get_routing_stats, update_routing_entry};
 
 // The API functions are defined in the api module and are automatically 
 // exported as canister endpoints through the #[update] and #[query] macros
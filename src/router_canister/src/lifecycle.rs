@@ -0,0 +1,102 @@
+use candid::Principal;
+use ic_cdk::caller;
+use shared::{Tenant, LMSError, LMSResult, current_time};
+use crate::types::TenantSuspension;
+use crate::storage::{with_tenant_registry, with_tenant_suspensions};
+use crate::canister_management;
+
+/// Suspend a tenant: stop its canister and mark it inactive so the router serves a maintenance page
+pub async fn suspend_tenant(tenant_id: String, reason: String) -> LMSResult<Tenant> {
+    if reason.trim().is_empty() {
+        return Err(LMSError::ValidationError("A suspension reason is required".to_string()));
+    }
+
+    let tenant = get_tenant(&tenant_id)?;
+    if !tenant.is_active {
+        return Err(LMSError::ValidationError(format!("Tenant '{}' is already suspended", tenant_id)));
+    }
+
+    let canister_id = parse_canister_id(&tenant)?;
+    canister_management::stop_canister(canister_id).await
+        .map_err(LMSError::InternalError)?;
+
+    let suspension = TenantSuspension {
+        tenant_id: tenant_id.clone(),
+        reason,
+        suspended_by: caller(),
+        suspended_at: current_time(),
+        reactivated_by: None,
+        reactivated_at: None,
+    };
+    with_tenant_suspensions(|suspensions| {
+        suspensions.borrow_mut().insert(tenant_id.clone(), suspension.clone());
+    });
+
+    let tenant = set_tenant_active(&tenant_id, false)?;
+    ic_cdk::println!("Suspended tenant {} (canister: {}): {}", tenant_id, canister_id, suspension.reason);
+    Ok(tenant)
+}
+
+/// Reactivate a suspended tenant: start its canister and resume routing
+pub async fn reactivate_tenant(tenant_id: String) -> LMSResult<Tenant> {
+    let tenant = get_tenant(&tenant_id)?;
+    if tenant.is_active {
+        return Err(LMSError::ValidationError(format!("Tenant '{}' is not suspended", tenant_id)));
+    }
+
+    let canister_id = parse_canister_id(&tenant)?;
+    canister_management::start_canister(canister_id).await
+        .map_err(LMSError::InternalError)?;
+
+    with_tenant_suspensions(|suspensions| {
+        let mut suspensions = suspensions.borrow_mut();
+        if let Some(mut suspension) = suspensions.get(&tenant_id) {
+            suspension.reactivated_by = Some(caller());
+            suspension.reactivated_at = Some(current_time());
+            suspensions.insert(tenant_id.clone(), suspension);
+        }
+    });
+
+    // Settings and name changed while suspended were only stored in the registry
+    if let Err(e) = crate::tenant_settings::push_settings(canister_id, &tenant.settings).await {
+        ic_cdk::println!("Failed to push settings to reactivated tenant {}: {}", tenant_id, e);
+    }
+    if let Err(e) = crate::tenant_settings::push_name(canister_id, &tenant.name).await {
+        ic_cdk::println!("Failed to push name to reactivated tenant {}: {}", tenant_id, e);
+    }
+
+    let tenant = set_tenant_active(&tenant_id, true)?;
+    ic_cdk::println!("Reactivated tenant {} (canister: {})", tenant_id, canister_id);
+    Ok(tenant)
+}
+
+/// Get the latest suspension record of a tenant
+pub fn get_tenant_suspension(tenant_id: String) -> LMSResult<TenantSuspension> {
+    with_tenant_suspensions(|suspensions| {
+        suspensions.borrow()
+            .get(&tenant_id)
+            .ok_or_else(|| LMSError::NotFound(format!("Tenant '{}' has never been suspended", tenant_id)))
+    })
+}
+
+pub(crate) fn get_tenant(tenant_id: &str) -> LMSResult<Tenant> {
+    with_tenant_registry(|registry| registry.borrow().get(&tenant_id.to_string()))
+        .ok_or_else(|| LMSError::NotFound(format!("Tenant '{}' not found", tenant_id)))
+}
+
+pub(crate) fn parse_canister_id(tenant: &Tenant) -> LMSResult<Principal> {
+    Principal::from_text(&tenant.canister_id)
+        .map_err(|_| LMSError::ValidationError("Invalid canister ID".to_string()))
+}
+
+pub(crate) fn set_tenant_active(tenant_id: &str, is_active: bool) -> LMSResult<Tenant> {
+    with_tenant_registry(|registry| {
+        let mut registry = registry.borrow_mut();
+        let mut tenant = registry.get(&tenant_id.to_string())
+            .ok_or_else(|| LMSError::NotFound(format!("Tenant '{}' not found", tenant_id)))?;
+        tenant.is_active = is_active;
+        tenant.updated_at = current_time();
+        registry.insert(tenant_id.to_string(), tenant.clone());
+        Ok(tenant)
+    })
+}
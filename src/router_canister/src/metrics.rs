@@ -0,0 +1,158 @@
+use shared::current_time;
+use crate::types::{MetricsBucket, MetricsGranularity, RequestMetrics, TenantRequestTotals};
+use crate::storage::with_request_metrics;
+
+const NANOS_PER_HOUR: u64 = 60 * 60 * 1_000_000_000;
+const NANOS_PER_DAY: u64 = 24 * NANOS_PER_HOUR;
+const HOURLY_RETENTION: u64 = 7 * NANOS_PER_DAY;
+const DAILY_RETENTION: u64 = 90 * NANOS_PER_DAY;
+// Key used for requests that never resolved to a tenant (cannot collide with a tenant ID)
+const UNROUTED_KEY: &str = "_unrouted";
+
+/// Count a request served by `http_request_update` in the hourly and daily buckets
+pub fn record_request(tenant_id: Option<&str>, status_code: u16, routing_failed: bool) {
+    let now = current_time();
+    for granularity in [MetricsGranularity::Hourly, MetricsGranularity::Daily] {
+        let bucket_start = bucket_start(granularity, now);
+        let key = bucket_key(tenant_id, granularity, bucket_start);
+
+        with_request_metrics(|metrics| {
+            let mut metrics = metrics.borrow_mut();
+            let mut bucket = metrics.get(&key).unwrap_or_else(|| MetricsBucket {
+                tenant_id: tenant_id.map(|id| id.to_string()),
+                granularity,
+                bucket_start,
+                metrics: RequestMetrics::default(),
+            });
+            count(&mut bucket.metrics, status_code, routing_failed);
+            metrics.insert(key, bucket);
+        });
+    }
+}
+
+/// Get a tenant's buckets of one granularity, oldest first, optionally limited to `[from, to)`
+pub fn get_tenant_metrics(tenant_id: Option<String>, granularity: MetricsGranularity, from: Option<u64>, to: Option<u64>) -> Vec<MetricsBucket> {
+    let prefix = series_prefix(tenant_id.as_deref(), granularity);
+    let from = from.unwrap_or(0);
+    let to = to.unwrap_or(u64::MAX);
+
+    with_request_metrics(|metrics| {
+        metrics.borrow()
+            .range(bucket_key(tenant_id.as_deref(), granularity, bucket_start(granularity, from))..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, bucket)| bucket)
+            .filter(|bucket| bucket.bucket_start >= from && bucket.bucket_start < to)
+            .collect()
+    })
+}
+
+/// Per-tenant totals from the daily buckets covering `since` onwards
+pub fn get_request_totals(since: u64) -> Vec<TenantRequestTotals> {
+    let first_bucket = bucket_start(MetricsGranularity::Daily, since);
+    let mut totals: Vec<TenantRequestTotals> = Vec::new();
+    with_request_metrics(|metrics| {
+        for (_, bucket) in metrics.borrow().iter() {
+            if bucket.granularity != MetricsGranularity::Daily || bucket.bucket_start < first_bucket {
+                continue;
+            }
+            match totals.iter_mut().find(|total| total.tenant_id == bucket.tenant_id) {
+                Some(total) => add(&mut total.metrics, &bucket.metrics),
+                None => totals.push(TenantRequestTotals {
+                    tenant_id: bucket.tenant_id,
+                    since: first_bucket,
+                    metrics: bucket.metrics,
+                }),
+            }
+        }
+    });
+    totals
+}
+
+/// Background job entry point used by the scheduler: drop buckets past their retention
+pub async fn prune_request_metrics() {
+    let now = current_time();
+    let expired: Vec<String> = with_request_metrics(|metrics| {
+        metrics.borrow()
+            .iter()
+            .filter(|(_, bucket)| {
+                let retention = match bucket.granularity {
+                    MetricsGranularity::Hourly => HOURLY_RETENTION,
+                    MetricsGranularity::Daily => DAILY_RETENTION,
+                };
+                bucket.bucket_start.saturating_add(retention) < now
+            })
+            .map(|(key, _)| key)
+            .collect()
+    });
+
+    with_request_metrics(|metrics| {
+        let mut metrics = metrics.borrow_mut();
+        for key in &expired {
+            metrics.remove(key);
+        }
+    });
+}
+
+fn count(metrics: &mut RequestMetrics, status_code: u16, routing_failed: bool) {
+    metrics.requests += 1;
+    match status_code {
+        200..=299 => metrics.status_2xx += 1,
+        300..=399 => metrics.status_3xx += 1,
+        400..=499 => metrics.status_4xx += 1,
+        500..=599 => metrics.status_5xx += 1,
+        _ => {}
+    }
+    if routing_failed {
+        metrics.routing_failures += 1;
+    }
+}
+
+fn add(total: &mut RequestMetrics, other: &RequestMetrics) {
+    total.requests += other.requests;
+    total.status_2xx += other.status_2xx;
+    total.status_3xx += other.status_3xx;
+    total.status_4xx += other.status_4xx;
+    total.status_5xx += other.status_5xx;
+    total.routing_failures += other.routing_failures;
+}
+
+fn bucket_start(granularity: MetricsGranularity, time: u64) -> u64 {
+    let size = match granularity {
+        MetricsGranularity::Hourly => NANOS_PER_HOUR,
+        MetricsGranularity::Daily => NANOS_PER_DAY,
+    };
+    time - time % size
+}
+
+fn series_prefix(tenant_id: Option<&str>, granularity: MetricsGranularity) -> String {
+    let granularity = match granularity {
+        MetricsGranularity::Hourly => "h",
+        MetricsGranularity::Daily => "d",
+    };
+    format!("{}#{}#", tenant_id.unwrap_or(UNROUTED_KEY), granularity)
+}
+
+fn bucket_key(tenant_id: Option<&str>, granularity: MetricsGranularity, bucket_start: u64) -> String {
+    // Zero-padded so keys sort chronologically within a series
+    format!("{}{:020}", series_prefix(tenant_id, granularity), bucket_start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_by_status_class() {
+        let mut metrics = RequestMetrics::default();
+        count(&mut metrics, 200, false);
+        count(&mut metrics, 301, false);
+        count(&mut metrics, 404, true);
+        count(&mut metrics, 502, true);
+
+        assert_eq!(metrics.requests, 4);
+        assert_eq!((metrics.status_2xx, metrics.status_3xx, metrics.status_4xx, metrics.status_5xx), (1, 1, 1, 1));
+        assert_eq!(metrics.routing_failures, 2);
+        assert_eq!(bucket_start(MetricsGranularity::Hourly, NANOS_PER_HOUR + 5), NANOS_PER_HOUR);
+        assert!(bucket_key(Some("a"), MetricsGranularity::Daily, 9) < bucket_key(Some("a"), MetricsGranularity::Daily, 10));
+    }
+}
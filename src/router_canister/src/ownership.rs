@@ -0,0 +1,233 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use candid::Principal;
+use ic_cdk::{caller, id};
+use shared::{Tenant, LMSError, LMSResult, current_time};
+use crate::types::{OwnershipAuditEntry, OwnershipChange};
+use crate::storage::{with_ownership_audit, with_tenant_registry};
+use crate::lifecycle::{get_tenant, parse_canister_id};
+use crate::canister_management;
+
+// The IC limit on controllers per canister
+const MAX_CONTROLLERS: usize = 10;
+
+// Controller lists are read, changed and written back across calls, so one change per tenant at a time
+thread_local! {
+    static CHANGES_IN_FLIGHT: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+struct TenantLock(String);
+
+impl TenantLock {
+    fn acquire(tenant_id: &str) -> LMSResult<Self> {
+        let acquired = CHANGES_IN_FLIGHT.with(|tenants| tenants.borrow_mut().insert(tenant_id.to_string()));
+        if !acquired {
+            return Err(LMSError::ValidationError(format!(
+                "An ownership change for tenant '{}' is already in progress", tenant_id
+            )));
+        }
+        Ok(Self(tenant_id.to_string()))
+    }
+}
+
+impl Drop for TenantLock {
+    fn drop(&mut self) {
+        CHANGES_IN_FLIGHT.with(|tenants| tenants.borrow_mut().remove(&self.0));
+    }
+}
+
+/// Current controllers of a tenant's canister
+pub async fn list_tenant_controllers(tenant_id: String) -> LMSResult<Vec<Principal>> {
+    let tenant = get_tenant(&tenant_id)?;
+    let canister_id = parse_canister_id(&tenant)?;
+    canister_management::get_controllers(canister_id).await
+        .map_err(LMSError::InternalError)
+}
+
+/// Add a controller to a tenant's canister
+pub async fn add_tenant_controller(tenant_id: String, controller: Principal) -> LMSResult<Vec<Principal>> {
+    let _lock = TenantLock::acquire(&tenant_id)?;
+    let canister_id = parse_canister_id(&get_tenant(&tenant_id)?)?;
+    let current = canister_management::get_controllers(canister_id).await
+        .map_err(LMSError::InternalError)?;
+    let controllers = controllers_after(&current, Some(controller), None, id())
+        .map_err(LMSError::ValidationError)?;
+
+    canister_management::set_controllers(canister_id, controllers.clone()).await
+        .map_err(LMSError::InternalError)?;
+    record_change(&tenant_id, OwnershipChange::ControllerAdded(controller), None);
+
+    ic_cdk::println!("Added controller {} to tenant {} (canister: {})", controller, tenant_id, canister_id);
+    Ok(controllers)
+}
+
+/// Remove a controller from a tenant's canister. The router itself cannot be removed.
+pub async fn remove_tenant_controller(tenant_id: String, controller: Principal) -> LMSResult<Vec<Principal>> {
+    let _lock = TenantLock::acquire(&tenant_id)?;
+    let canister_id = parse_canister_id(&get_tenant(&tenant_id)?)?;
+    let current = canister_management::get_controllers(canister_id).await
+        .map_err(LMSError::InternalError)?;
+    let controllers = controllers_after(&current, None, Some(controller), id())
+        .map_err(LMSError::ValidationError)?;
+
+    canister_management::set_controllers(canister_id, controllers.clone()).await
+        .map_err(LMSError::InternalError)?;
+    record_change(&tenant_id, OwnershipChange::ControllerRemoved(controller), None);
+
+    ic_cdk::println!("Removed controller {} from tenant {} (canister: {})", controller, tenant_id, canister_id);
+    Ok(controllers)
+}
+
+/// Hand a tenant to a new admin. The new admin is made a controller first; the tenant canister
+/// then swaps its admin and TenantAdmin user in one message, and the registry follows. Unless
+/// `keep_previous_admin` is set, the previous admin is deactivated and loses its controller.
+pub async fn transfer_tenant_ownership(
+    tenant_id: String,
+    new_admin: Principal,
+    keep_previous_admin: bool,
+) -> LMSResult<Tenant> {
+    if new_admin == Principal::anonymous() {
+        return Err(LMSError::ValidationError("The anonymous principal cannot be an admin".to_string()));
+    }
+    let _lock = TenantLock::acquire(&tenant_id)?;
+    let tenant = get_tenant(&tenant_id)?;
+    if !tenant.is_active {
+        return Err(LMSError::ValidationError(format!("Tenant '{}' is suspended", tenant_id)));
+    }
+    let canister_id = parse_canister_id(&tenant)?;
+
+    // An extra controller is harmless if the transfer fails, so it is added before anything else
+    let current = canister_management::get_controllers(canister_id).await
+        .map_err(LMSError::InternalError)?;
+    if !current.contains(&new_admin) {
+        let controllers = controllers_after(&current, Some(new_admin), None, id())
+            .map_err(LMSError::ValidationError)?;
+        canister_management::set_controllers(canister_id, controllers).await
+            .map_err(LMSError::InternalError)?;
+    }
+
+    let (result,): (LMSResult<Principal>,) =
+        ic_cdk::call(canister_id, "transfer_tenant_admin", (new_admin, keep_previous_admin)).await
+            .map_err(|(code, msg)| LMSError::InternalError(format!("Admin transfer call failed: {:?} - {}", code, msg)))?;
+    let previous_admin = result?;
+
+    // Re-read after the calls so concurrent metadata changes are kept
+    let mut tenant = get_tenant(&tenant_id)?;
+    tenant.admin_ids = admin_ids_after(&tenant.admin_ids, previous_admin, new_admin, keep_previous_admin);
+    tenant.updated_at = current_time();
+    with_tenant_registry(|registry| {
+        registry.borrow_mut().insert(tenant.id.clone(), tenant.clone());
+    });
+
+    let mut note = None;
+    if !keep_previous_admin && previous_admin != new_admin {
+        if let Err(e) = remove_previous_admin_controller(canister_id, previous_admin).await {
+            ic_cdk::println!("Tenant {} still lists previous admin {} as a controller: {}", tenant_id, previous_admin, e);
+            note = Some(format!("Previous admin is still a controller: {}", e));
+        }
+    }
+    record_change(&tenant_id, OwnershipChange::AdminTransferred { from: previous_admin, to: new_admin }, note);
+
+    ic_cdk::println!("Transferred tenant {} from {} to {}", tenant_id, previous_admin, new_admin);
+    Ok(tenant)
+}
+
+/// Ownership changes, newest first, optionally only those of one tenant
+pub fn list_ownership_audit(tenant_id: Option<String>) -> Vec<OwnershipAuditEntry> {
+    with_ownership_audit(|entries| {
+        entries.borrow()
+            .iter()
+            .rev()
+            .map(|(_, entry)| entry)
+            .filter(|entry| tenant_id.as_ref().is_none_or(|id| &entry.tenant_id == id))
+            .collect()
+    })
+}
+
+async fn remove_previous_admin_controller(canister_id: Principal, previous_admin: Principal) -> Result<(), String> {
+    let current = canister_management::get_controllers(canister_id).await?;
+    if !current.contains(&previous_admin) {
+        return Ok(());
+    }
+    let controllers = controllers_after(&current, None, Some(previous_admin), id())?;
+    canister_management::set_controllers(canister_id, controllers).await
+}
+
+/// Controllers after adding and/or removing one; the router must always remain a controller
+fn controllers_after(
+    current: &[Principal],
+    add: Option<Principal>,
+    remove: Option<Principal>,
+    router: Principal,
+) -> Result<Vec<Principal>, String> {
+    let mut controllers = current.to_vec();
+    if let Some(controller) = remove {
+        if controller == router {
+            return Err("The router cannot be removed as a controller".to_string());
+        }
+        if !controllers.contains(&controller) {
+            return Err(format!("{} is not a controller", controller));
+        }
+        controllers.retain(|existing| *existing != controller);
+    }
+    if let Some(controller) = add {
+        if controller == Principal::anonymous() {
+            return Err("The anonymous principal cannot be a controller".to_string());
+        }
+        if controllers.contains(&controller) {
+            return Err(format!("{} is already a controller", controller));
+        }
+        if controllers.len() >= MAX_CONTROLLERS {
+            return Err(format!("A canister can have at most {} controllers", MAX_CONTROLLERS));
+        }
+        controllers.push(controller);
+    }
+    Ok(controllers)
+}
+
+/// Registry admins after a transfer: the new admin first, the previous one kept only if asked
+fn admin_ids_after(admin_ids: &[String], previous: Principal, new: Principal, keep_previous: bool) -> Vec<String> {
+    let (previous, new) = (previous.to_text(), new.to_text());
+    let mut updated = vec![new.clone()];
+    updated.extend(admin_ids.iter()
+        .filter(|admin| **admin != new && (keep_previous || **admin != previous))
+        .cloned());
+    updated
+}
+
+fn record_change(tenant_id: &str, change: OwnershipChange, note: Option<String>) {
+    with_ownership_audit(|entries| {
+        let mut entries = entries.borrow_mut();
+        let id = entries.last_key_value().map(|(id, _)| id + 1).unwrap_or(1);
+        entries.insert(id, OwnershipAuditEntry {
+            id,
+            tenant_id: tenant_id.to_string(),
+            change,
+            changed_by: caller(),
+            changed_at: current_time(),
+            note,
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_controller_and_admin_changes() {
+        let router = Principal::from_slice(&[1]);
+        let admin = Principal::from_slice(&[2]);
+        let new_admin = Principal::from_slice(&[3]);
+
+        let controllers = controllers_after(&[router, admin], Some(new_admin), Some(admin), router).unwrap();
+        assert_eq!(controllers, vec![router, new_admin]);
+        assert!(controllers_after(&controllers, None, Some(router), router).is_err());
+        assert!(controllers_after(&controllers, Some(new_admin), None, router).is_err());
+        assert!(controllers_after(&controllers, None, Some(admin), router).is_err());
+
+        let admin_ids = vec![admin.to_text(), "other".to_string()];
+        assert_eq!(admin_ids_after(&admin_ids, admin, new_admin, false), vec![new_admin.to_text(), "other".to_string()]);
+        assert_eq!(admin_ids_after(&admin_ids, admin, new_admin, true).len(), 3);
+    }
+}
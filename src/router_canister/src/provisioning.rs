@@ -0,0 +1,318 @@
+use candid::Principal;
+use ic_cdk::caller;
+use shared::{Tenant, TenantSettings, TemplateTrack, LMSError, LMSResult, current_time};
+use crate::types::{ProvisioningStatus, ProvisioningStep, TenantProvisioning};
+use crate::storage::{with_decommissions, with_provisionings, with_routing_table, with_tenant_registry};
+use crate::template::{get_template_config, get_deployed_tenant_canister, install_latest_template, load_release_module, resolve_track};
+use crate::canister_management;
+
+const NANOS_PER_MINUTE: u64 = 60 * 1_000_000_000;
+// Far longer than one attempt takes; an attempt that trapped releases the record after this
+const LEASE_MINUTES: u64 = 10;
+
+/// Record a new provisioning and run it. A failed provisioning keeps its progress
+/// and holds its subdomain until it is resumed or cancelled.
+pub async fn start_provisioning(
+    subdomain: String,
+    university_name: String,
+    admin_principal: Principal,
+    template_track: Option<TemplateTrack>,
+) -> LMSResult<Tenant> {
+    let record = create_provisioning(subdomain, university_name, admin_principal, template_track, None)?;
+    run_provisioning(record).await
+}
+
+/// Record a new provisioning without running it. The record holds its subdomain from here on.
+pub fn create_provisioning(
+    subdomain: String,
+    university_name: String,
+    admin_principal: Principal,
+    template_track: Option<TemplateTrack>,
+    settings: Option<TenantSettings>,
+) -> LMSResult<TenantProvisioning> {
+    // Fail before creating anything when no template is available
+    template_canister_id()?;
+    resolve_track(&template_track.clone().unwrap_or_default())?;
+
+    let now = current_time();
+    let request_id = next_request_id();
+    let record = TenantProvisioning {
+        request_id,
+        tenant_id: tenant_id_for(request_id),
+        subdomain,
+        university_name,
+        admin_principal,
+        requested_by: caller(),
+        requested_at: now,
+        next_step: ProvisioningStep::CreateCanister,
+        status: ProvisioningStatus::InProgress,
+        canister_id: None,
+        attempts: 0,
+        lease_until: 0,
+        updated_at: now,
+        completed_at: None,
+        template_track,
+        template_version: None,
+        module_hash: None,
+        settings,
+    };
+    save_provisioning(&record);
+
+    ic_cdk::println!("Provisioning {} started: {} -> {}", request_id, record.subdomain, record.tenant_id);
+    Ok(record)
+}
+
+/// Continue a provisioning from its last completed step.
+/// Resuming a completed provisioning returns its tenant, so retries are safe.
+pub async fn resume_provisioning(request_id: u64) -> LMSResult<Tenant> {
+    let record = get_provisioning(request_id)?;
+    match record.status {
+        ProvisioningStatus::Completed => crate::lifecycle::get_tenant(&record.tenant_id),
+        ProvisioningStatus::Cancelled => Err(LMSError::ValidationError(format!(
+            "Provisioning {} was cancelled", request_id
+        ))),
+        _ => run_provisioning(record).await,
+    }
+}
+
+/// Abandon an unfinished provisioning, deleting its canister and releasing its subdomain
+pub async fn cancel_provisioning(request_id: u64) -> LMSResult<TenantProvisioning> {
+    let mut record = get_provisioning(request_id)?;
+    if matches!(record.status, ProvisioningStatus::Completed | ProvisioningStatus::Cancelled) {
+        return Err(LMSError::ValidationError(format!("Provisioning {} is already finished", request_id)));
+    }
+    if record.lease_until > current_time() {
+        return Err(LMSError::ValidationError(format!("Provisioning {} is running", request_id)));
+    }
+
+    if let Some(canister_id) = record.canister_id {
+        record.lease_until = current_time() + LEASE_MINUTES * NANOS_PER_MINUTE;
+        save_provisioning(&record);
+
+        // The IC only deletes stopped canisters
+        let result = match canister_management::stop_canister(canister_id).await {
+            Ok(()) => canister_management::delete_canister(canister_id).await,
+            Err(e) => Err(e),
+        };
+        record.lease_until = 0;
+        if let Err(reason) = result {
+            record.status = ProvisioningStatus::Failed(reason.clone());
+            record.updated_at = current_time();
+            save_provisioning(&record);
+            return Err(LMSError::InternalError(reason));
+        }
+    }
+
+    record.status = ProvisioningStatus::Cancelled;
+    record.updated_at = current_time();
+    record.completed_at = Some(record.updated_at);
+    save_provisioning(&record);
+
+    ic_cdk::println!("Provisioning {} cancelled by {}", request_id, caller());
+    Ok(record)
+}
+
+/// Get a provisioning by request ID
+pub fn get_provisioning(request_id: u64) -> LMSResult<TenantProvisioning> {
+    with_provisionings(|records| {
+        records.borrow()
+            .get(&request_id)
+            .ok_or_else(|| LMSError::NotFound(format!("Provisioning {} not found", request_id)))
+    })
+}
+
+/// List in-flight and failed provisionings, plus finished ones when `include_finished` is set
+pub fn list_provisionings(include_finished: bool) -> Vec<TenantProvisioning> {
+    with_provisionings(|records| {
+        records.borrow()
+            .iter()
+            .map(|(_, record)| record)
+            .filter(|record| include_finished || is_unfinished(record))
+            .collect()
+    })
+}
+
+/// Whether an unfinished provisioning holds this subdomain
+pub fn is_subdomain_pending(subdomain: &str) -> bool {
+    with_provisionings(|records| {
+        records.borrow()
+            .iter()
+            .any(|(_, record)| record.subdomain == subdomain && is_unfinished(&record))
+    })
+}
+
+/// Run the remaining steps, persisting progress after each one
+async fn run_provisioning(mut record: TenantProvisioning) -> LMSResult<Tenant> {
+    let now = current_time();
+    if record.lease_until > now {
+        return Err(LMSError::ValidationError(format!("Provisioning {} is already running", record.request_id)));
+    }
+    record.status = ProvisioningStatus::InProgress;
+    record.attempts += 1;
+    record.lease_until = now + LEASE_MINUTES * NANOS_PER_MINUTE;
+    save_provisioning(&record);
+
+    while record.next_step != ProvisioningStep::Done {
+        let step = record.next_step.clone();
+        let result = match step {
+            ProvisioningStep::CreateCanister => create_canister(&mut record).await,
+            ProvisioningStep::InstallCode => install_code(&mut record).await,
+            ProvisioningStep::ApplySettings => apply_settings(&record).await,
+            ProvisioningStep::Register => register_tenant(&record),
+            ProvisioningStep::Done => Ok(()),
+        };
+
+        record.updated_at = current_time();
+        match result {
+            Ok(()) => {
+                record.next_step = match step {
+                    ProvisioningStep::CreateCanister => ProvisioningStep::InstallCode,
+                    ProvisioningStep::InstallCode => ProvisioningStep::ApplySettings,
+                    ProvisioningStep::ApplySettings => ProvisioningStep::Register,
+                    _ => ProvisioningStep::Done,
+                };
+                save_provisioning(&record);
+            },
+            Err(reason) => {
+                let message = format!("{:?} failed: {}", step, reason);
+                record.status = ProvisioningStatus::Failed(message.clone());
+                record.lease_until = 0;
+                save_provisioning(&record);
+
+                ic_cdk::println!("Provisioning {} failed: {}", record.request_id, message);
+                return Err(LMSError::InternalError(format!(
+                    "Provisioning {} failed: {}. Resume it with resume_provisioning", record.request_id, message
+                )));
+            }
+        }
+    }
+
+    record.status = ProvisioningStatus::Completed;
+    record.lease_until = 0;
+    record.completed_at = Some(record.updated_at);
+    save_provisioning(&record);
+
+    let tenant = crate::lifecycle::get_tenant(&record.tenant_id)?;
+    ic_cdk::println!("University registered: {} -> {} (provisioning {}, attempt {})",
+                     tenant.subdomain, tenant.canister_id, record.request_id, record.attempts);
+    Ok(tenant)
+}
+
+async fn create_canister(record: &mut TenantProvisioning) -> Result<(), String> {
+    let canister_id = canister_management::create_canister_with_admin(record.admin_principal).await?;
+    // Recorded before anything else can fail, so a retry never creates a second canister
+    record.canister_id = Some(canister_id);
+    Ok(())
+}
+
+async fn install_code(record: &mut TenantProvisioning) -> Result<(), String> {
+    let canister_id = record.canister_id.ok_or("No canister was created")?;
+
+    // install_code is atomic: an installed module means an earlier attempt succeeded
+    if let Some(installed) = canister_management::get_module_hash(canister_id).await? {
+        if record.module_hash.as_deref() != Some(installed.as_str()) {
+            record.template_version = crate::template::version_for_hash(&installed);
+            record.module_hash = Some(installed);
+        }
+        return Ok(());
+    }
+
+    let template_canister_id = template_canister_id().map_err(|e| format!("{:?}", e))?;
+    let release = resolve_track(&record.template_track.clone().unwrap_or_default())
+        .map_err(|e| format!("{:?}", e))?;
+    let wasm_module = load_release_module(&release).map_err(|e| format!("{:?}", e))?;
+    record.template_version = Some(release.version);
+    record.module_hash = Some(canister_management::module_hash_hex(&wasm_module));
+    install_latest_template(canister_id, template_canister_id, wasm_module, record.admin_principal, record.tenant_id.clone()).await
+}
+
+/// Quotas are enforced by the tenant, so it must hold them before it is reachable
+async fn apply_settings(record: &TenantProvisioning) -> Result<(), String> {
+    let canister_id = record.canister_id.ok_or("No canister was created")?;
+    crate::tenant_settings::push_settings(canister_id, &record.settings.clone().unwrap_or_default()).await?;
+    // The name is informational, and templates released before it was pushed do not accept it
+    if let Err(e) = crate::tenant_settings::push_name(canister_id, &record.university_name).await {
+        ic_cdk::println!("Failed to push name to tenant {}: {}", record.tenant_id, e);
+    }
+    Ok(())
+}
+
+/// Write the routing entry and registry record in one message
+fn register_tenant(record: &TenantProvisioning) -> Result<(), String> {
+    let canister_id = record.canister_id.ok_or("No canister was created")?;
+
+    // The subdomain is held by this provisioning, but a legacy registration may have bypassed that
+    let routed_elsewhere = with_routing_table(|table| table.borrow().get(&record.subdomain))
+        .map(|existing| existing != canister_id)
+        .unwrap_or(false);
+    if routed_elsewhere || crate::subdomains::get_active_alias(&record.subdomain).is_some() {
+        return Err(format!("Subdomain '{}' was taken during provisioning", record.subdomain));
+    }
+
+    let now = current_time();
+    let tenant = Tenant {
+        id: record.tenant_id.clone(),
+        name: record.university_name.clone(),
+        subdomain: record.subdomain.clone(),
+        canister_id: canister_id.to_string(),
+        admin_ids: vec![record.admin_principal.to_string()],
+        created_at: now,
+        updated_at: now,
+        is_active: true,
+        settings: record.settings.clone().unwrap_or_default(),
+        template_version: record.template_version.clone(),
+        module_hash: record.module_hash.clone(),
+        template_track: record.template_track.clone().unwrap_or_default(),
+    };
+
+    with_routing_table(|table| {
+        table.borrow_mut().insert(record.subdomain.clone(), canister_id);
+    });
+    with_tenant_registry(|registry| {
+        registry.borrow_mut().insert(tenant.id.clone(), tenant);
+    });
+    Ok(())
+}
+
+fn template_canister_id() -> LMSResult<Principal> {
+    match get_template_config().template_canister_id {
+        Some(id) => Ok(id),
+        None => {
+            // Fallback: use the deployed tenant canister as template
+            ic_cdk::println!("No template configured, using deployed tenant canister");
+            get_deployed_tenant_canister()
+        }
+    }
+}
+
+fn is_unfinished(record: &TenantProvisioning) -> bool {
+    matches!(record.status, ProvisioningStatus::InProgress | ProvisioningStatus::Failed(_))
+}
+
+/// Request IDs come from a stable sequence and tenant IDs derive from them, so two
+/// registrations in the same round can never share an ID
+fn next_request_id() -> u64 {
+    let mut request_id = with_provisionings(|records| {
+        records.borrow().last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
+    });
+    // Skip IDs already taken by tenants registered before provisionings were recorded
+    while tenant_id_in_use(&tenant_id_for(request_id)) {
+        request_id += 1;
+    }
+    request_id
+}
+
+fn tenant_id_for(request_id: u64) -> String {
+    format!("tenant_{}", request_id)
+}
+
+fn tenant_id_in_use(tenant_id: &str) -> bool {
+    with_tenant_registry(|registry| registry.borrow().contains_key(&tenant_id.to_string()))
+        || with_decommissions(|records| records.borrow().contains_key(&tenant_id.to_string()))
+}
+
+fn save_provisioning(record: &TenantProvisioning) {
+    with_provisionings(|records| {
+        records.borrow_mut().insert(record.request_id, record.clone());
+    });
+}
@@ -0,0 +1,202 @@
+use candid::Principal;
+use ic_cdk::api::call::RejectionCode;
+use ic_cdk::api::management_canister::main::{canister_status, CanisterIdRecord};
+use ic_cdk::caller;
+use shared::{Tenant, LMSError, LMSResult, current_time};
+use crate::types::{RepairAction, RepairActionRecord, RepairRun};
+use crate::storage::{with_repair_runs, with_routing_table, with_tenant_registry};
+
+/// Find inconsistencies between the tenant registry, the routing table and the deployed canisters.
+/// With `apply` false the proposed fixes are only recorded; with `apply` true they are carried out.
+pub async fn repair_system(apply: bool) -> LMSResult<RepairRun> {
+    let started_at = current_time();
+    let tenants: Vec<Tenant> = with_tenant_registry(|registry| {
+        registry.borrow().iter().map(|(_, tenant)| tenant).collect()
+    });
+    let routes: Vec<(String, Principal)> = with_routing_table(|table| table.borrow().iter().collect());
+
+    let mut actions = plan_route_repairs(&tenants, &routes);
+    for tenant in tenants.iter().filter(|tenant| tenant.is_active) {
+        if let Some(reason) = missing_canister_reason(&tenant.canister_id).await {
+            actions.push(RepairAction::MarkCanisterMissing {
+                tenant_id: tenant.id.clone(),
+                canister_id: tenant.canister_id.clone(),
+                reason,
+            });
+        }
+    }
+
+    let records = actions.into_iter().map(|action| {
+        let result = if apply { apply_action(&action) } else { Ok(()) };
+        RepairActionRecord {
+            action,
+            applied: apply && result.is_ok(),
+            error: result.err(),
+        }
+    }).collect();
+
+    let run = RepairRun {
+        id: next_run_id(),
+        dry_run: !apply,
+        started_by: caller(),
+        started_at,
+        finished_at: current_time(),
+        actions: records,
+    };
+    with_repair_runs(|runs| {
+        runs.borrow_mut().insert(run.id, run.clone());
+    });
+
+    ic_cdk::println!("Repair run {} ({}): {} actions",
+                     run.id, if apply { "apply" } else { "dry run" }, run.actions.len());
+    Ok(run)
+}
+
+/// Get a repair run by ID
+pub fn get_repair_run(run_id: u64) -> LMSResult<RepairRun> {
+    with_repair_runs(|runs| {
+        runs.borrow()
+            .get(&run_id)
+            .ok_or_else(|| LMSError::NotFound(format!("Repair run {} not found", run_id)))
+    })
+}
+
+/// List all repair runs, newest first
+pub fn list_repair_runs() -> Vec<RepairRun> {
+    with_repair_runs(|runs| {
+        runs.borrow().iter().rev().map(|(_, run)| run).collect()
+    })
+}
+
+/// Route fixes needed so every tenant is reachable under exactly its own subdomain.
+/// Removals come first so a freed subdomain can be recreated in the same run.
+fn plan_route_repairs(tenants: &[Tenant], routes: &[(String, Principal)]) -> Vec<RepairAction> {
+    let expected: Vec<(&Tenant, String, Principal)> = tenants.iter()
+        .filter_map(|tenant| {
+            let canister_id = Principal::from_text(&tenant.canister_id).ok()?;
+            Some((tenant, route_subdomain(tenant), canister_id))
+        })
+        .collect();
+
+    let mut actions = Vec::new();
+    for (subdomain, canister_id) in routes {
+        let valid = expected.iter().any(|(_, expected_subdomain, expected_canister)| {
+            expected_subdomain == subdomain && expected_canister == canister_id
+        });
+        if !valid {
+            actions.push(RepairAction::RemoveRoute { subdomain: subdomain.clone(), canister_id: *canister_id });
+        }
+    }
+
+    for (tenant, subdomain, canister_id) in &expected {
+        let existing = routes.iter().find(|(route, _)| route == subdomain);
+        let removed = actions.iter().any(|action| {
+            matches!(action, RepairAction::RemoveRoute { subdomain: removed, .. } if removed == subdomain)
+        });
+        // A subdomain shared by two tenants needs a manual rename, not a repair
+        let claimed_by_other = expected.iter().filter(|(_, other, _)| other == subdomain).count() > 1;
+        if (existing.is_none() || removed) && !claimed_by_other {
+            actions.push(RepairAction::RecreateRoute {
+                tenant_id: tenant.id.clone(),
+                subdomain: subdomain.clone(),
+                canister_id: *canister_id,
+            });
+        }
+    }
+
+    actions
+}
+
+fn apply_action(action: &RepairAction) -> Result<(), String> {
+    match action {
+        RepairAction::RemoveRoute { subdomain, .. } => {
+            with_routing_table(|table| table.borrow_mut().remove(subdomain));
+            Ok(())
+        },
+        RepairAction::RecreateRoute { subdomain, canister_id, .. } => {
+            with_routing_table(|table| {
+                let mut table = table.borrow_mut();
+                match table.get(subdomain) {
+                    Some(existing) if existing != *canister_id => {
+                        Err(format!("Subdomain '{}' is routed to {}", subdomain, existing))
+                    },
+                    _ => {
+                        table.insert(subdomain.clone(), *canister_id);
+                        Ok(())
+                    }
+                }
+            })
+        },
+        RepairAction::MarkCanisterMissing { tenant_id, .. } => {
+            crate::lifecycle::set_tenant_active(tenant_id, false)
+                .map(|_| ())
+                .map_err(|e| format!("{:?}", e))
+        },
+    }
+}
+
+/// `Some(reason)` when the management canister reports that the canister does not exist
+async fn missing_canister_reason(canister_id: &str) -> Option<String> {
+    let canister_id = Principal::from_text(canister_id).ok()?;
+    match canister_status(CanisterIdRecord { canister_id }).await {
+        Err((RejectionCode::DestinationInvalid, msg)) => Some(msg),
+        Err((_, msg)) if msg.to_lowercase().contains("not found") => Some(msg),
+        // Other failures (e.g. the router is not a controller) do not prove the canister is gone
+        _ => None,
+    }
+}
+
+/// Routing key for a tenant; legacy tenants store a full domain whose first label is routed
+fn route_subdomain(tenant: &Tenant) -> String {
+    tenant.subdomain.split('.').next().unwrap_or(&tenant.subdomain).to_string()
+}
+
+fn next_run_id() -> u64 {
+    with_repair_runs(|runs| {
+        runs.borrow().last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::TenantSettings;
+
+    fn tenant(id: &str, subdomain: &str, canister_id: Principal) -> Tenant {
+        Tenant {
+            id: id.to_string(),
+            name: id.to_string(),
+            subdomain: subdomain.to_string(),
+            canister_id: canister_id.to_string(),
+            admin_ids: vec![],
+            created_at: 0,
+            updated_at: 0,
+            is_active: true,
+            settings: TenantSettings::default(),
+            template_version: None,
+            module_hash: None,
+            template_track: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_plan_route_repairs() {
+        let harvard = Principal::from_slice(&[1]);
+        let mit = Principal::from_slice(&[2]);
+        let gone = Principal::from_slice(&[3]);
+        let tenants = vec![tenant("t1", "harvard", harvard), tenant("t2", "mit", mit)];
+        let routes = vec![
+            ("harvard".to_string(), harvard),
+            ("old-mit".to_string(), mit),
+            ("ghost".to_string(), gone),
+        ];
+
+        let actions = plan_route_repairs(&tenants, &routes);
+        assert_eq!(actions, vec![
+            RepairAction::RemoveRoute { subdomain: "old-mit".to_string(), canister_id: mit },
+            RepairAction::RemoveRoute { subdomain: "ghost".to_string(), canister_id: gone },
+            RepairAction::RecreateRoute { tenant_id: "t2".to_string(), subdomain: "mit".to_string(), canister_id: mit },
+        ]);
+        assert!(plan_route_repairs(&tenants[..1], &routes[..1]).is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::time::Duration;
+use shared::current_time;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+// Every job interval is a multiple of this, so no job waits long past its due time
+const TICK_SECONDS: u64 = 10;
+// A job still marked running after this long is assumed to have trapped
+const STALE_JOB_SECONDS: u64 = 24 * 60 * 60;
+const DECOMMISSION_INTERVAL_SECONDS: u64 = 60;
+const ALIAS_CLEANUP_INTERVAL_SECONDS: u64 = 60 * 60;
+const METRICS_PRUNE_INTERVAL_SECONDS: u64 = 24 * 60 * 60;
+
+// Job bookkeeping lives on the heap: after an upgrade every job simply runs again on its next tick
+thread_local! {
+    static LAST_STARTED: RefCell<HashMap<&'static str, u64>> = RefCell::new(HashMap::new());
+    static IN_PROGRESS: RefCell<HashSet<&'static str>> = RefCell::new(HashSet::new());
+}
+
+/// Start the timer that drives the router's periodic background jobs.
+/// Timers do not survive upgrades, so this runs from both `init` and `post_upgrade`.
+pub fn start_scheduler() {
+    ic_cdk_timers::set_timer_interval(Duration::from_secs(TICK_SECONDS), tick);
+}
+
+fn tick() {
+    let config = crate::cycles::get_cycles_monitor_config();
+    if config.enabled {
+        run_if_due("cycles_monitor", config.check_interval_seconds, crate::cycles::run_cycles_monitor);
+    }
+    let health = crate::health::get_health_monitor_config();
+    if health.enabled {
+        run_if_due("health_monitor", health.check_interval_seconds, crate::health::run_health_monitor);
+    }
+    run_if_due("decommissions", DECOMMISSION_INTERVAL_SECONDS, crate::decommission::run_decommission_jobs);
+    run_if_due("alias_cleanup", ALIAS_CLEANUP_INTERVAL_SECONDS, crate::subdomains::release_expired_aliases);
+    run_if_due("metrics_prune", METRICS_PRUNE_INTERVAL_SECONDS, crate::metrics::prune_request_metrics);
+    run_if_due("tenant_summaries", crate::analytics::COLLECTION_INTERVAL_SECONDS, crate::analytics::run_summary_collection);
+    run_if_due("usage_metering", crate::billing::METERING_INTERVAL_SECONDS, crate::billing::run_usage_metering);
+    run_if_due("application_expiry", crate::applications::EXPIRY_INTERVAL_SECONDS, crate::applications::expire_stale_applications);
+    run_if_due("tenant_backups", crate::backup::BACKUP_INTERVAL_SECONDS, crate::backup::run_backup_jobs);
+    run_if_due("schema_migration", crate::schema::MIGRATION_INTERVAL_SECONDS, crate::schema::run_pending_migrations);
+}
+
+/// Spawn a job if its interval has elapsed and it isn't still running from a previous start
+pub fn run_if_due<F, Fut>(name: &'static str, interval_seconds: u64, job: F)
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = ()> + 'static,
+{
+    let now = current_time();
+    let interval = interval_seconds.saturating_mul(NANOS_PER_SECOND);
+
+    let elapsed = LAST_STARTED.with(|last| {
+        last.borrow().get(name).map(|started| now.saturating_sub(*started))
+    });
+    let due = elapsed.map(|elapsed| elapsed >= interval).unwrap_or(true);
+    let stale = elapsed.map(|elapsed| elapsed >= STALE_JOB_SECONDS * NANOS_PER_SECOND).unwrap_or(true);
+    let running = IN_PROGRESS.with(|running| running.borrow().contains(name));
+    if !due || (running && !stale) {
+        return;
+    }
+
+    LAST_STARTED.with(|last| last.borrow_mut().insert(name, now));
+    IN_PROGRESS.with(|running| running.borrow_mut().insert(name));
+
+    let future = job();
+    ic_cdk::spawn(async move {
+        future.await;
+        IN_PROGRESS.with(|running| running.borrow_mut().remove(name));
+    });
+}
@@ -0,0 +1,180 @@
+use candid::Principal;
+use ic_cdk::api::instruction_counter;
+use shared::versioned::{SchemaStore, Versioned, rewrite_cell, rewrite_entries};
+use shared::{Tenant, SchemaState, BackupBatch, LMSResult, current_time};
+use crate::types::{
+    TemplateConfig, FleetUpgrade, WasmModuleInfo, CyclesMonitorConfig, TenantCyclesStatus, CyclesAlert,
+    TenantSuspension, TenantDecommission, PlatformOperator, CustomDomain, BaseDomainConfig, SubdomainAlias,
+    SubdomainPolicy, MetricsBucket, RepairRun, TenantProvisioning, TenantSummarySample, PriceTable, UsageMeter,
+    BillingStatement, TenantApplication, OwnershipAuditEntry, TenantBackup, TenantRestore, HealthMonitorConfig,
+    TenantHealth
+};
+use crate::storage::{
+    with_tenant_registry, with_template_config, with_fleet_upgrades, with_wasm_registry, with_cycles_monitor_config,
+    with_tenant_cycles, with_cycles_alerts, with_tenant_suspensions, with_decommissions, with_platform_operators,
+    with_custom_domains, with_base_domains, with_subdomain_aliases, with_subdomain_policy, with_request_metrics,
+    with_repair_runs, with_provisionings, with_tenant_summaries, with_billing_prices, with_usage_meters,
+    with_billing_statements, with_tenant_applications, with_ownership_audit, with_tenant_backups, with_backup_batches,
+    with_tenant_restores, with_health_monitor_config, with_tenant_health, with_schema_state
+};
+
+const BATCH_SIZE: usize = 100;
+// Headroom below the instruction limits of an upgrade (300B) and of an update or timer (40B)
+const UPGRADE_INSTRUCTION_BUDGET: u64 = 200_000_000_000;
+const MESSAGE_INSTRUCTION_BUDGET: u64 = 10_000_000_000;
+/// How often the scheduler continues migrations the upgrade did not finish
+pub const MIGRATION_INTERVAL_SECONDS: u64 = 10;
+// Tenants have no timers, so the router drives what their upgrade left unfinished
+const TENANT_MIGRATION_ROUNDS: u32 = 50;
+
+macro_rules! map_store {
+    ($name:literal, $value:ty, $with:ident) => {
+        SchemaStore {
+            name: $name,
+            version: <$value as Versioned>::schema_version(),
+            len: || $with(|map| map.borrow().len()),
+            rewrite: |cursor, limit| $with(|map| rewrite_entries(&mut map.borrow_mut(), cursor, limit)),
+        }
+    };
+}
+
+macro_rules! cell_store {
+    ($name:literal, $value:ty, $with:ident) => {
+        SchemaStore {
+            name: $name,
+            version: <$value as Versioned>::schema_version(),
+            len: || 1,
+            rewrite: |_, _| $with(|cell| rewrite_cell(&mut cell.borrow_mut())),
+        }
+    };
+}
+
+/// Every store whose values are versioned; stores of raw bytes and principals have no schema
+fn stores() -> Vec<SchemaStore> {
+    vec![
+        map_store!("tenant_registry", Tenant, with_tenant_registry),
+        cell_store!("template_config", TemplateConfig, with_template_config),
+        map_store!("fleet_upgrades", FleetUpgrade, with_fleet_upgrades),
+        map_store!("wasm_registry", WasmModuleInfo, with_wasm_registry),
+        cell_store!("cycles_monitor_config", CyclesMonitorConfig, with_cycles_monitor_config),
+        map_store!("tenant_cycles", TenantCyclesStatus, with_tenant_cycles),
+        map_store!("cycles_alerts", CyclesAlert, with_cycles_alerts),
+        map_store!("tenant_suspensions", TenantSuspension, with_tenant_suspensions),
+        map_store!("decommissions", TenantDecommission, with_decommissions),
+        map_store!("platform_operators", PlatformOperator, with_platform_operators),
+        map_store!("custom_domains", CustomDomain, with_custom_domains),
+        cell_store!("base_domains", BaseDomainConfig, with_base_domains),
+        map_store!("subdomain_aliases", SubdomainAlias, with_subdomain_aliases),
+        cell_store!("subdomain_policy", SubdomainPolicy, with_subdomain_policy),
+        map_store!("request_metrics", MetricsBucket, with_request_metrics),
+        map_store!("repair_runs", RepairRun, with_repair_runs),
+        map_store!("provisionings", TenantProvisioning, with_provisionings),
+        map_store!("tenant_summaries", TenantSummarySample, with_tenant_summaries),
+        cell_store!("billing_prices", PriceTable, with_billing_prices),
+        map_store!("usage_meters", UsageMeter, with_usage_meters),
+        map_store!("billing_statements", BillingStatement, with_billing_statements),
+        map_store!("tenant_applications", TenantApplication, with_tenant_applications),
+        map_store!("ownership_audit", OwnershipAuditEntry, with_ownership_audit),
+        map_store!("tenant_backups", TenantBackup, with_tenant_backups),
+        map_store!("backup_batches", BackupBatch, with_backup_batches),
+        map_store!("tenant_restores", TenantRestore, with_tenant_restores),
+        cell_store!("health_monitor_config", HealthMonitorConfig, with_health_monitor_config),
+        map_store!("tenant_health", TenantHealth, with_tenant_health),
+    ]
+}
+
+/// Record every store at its current version on a fresh install
+pub fn init_schema() {
+    let mut state = get_schema_status();
+    state.record_current(&stores());
+    save_schema_state(state);
+}
+
+/// Start migrations for stores whose schema changed and run as much as fits in the upgrade.
+/// Traps when the stored schema is newer than this build, so a downgrade is rolled back.
+pub fn run_upgrade_migrations() {
+    let stores = stores();
+    let mut state = get_schema_status();
+    let started = state.plan(&stores, current_time()).unwrap_or_else(|reason| ic_cdk::trap(&reason));
+    let finished = state.run(&stores, BATCH_SIZE, current_time(), || {
+        instruction_counter() > UPGRADE_INSTRUCTION_BUDGET
+    });
+    save_schema_state(state);
+
+    if started > 0 {
+        ic_cdk::println!("Schema migrations started for {} stores ({})",
+                         started, if finished { "finished" } else { "continuing in the background" });
+    }
+}
+
+/// Continue unfinished migrations within one message's instruction budget
+pub fn continue_migrations() -> SchemaState {
+    let mut state = get_schema_status();
+    if state.is_migrating() {
+        state.run(&stores(), BATCH_SIZE, current_time(), || {
+            instruction_counter() > MESSAGE_INSTRUCTION_BUDGET
+        });
+        save_schema_state(state.clone());
+    }
+    state
+}
+
+/// Background job entry point used by the scheduler
+pub async fn run_pending_migrations() {
+    continue_migrations();
+}
+
+/// Drive an upgraded tenant's schema migrations to completion. Best effort: a tenant
+/// part-way through still reads every older version, so failures are only logged.
+pub async fn finish_tenant_migrations(canister_id: Principal) {
+    for _ in 0..TENANT_MIGRATION_ROUNDS {
+        let result: Result<(LMSResult<SchemaState>,), _> =
+            ic_cdk::call(canister_id, "run_schema_migration", ()).await;
+        match result {
+            Ok((Ok(state),)) if !state.is_migrating() => return,
+            Ok((Ok(_),)) => continue,
+            Ok((Err(e),)) => {
+                ic_cdk::println!("Tenant {} refused schema migration: {:?}", canister_id, e);
+                return;
+            },
+            Err((code, msg)) => {
+                ic_cdk::println!("Schema migration call to {} failed: {:?} - {}", canister_id, code, msg);
+                return;
+            }
+        }
+    }
+    ic_cdk::println!("Tenant {} is still migrating after {} rounds", canister_id, TENANT_MIGRATION_ROUNDS);
+}
+
+/// Recorded store versions and the latest migration of each store
+pub fn get_schema_status() -> SchemaState {
+    with_schema_state(|state| state.borrow().get().clone())
+}
+
+fn save_schema_state(state: SchemaState) {
+    with_schema_state(|cell| {
+        cell.borrow_mut().set(state).expect("Failed to store schema state");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stores_registered_once_at_current_version() {
+        let stores = stores();
+        let mut names: Vec<&str> = stores.iter().map(|store| store.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), stores.len());
+
+        let tenants = stores.iter().find(|store| store.name == "tenant_registry").unwrap();
+        assert_eq!(tenants.version, Tenant::schema_version());
+
+        // A fresh install has nothing to migrate
+        let mut state = SchemaState::default();
+        state.record_current(&stores);
+        assert_eq!(state.plan(&stores, 1), Ok(0));
+    }
+}
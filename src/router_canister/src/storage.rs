@@ -6,8 +6,13 @@ use ic_stable_structures::{
     StableCell,
     memory_manager::{MemoryId, MemoryManager}
 };
-use shared::Tenant;
-use crate::types::{Memory, TemplateConfig};
+use shared::{Tenant, SchemaState, BackupBatch};
+use crate::types::{
+    Memory, TemplateConfig, FleetUpgrade, WasmModuleInfo,
+    CyclesMonitorConfig, TenantCyclesStatus, CyclesAlert, TenantSuspension, TenantDecommission, PlatformOperator, CustomDomain, BaseDomainConfig, SubdomainAlias, SubdomainPolicy, MetricsBucket, RepairRun, TenantProvisioning, TenantSummarySample,
+    PriceTable, UsageMeter, BillingStatement, TenantApplication, OwnershipAuditEntry, TenantBackup, TenantRestore,
+    HealthMonitorConfig, TenantHealth
+};
 
 // Router state with stable storage
 thread_local! {
@@ -44,6 +49,222 @@ thread_local! {
             false
         ).unwrap()
     );
+    
+    // Fleet upgrade history: upgrade_id -> FleetUpgrade
+    static FLEET_UPGRADES: RefCell<StableBTreeMap<u64, FleetUpgrade, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(4)))
+        )
+    );
+    
+    // Tenant WASM modules installed by the router: sha256 hex -> module bytes
+    static TENANT_MODULES: RefCell<StableBTreeMap<String, Vec<u8>, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(5)))
+        )
+    );
+    
+    // Uploaded tenant WASM versions: semver -> WasmModuleInfo
+    static WASM_REGISTRY: RefCell<StableBTreeMap<String, WasmModuleInfo, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(6)))
+        )
+    );
+    
+    // Chunks of in-progress WASM uploads: "{version}#{index}" -> bytes
+    static WASM_CHUNKS: RefCell<StableBTreeMap<String, Vec<u8>, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(7)))
+        )
+    );
+    
+    // Cycles top-up monitor configuration
+    static CYCLES_MONITOR_CONFIG: RefCell<StableCell<CyclesMonitorConfig, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(8))),
+            CyclesMonitorConfig::default()
+        ).expect("Failed to initialize cycles monitor config")
+    );
+    
+    // Tenant cycles state: tenant_id -> TenantCyclesStatus
+    static TENANT_CYCLES: RefCell<StableBTreeMap<String, TenantCyclesStatus, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(9)))
+        )
+    );
+    
+    // Low-balance alerts: alert_id -> CyclesAlert
+    static CYCLES_ALERTS: RefCell<StableBTreeMap<u64, CyclesAlert, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(10)))
+        )
+    );
+    
+    // Tenant suspensions: tenant_id -> latest TenantSuspension
+    static TENANT_SUSPENSIONS: RefCell<StableBTreeMap<String, TenantSuspension, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(11)))
+        )
+    );
+    
+    // Tenant decommissions: tenant_id -> TenantDecommission (kept as a tombstone after deletion)
+    static DECOMMISSIONS: RefCell<StableBTreeMap<String, TenantDecommission, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(12)))
+        )
+    );
+    
+    // Decommission exports: tenant_id -> candid-encoded TenantDataExport
+    static DECOMMISSION_EXPORTS: RefCell<StableBTreeMap<String, Vec<u8>, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(13)))
+        )
+    );
+    
+    // Platform operators: principal -> PlatformOperator (controllers are implicitly operators)
+    static PLATFORM_OPERATORS: RefCell<StableBTreeMap<Principal, PlatformOperator, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(14)))
+        )
+    );
+    
+    // Custom domains: hostname -> CustomDomain
+    static CUSTOM_DOMAINS: RefCell<StableBTreeMap<String, CustomDomain, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(15)))
+        )
+    );
+    
+    // Platform base domains used for subdomain routing
+    static BASE_DOMAINS: RefCell<StableCell<BaseDomainConfig, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(16))),
+            BaseDomainConfig::default()
+        ).expect("Failed to initialize base domain config")
+    );
+    
+    // Subdomain aliases: former subdomain -> SubdomainAlias
+    static SUBDOMAIN_ALIASES: RefCell<StableBTreeMap<String, SubdomainAlias, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(17)))
+        )
+    );
+    
+    // Reserved names and blocked words for tenant subdomains
+    static SUBDOMAIN_POLICY: RefCell<StableCell<SubdomainPolicy, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(18))),
+            SubdomainPolicy::default()
+        ).expect("Failed to initialize subdomain policy")
+    );
+    
+    // Request metrics: "tenant#granularity#bucket_start" -> MetricsBucket
+    static REQUEST_METRICS: RefCell<StableBTreeMap<String, MetricsBucket, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(19)))
+        )
+    );
+    
+    // Repair runs: run_id -> RepairRun
+    static REPAIR_RUNS: RefCell<StableBTreeMap<u64, RepairRun, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(20)))
+        )
+    );
+    
+    // Tenant provisionings: request_id -> TenantProvisioning
+    static PROVISIONINGS: RefCell<StableBTreeMap<u64, TenantProvisioning, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(21)))
+        )
+    );
+    
+    // Tenant summaries: "tenant_id#bucket_start" -> TenantSummarySample
+    static TENANT_SUMMARIES: RefCell<StableBTreeMap<String, TenantSummarySample, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(22)))
+        )
+    );
+    
+    // Schema versions of the stores above and progress of their migrations
+    static SCHEMA_STATE: RefCell<StableCell<SchemaState, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(23))),
+            SchemaState::default()
+        ).expect("Failed to initialize schema state")
+    );
+    
+    // Prices applied when monthly statements are generated
+    static BILLING_PRICES: RefCell<StableCell<PriceTable, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(24))),
+            PriceTable::default()
+        ).expect("Failed to initialize billing prices")
+    );
+    
+    // Usage meters: "YYYY-MM#tenant_id" -> UsageMeter
+    static USAGE_METERS: RefCell<StableBTreeMap<String, UsageMeter, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(25)))
+        )
+    );
+    
+    // Billing statements: "YYYY-MM#tenant_id" -> BillingStatement
+    static BILLING_STATEMENTS: RefCell<StableBTreeMap<String, BillingStatement, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(26)))
+        )
+    );
+    
+    // Signup applications: application_id -> TenantApplication
+    static TENANT_APPLICATIONS: RefCell<StableBTreeMap<u64, TenantApplication, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(27)))
+        )
+    );
+    
+    // Ownership audit trail: entry_id -> OwnershipAuditEntry
+    static OWNERSHIP_AUDIT: RefCell<StableBTreeMap<u64, OwnershipAuditEntry, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(28)))
+        )
+    );
+    
+    // Tenant backups: backup_id -> TenantBackup
+    static TENANT_BACKUPS: RefCell<StableBTreeMap<u64, TenantBackup, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(29)))
+        )
+    );
+    
+    // Exported backup batches: "backup_id#sequence" (zero-padded) -> BackupBatch
+    static BACKUP_BATCHES: RefCell<StableBTreeMap<String, BackupBatch, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(30)))
+        )
+    );
+    
+    // Backup restores: restore_id -> TenantRestore
+    static TENANT_RESTORES: RefCell<StableBTreeMap<u64, TenantRestore, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(31)))
+        )
+    );
+    
+    // Tenant health monitor configuration
+    static HEALTH_MONITOR_CONFIG: RefCell<StableCell<HealthMonitorConfig, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(32))),
+            HealthMonitorConfig::default()
+        ).expect("Failed to initialize health monitor config")
+    );
+    
+    // Latest health check per tenant: tenant_id -> TenantHealth
+    static TENANT_HEALTH: RefCell<StableBTreeMap<String, TenantHealth, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(33)))
+        )
+    );
 }
 
 pub fn with_routing_table<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, Principal, Memory>>) -> R) -> R {
@@ -61,3 +282,123 @@ pub fn with_template_config<R>(f: impl FnOnce(&RefCell<StableCell<TemplateConfig
 pub fn with_router_config<R>(f: impl FnOnce(&RefCell<StableCell<bool, Memory>>) -> R) -> R {
     ROUTER_CONFIG.with(f)
 }
+
+pub fn with_fleet_upgrades<R>(f: impl FnOnce(&RefCell<StableBTreeMap<u64, FleetUpgrade, Memory>>) -> R) -> R {
+    FLEET_UPGRADES.with(f)
+}
+
+pub fn with_tenant_modules<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, Vec<u8>, Memory>>) -> R) -> R {
+    TENANT_MODULES.with(f)
+}
+
+pub fn with_wasm_registry<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, WasmModuleInfo, Memory>>) -> R) -> R {
+    WASM_REGISTRY.with(f)
+}
+
+pub fn with_wasm_chunks<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, Vec<u8>, Memory>>) -> R) -> R {
+    WASM_CHUNKS.with(f)
+}
+
+pub fn with_cycles_monitor_config<R>(f: impl FnOnce(&RefCell<StableCell<CyclesMonitorConfig, Memory>>) -> R) -> R {
+    CYCLES_MONITOR_CONFIG.with(f)
+}
+
+pub fn with_tenant_cycles<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, TenantCyclesStatus, Memory>>) -> R) -> R {
+    TENANT_CYCLES.with(f)
+}
+
+pub fn with_cycles_alerts<R>(f: impl FnOnce(&RefCell<StableBTreeMap<u64, CyclesAlert, Memory>>) -> R) -> R {
+    CYCLES_ALERTS.with(f)
+}
+
+pub fn with_tenant_suspensions<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, TenantSuspension, Memory>>) -> R) -> R {
+    TENANT_SUSPENSIONS.with(f)
+}
+
+pub fn with_decommissions<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, TenantDecommission, Memory>>) -> R) -> R {
+    DECOMMISSIONS.with(f)
+}
+
+pub fn with_decommission_exports<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, Vec<u8>, Memory>>) -> R) -> R {
+    DECOMMISSION_EXPORTS.with(f)
+}
+
+pub fn with_platform_operators<R>(f: impl FnOnce(&RefCell<StableBTreeMap<Principal, PlatformOperator, Memory>>) -> R) -> R {
+    PLATFORM_OPERATORS.with(f)
+}
+
+pub fn with_custom_domains<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, CustomDomain, Memory>>) -> R) -> R {
+    CUSTOM_DOMAINS.with(f)
+}
+
+pub fn with_base_domains<R>(f: impl FnOnce(&RefCell<StableCell<BaseDomainConfig, Memory>>) -> R) -> R {
+    BASE_DOMAINS.with(f)
+}
+
+pub fn with_subdomain_aliases<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, SubdomainAlias, Memory>>) -> R) -> R {
+    SUBDOMAIN_ALIASES.with(f)
+}
+
+pub fn with_subdomain_policy<R>(f: impl FnOnce(&RefCell<StableCell<SubdomainPolicy, Memory>>) -> R) -> R {
+    SUBDOMAIN_POLICY.with(f)
+}
+
+pub fn with_request_metrics<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, MetricsBucket, Memory>>) -> R) -> R {
+    REQUEST_METRICS.with(f)
+}
+
+pub fn with_repair_runs<R>(f: impl FnOnce(&RefCell<StableBTreeMap<u64, RepairRun, Memory>>) -> R) -> R {
+    REPAIR_RUNS.with(f)
+}
+
+pub fn with_provisionings<R>(f: impl FnOnce(&RefCell<StableBTreeMap<u64, TenantProvisioning, Memory>>) -> R) -> R {
+    PROVISIONINGS.with(f)
+}
+
+pub fn with_tenant_summaries<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, TenantSummarySample, Memory>>) -> R) -> R {
+    TENANT_SUMMARIES.with(f)
+}
+
+pub fn with_schema_state<R>(f: impl FnOnce(&RefCell<StableCell<SchemaState, Memory>>) -> R) -> R {
+    SCHEMA_STATE.with(f)
+}
+
+pub fn with_billing_prices<R>(f: impl FnOnce(&RefCell<StableCell<PriceTable, Memory>>) -> R) -> R {
+    BILLING_PRICES.with(f)
+}
+
+pub fn with_usage_meters<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, UsageMeter, Memory>>) -> R) -> R {
+    USAGE_METERS.with(f)
+}
+
+pub fn with_billing_statements<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, BillingStatement, Memory>>) -> R) -> R {
+    BILLING_STATEMENTS.with(f)
+}
+
+pub fn with_tenant_applications<R>(f: impl FnOnce(&RefCell<StableBTreeMap<u64, TenantApplication, Memory>>) -> R) -> R {
+    TENANT_APPLICATIONS.with(f)
+}
+
+pub fn with_ownership_audit<R>(f: impl FnOnce(&RefCell<StableBTreeMap<u64, OwnershipAuditEntry, Memory>>) -> R) -> R {
+    OWNERSHIP_AUDIT.with(f)
+}
+
+pub fn with_tenant_backups<R>(f: impl FnOnce(&RefCell<StableBTreeMap<u64, TenantBackup, Memory>>) -> R) -> R {
+    TENANT_BACKUPS.with(f)
+}
+
+pub fn with_backup_batches<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, BackupBatch, Memory>>) -> R) -> R {
+    BACKUP_BATCHES.with(f)
+}
+
+pub fn with_tenant_restores<R>(f: impl FnOnce(&RefCell<StableBTreeMap<u64, TenantRestore, Memory>>) -> R) -> R {
+    TENANT_RESTORES.with(f)
+}
+
+pub fn with_health_monitor_config<R>(f: impl FnOnce(&RefCell<StableCell<HealthMonitorConfig, Memory>>) -> R) -> R {
+    HEALTH_MONITOR_CONFIG.with(f)
+}
+
+pub fn with_tenant_health<R>(f: impl FnOnce(&RefCell<StableBTreeMap<String, TenantHealth, Memory>>) -> R) -> R {
+    TENANT_HEALTH.with(f)
+}
@@ -0,0 +1,257 @@
+use candid::Principal;
+use ic_cdk::caller;
+use shared::{Tenant, LMSError, LMSResult, utils, current_time};
+use crate::types::{SubdomainAlias, SubdomainPolicy};
+use crate::storage::{with_routing_table, with_subdomain_aliases, with_subdomain_policy, with_tenant_registry};
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+const DEFAULT_ALIAS_DAYS: u32 = 90;
+const MAX_ALIAS_DAYS: u32 = 365;
+
+/// Rename a tenant's subdomain in the routing table and registry.
+/// The old subdomain keeps redirecting to the new one for `alias_days` (0 releases it immediately).
+pub fn rename_subdomain(tenant_id: String, new_subdomain: String, alias_days: Option<u32>) -> LMSResult<Tenant> {
+    let new_subdomain = new_subdomain.trim().to_lowercase();
+    let alias_days = alias_days.unwrap_or(DEFAULT_ALIAS_DAYS);
+    if alias_days > MAX_ALIAS_DAYS {
+        return Err(LMSError::ValidationError(format!("Alias period cannot exceed {} days", MAX_ALIAS_DAYS)));
+    }
+
+    validate_subdomain(&new_subdomain)?;
+
+    let mut tenant = with_tenant_registry(|registry| registry.borrow().get(&tenant_id))
+        .ok_or_else(|| LMSError::NotFound(format!("Tenant '{}' not found", tenant_id)))?;
+    let old_subdomain = tenant.subdomain.clone();
+    if old_subdomain == new_subdomain {
+        return Err(LMSError::ValidationError(format!("Tenant already uses subdomain '{}'", new_subdomain)));
+    }
+
+    // A tenant may move back to one of its own aliases
+    let own_alias = get_active_alias(&new_subdomain)
+        .map(|alias| alias.tenant_id == tenant_id)
+        .unwrap_or(false);
+    if !own_alias && is_subdomain_taken(&new_subdomain) {
+        return Err(LMSError::AlreadyExists(format!("Subdomain '{}' is already in use", new_subdomain)));
+    }
+
+    let canister_id = match with_routing_table(|table| table.borrow().get(&old_subdomain)) {
+        Some(canister_id) => canister_id,
+        None => Principal::from_text(&tenant.canister_id)
+            .map_err(|_| LMSError::ValidationError("Invalid canister ID".to_string()))?,
+    };
+
+    // All writes happen within this message, so routing never sees a half-renamed tenant
+    let now = current_time();
+    with_routing_table(|table| {
+        let mut table = table.borrow_mut();
+        table.remove(&old_subdomain);
+        table.insert(new_subdomain.clone(), canister_id);
+    });
+
+    tenant.subdomain = new_subdomain.clone();
+    tenant.updated_at = now;
+    with_tenant_registry(|registry| {
+        registry.borrow_mut().insert(tenant_id.clone(), tenant.clone());
+    });
+
+    with_subdomain_aliases(|aliases| {
+        let mut aliases = aliases.borrow_mut();
+        aliases.remove(&new_subdomain);
+        if alias_days > 0 {
+            aliases.insert(old_subdomain.clone(), SubdomainAlias {
+                subdomain: old_subdomain.clone(),
+                tenant_id: tenant_id.clone(),
+                created_by: caller(),
+                created_at: now,
+                expires_at: now + alias_days as u64 * NANOS_PER_DAY,
+            });
+        }
+    });
+
+    ic_cdk::println!("Renamed tenant {} subdomain: {} -> {} (alias for {} days)",
+                     tenant_id, old_subdomain, new_subdomain, alias_days);
+    Ok(tenant)
+}
+
+/// Check a new subdomain against DNS label rules and the reserved-name policy
+pub fn validate_subdomain(subdomain: &str) -> LMSResult<()> {
+    check_subdomain(&get_subdomain_policy(), subdomain)
+        .map_err(LMSError::ValidationError)
+}
+
+fn check_subdomain(policy: &SubdomainPolicy, subdomain: &str) -> Result<(), String> {
+    if !utils::is_valid_subdomain(subdomain) {
+        return Err("Subdomain must be 1-63 lowercase letters, digits or hyphens and cannot start or end with a hyphen".to_string());
+    }
+
+    if subdomain.len() < policy.min_length as usize {
+        return Err(format!("Subdomain must be at least {} characters", policy.min_length));
+    }
+
+    // Punycode labels would let look-alike names through
+    if subdomain.starts_with("xn--") {
+        return Err("Internationalized subdomains are not supported".to_string());
+    }
+
+    if policy.reserved_names.iter().any(|name| name == subdomain) {
+        return Err(format!("Subdomain '{}' is reserved", subdomain));
+    }
+
+    if let Some(word) = policy.blocked_words.iter().find(|word| subdomain.contains(word.as_str())) {
+        return Err(format!("Subdomain contains the blocked word '{}'", word));
+    }
+
+    Ok(())
+}
+
+/// Whether a host name belongs to the platform rather than a tenant.
+/// The policy only applies to new names, so tenants routed on a name reserved later keep it.
+pub fn is_platform_name(subdomain: &str) -> bool {
+    let reserved = with_subdomain_policy(|policy| {
+        policy.borrow().get().reserved_names.iter().any(|name| name == subdomain)
+    });
+    reserved
+        && !with_routing_table(|table| table.borrow().contains_key(&subdomain.to_string()))
+        && !with_tenant_registry(|registry| registry.borrow().contains_key(&subdomain.to_string()))
+        && get_active_alias(subdomain).is_none()
+}
+
+/// Get the subdomain policy
+pub fn get_subdomain_policy() -> SubdomainPolicy {
+    with_subdomain_policy(|policy| policy.borrow().get().clone())
+}
+
+/// Replace the reserved names and blocked words; existing tenants are not affected
+pub fn configure_subdomain_policy(mut policy: SubdomainPolicy) -> LMSResult<SubdomainPolicy> {
+    if policy.min_length == 0 || policy.min_length > 63 {
+        return Err(LMSError::ValidationError("Minimum length must be between 1 and 63".to_string()));
+    }
+
+    let normalize = |names: Vec<String>| -> Vec<String> {
+        let mut names: Vec<String> = names.into_iter()
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    };
+    policy.reserved_names = normalize(policy.reserved_names);
+    policy.blocked_words = normalize(policy.blocked_words);
+    policy.updated_at = current_time();
+
+    with_subdomain_policy(|stored| {
+        stored.borrow_mut().set(policy.clone()).map_err(|_| {
+            LMSError::InternalError("Failed to store subdomain policy".to_string())
+        })
+    })?;
+
+    ic_cdk::println!("Subdomain policy updated: {} reserved names, {} blocked words",
+                     policy.reserved_names.len(), policy.blocked_words.len());
+    Ok(policy)
+}
+
+/// Check that a subdomain passes the policy and is not already taken
+pub fn check_subdomain_available(subdomain: String) -> LMSResult<()> {
+    let subdomain = subdomain.trim().to_lowercase();
+    validate_subdomain(&subdomain)?;
+    if is_subdomain_taken(&subdomain) {
+        return Err(LMSError::AlreadyExists(format!("Subdomain '{}' is already in use", subdomain)));
+    }
+    Ok(())
+}
+
+/// Whether a subdomain is routed to a tenant, held by an unexpired alias or being provisioned
+pub fn is_subdomain_taken(subdomain: &str) -> bool {
+    with_routing_table(|table| table.borrow().contains_key(&subdomain.to_string()))
+        || get_active_alias(subdomain).is_some()
+        || crate::provisioning::is_subdomain_pending(subdomain)
+}
+
+/// Get an alias that has not expired yet
+pub fn get_active_alias(subdomain: &str) -> Option<SubdomainAlias> {
+    with_subdomain_aliases(|aliases| aliases.borrow().get(&subdomain.to_string()))
+        .filter(|alias| alias.expires_at > current_time())
+}
+
+/// List all subdomain aliases, optionally only those of one tenant
+pub fn list_subdomain_aliases(tenant_id: Option<String>) -> Vec<SubdomainAlias> {
+    with_subdomain_aliases(|aliases| {
+        aliases.borrow()
+            .iter()
+            .map(|(_, alias)| alias)
+            .filter(|alias| tenant_id.as_ref().map(|id| &alias.tenant_id == id).unwrap_or(true))
+            .collect()
+    })
+}
+
+/// Release an alias before it expires
+pub fn remove_subdomain_alias(subdomain: String) -> LMSResult<()> {
+    with_subdomain_aliases(|aliases| aliases.borrow_mut().remove(&subdomain))
+        .ok_or_else(|| LMSError::NotFound(format!("Alias '{}' not found", subdomain)))?;
+
+    ic_cdk::println!("Released subdomain alias {}", subdomain);
+    Ok(())
+}
+
+/// Remove every alias of a tenant
+pub fn remove_tenant_aliases(tenant_id: &str) {
+    let subdomains: Vec<String> = list_subdomain_aliases(Some(tenant_id.to_string()))
+        .into_iter()
+        .map(|alias| alias.subdomain)
+        .collect();
+    with_subdomain_aliases(|aliases| {
+        let mut aliases = aliases.borrow_mut();
+        for subdomain in subdomains {
+            aliases.remove(&subdomain);
+        }
+    });
+}
+
+/// Background job entry point used by the scheduler
+pub async fn release_expired_aliases() {
+    let now = current_time();
+    let expired: Vec<String> = with_subdomain_aliases(|aliases| {
+        aliases.borrow()
+            .iter()
+            .filter(|(_, alias)| alias.expires_at <= now)
+            .map(|(subdomain, _)| subdomain)
+            .collect()
+    });
+
+    if expired.is_empty() {
+        return;
+    }
+
+    with_subdomain_aliases(|aliases| {
+        let mut aliases = aliases.borrow_mut();
+        for subdomain in &expired {
+            aliases.remove(subdomain);
+        }
+    });
+    ic_cdk::println!("Released {} expired subdomain aliases", expired.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_subdomain;
+    use crate::types::SubdomainPolicy;
+
+    #[test]
+    fn test_check_subdomain() {
+        let policy = SubdomainPolicy {
+            blocked_words: vec!["scam".to_string()],
+            ..SubdomainPolicy::default()
+        };
+
+        assert!(check_subdomain(&policy, "harvard").is_ok());
+        assert!(check_subdomain(&policy, "uc-berkeley").is_ok());
+        assert!(check_subdomain(&policy, "www").is_err());
+        assert!(check_subdomain(&policy, "admin").is_err());
+        assert!(check_subdomain(&policy, "ab").is_err());
+        assert!(check_subdomain(&policy, "Harvard").is_err());
+        assert!(check_subdomain(&policy, "xn--hrvard-9ya").is_err());
+        assert!(check_subdomain(&policy, "not-a-scam-university").is_err());
+        assert!(check_subdomain(&policy, &"a".repeat(64)).is_err());
+    }
+}
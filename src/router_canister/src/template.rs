@@ -1,15 +1,32 @@
 use candid::Principal;
-use shared::{LMSError, LMSResult, utils};
-use crate::types::TemplateConfig;
-use crate::storage::{with_template_config, with_tenant_registry};
+use shared::{Tenant, ReleaseChannel, TemplateTrack, LMSError, LMSResult, utils};
+use crate::types::{FleetUpgradeStatus, TemplateConfig};
+use crate::storage::{with_fleet_upgrades, with_template_config, with_tenant_registry, with_wasm_registry};
+use crate::lifecycle::{get_tenant, parse_canister_id};
+use crate::canister_management;
+
+/// A template version a track resolves to
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemplateRelease {
+    pub version: String,
+    /// Registry module to install; `None` is the module embedded in the router build
+    pub module_hash: Option<String>,
+}
 
 /// Configure template canister for tenant provisioning
 pub fn configure_template(template_canister_id: Principal, version: String) -> LMSResult<()> {
+    // The version must have been uploaded to the WASM registry
+    let module = crate::wasm_registry::find_ready_version(&version).ok_or_else(|| {
+        LMSError::NotFound(format!("WASM version {} is not ready in the registry", version))
+    })?;
+    
     let config = TemplateConfig {
         template_canister_id: Some(template_canister_id),
         template_version: version,
         last_updated: utils::current_time(),
         auto_update: true,
+        active_module_hash: Some(module.sha256),
+        beta_version: get_template_config().beta_version,
     };
     
     with_template_config(|template| {
@@ -60,6 +77,7 @@ pub fn get_deployed_tenant_canister() -> LMSResult<Principal> {
 pub async fn install_latest_template(
     canister_id: Principal,
     template_canister_id: Principal,
+    wasm_module: Vec<u8>,
     admin_principal: Principal,
     tenant_id: String,
 ) -> Result<(), String> {
@@ -70,7 +88,7 @@ pub async fn install_latest_template(
     // In a real implementation, you might use canister_status to get the module_hash
     // and then install from a known source
     
-    match crate::canister_management::install_from_template(canister_id, template_canister_id, admin_principal, tenant_id).await {
+    match crate::canister_management::install_from_template(canister_id, template_canister_id, wasm_module, admin_principal, tenant_id).await {
         Ok(_) => {
             ic_cdk::println!("Successfully installed template {} on canister {}", 
                            template_canister_id, canister_id);
@@ -82,3 +100,137 @@ pub async fn install_latest_template(
         }
     }
 }
+
+/// The version a track currently resolves to
+pub fn resolve_track(track: &TemplateTrack) -> LMSResult<TemplateRelease> {
+    let config = get_template_config();
+    release_for_track(track, &config, |version| {
+        crate::wasm_registry::find_ready_version(version).map(|info| info.sha256)
+    })
+}
+
+/// Load and verify the module of a resolved release
+pub fn load_release_module(release: &TemplateRelease) -> LMSResult<Vec<u8>> {
+    canister_management::get_tenant_module(release.module_hash.as_deref())
+        .map_err(LMSError::ValidationError)
+}
+
+/// The registry version whose module has this hash, if it was uploaded
+pub fn version_for_hash(module_hash: &str) -> Option<String> {
+    with_wasm_registry(|registry| {
+        registry.borrow()
+            .iter()
+            .find(|(_, info)| info.sha256 == module_hash)
+            .map(|(version, _)| version)
+    })
+}
+
+/// Record the template a tenant's canister now runs
+pub fn record_installed_template(tenant_id: &str, template_version: Option<String>, module_hash: String) {
+    with_tenant_registry(|registry| {
+        let mut registry = registry.borrow_mut();
+        if let Some(mut tenant) = registry.get(&tenant_id.to_string()) {
+            tenant.template_version = template_version;
+            tenant.module_hash = Some(module_hash);
+            tenant.updated_at = utils::current_time();
+            registry.insert(tenant.id.clone(), tenant);
+        }
+    });
+}
+
+/// Move a tenant to another channel or pin it to a version. The canister is upgraded straight
+/// away when the track resolves to a different module; the track is only saved once it runs it.
+pub async fn set_tenant_template_track(tenant_id: String, track: TemplateTrack) -> LMSResult<Tenant> {
+    // A running rollout planned its tenants by track, so tracks stay put until it finishes
+    let rollout_running = with_fleet_upgrades(|upgrades| {
+        upgrades.borrow().iter().any(|(_, upgrade)| upgrade.status == FleetUpgradeStatus::Running)
+    });
+    if rollout_running {
+        return Err(LMSError::ValidationError("A fleet upgrade is running; change the track once it finishes".to_string()));
+    }
+
+    let tenant = get_tenant(&tenant_id)?;
+    let canister_id = parse_canister_id(&tenant)?;
+    let release = resolve_track(&track)?;
+    let wasm_module = load_release_module(&release)?;
+    let module_hash = canister_management::store_tenant_module(&wasm_module);
+
+    let installed = canister_management::get_module_hash(canister_id).await
+        .map_err(LMSError::InternalError)?;
+    if installed.as_deref() != Some(module_hash.as_str()) {
+        canister_management::upgrade_canister_code(canister_id, wasm_module).await
+            .map_err(LMSError::InternalError)?;
+        crate::schema::finish_tenant_migrations(canister_id).await;
+    }
+
+    // Re-read after the calls so concurrent metadata changes are kept
+    let mut tenant = get_tenant(&tenant_id)?;
+    tenant.template_track = track;
+    tenant.template_version = Some(release.version.clone());
+    tenant.module_hash = Some(module_hash);
+    tenant.updated_at = utils::current_time();
+    with_tenant_registry(|registry| {
+        registry.borrow_mut().insert(tenant.id.clone(), tenant.clone());
+    });
+
+    ic_cdk::println!("Tenant {} now runs template {} ({:?})", tenant_id, release.version, tenant.template_track);
+    Ok(tenant)
+}
+
+/// Resolve a track against the template config. Beta follows stable while it is unset or its
+/// version is no longer ready; a pin must name a ready registry version or the stable one.
+fn release_for_track(
+    track: &TemplateTrack,
+    config: &TemplateConfig,
+    ready_hash: impl Fn(&str) -> Option<String>,
+) -> LMSResult<TemplateRelease> {
+    let stable = TemplateRelease {
+        version: config.template_version.clone(),
+        module_hash: config.active_module_hash.clone(),
+    };
+    let registry_release = |version: &str| ready_hash(version).map(|hash| TemplateRelease {
+        version: version.to_string(),
+        module_hash: Some(hash),
+    });
+
+    match track {
+        TemplateTrack::Channel(ReleaseChannel::Stable) => Ok(stable),
+        TemplateTrack::Channel(ReleaseChannel::Beta) => Ok(config.beta_version.as_deref()
+            .and_then(registry_release)
+            .unwrap_or(stable)),
+        TemplateTrack::Pinned(version) if *version == stable.version => Ok(stable),
+        TemplateTrack::Pinned(version) => registry_release(version).ok_or_else(|| {
+            LMSError::ValidationError(format!("WASM version {} is not uploaded or not ready", version))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_for_track() {
+        let config = TemplateConfig {
+            template_version: "1.0.0".to_string(),
+            beta_version: Some("1.1.0".to_string()),
+            ..TemplateConfig::default()
+        };
+        let ready = |version: &str| (version == "1.1.0" || version == "0.9.0").then(|| format!("hash-{}", version));
+
+        let stable = release_for_track(&TemplateTrack::Channel(ReleaseChannel::Stable), &config, ready).unwrap();
+        assert_eq!(stable, TemplateRelease { version: "1.0.0".to_string(), module_hash: None });
+
+        let beta = release_for_track(&TemplateTrack::Channel(ReleaseChannel::Beta), &config, ready).unwrap();
+        assert_eq!(beta.module_hash.as_deref(), Some("hash-1.1.0"));
+
+        let pinned = release_for_track(&TemplateTrack::Pinned("0.9.0".to_string()), &config, ready).unwrap();
+        assert_eq!(pinned.version, "0.9.0");
+        assert_eq!(release_for_track(&TemplateTrack::Pinned("1.0.0".to_string()), &config, ready).unwrap(), stable);
+        assert!(release_for_track(&TemplateTrack::Pinned("2.0.0".to_string()), &config, ready).is_err());
+
+        // A withdrawn beta falls back to stable
+        let no_beta = TemplateConfig { beta_version: Some("1.2.0".to_string()), ..config };
+        assert_eq!(release_for_track(&TemplateTrack::Channel(ReleaseChannel::Beta), &no_beta, ready).unwrap(), stable);
+    }
+}
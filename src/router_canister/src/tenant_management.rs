@@ -1,93 +1,28 @@
 use candid::Principal;
 use ic_cdk::caller;
-use shared::{Tenant, TenantSettings, LMSError, LMSResult, utils, current_time};
+use shared::{Tenant, TenantSettings, TemplateTrack, LMSError, LMSResult, current_time};
 use crate::storage::{with_routing_table, with_tenant_registry};
-use crate::template::{get_template_config, get_deployed_tenant_canister, install_latest_template};
-use crate::canister_management;
 
-/// Register a new university and provision its tenant canister (Modern Template-Based Approach)
+/// Register a new university and provision its tenant canister (Modern Template-Based Approach).
+/// A failed registration can be continued with `resume_provisioning`. Without a template
+/// track the tenant follows the stable channel.
 pub async fn register_university(
     subdomain: String,
     university_name: String,
     admin_principal: Principal,
+    template_track: Option<TemplateTrack>,
 ) -> LMSResult<Tenant> {
-    // Validate subdomain format
-    if !utils::is_valid_subdomain(&subdomain) {
-        return Err(LMSError::ValidationError("Invalid subdomain format".to_string()));
-    }
+    // Validate subdomain format and reserved-name policy
+    let subdomain = subdomain.trim().to_lowercase();
+    crate::subdomains::validate_subdomain(&subdomain)?;
     
     // Check if subdomain already exists
-    let subdomain_exists = with_routing_table(|table| {
-        table.borrow().contains_key(&subdomain)
-    });
-    
-    if subdomain_exists {
+    if crate::subdomains::is_subdomain_taken(&subdomain) {
         return Err(LMSError::AlreadyExists("Subdomain already registered".to_string()));
     }
     
-    // Get template configuration
-    let template_config = get_template_config();
-    
-    // Check if template is configured
-    let template_canister_id = match template_config.template_canister_id {
-        Some(id) => id,
-        None => {
-            // Fallback: use the deployed tenant canister as template
-            ic_cdk::println!("No template configured, using deployed tenant canister");
-            get_deployed_tenant_canister()?
-        }
-    };
-    
-    // Create new canister with admin as controller
-    let canister_id = match canister_management::create_canister_with_admin(admin_principal).await {
-        Ok(principal) => principal,
-        Err(e) => return Err(LMSError::InternalError(format!("Failed to create canister: {:?}", e))),
-    };
-    
-    // Generate tenant ID once to ensure consistency
-    let tenant_id = format!("tenant_{}", current_time());
-    
-    // Install latest template code with the tenant ID
-    match install_latest_template(canister_id, template_canister_id, admin_principal, tenant_id.clone()).await {
-        Ok(_) => {},
-        Err(e) => {
-            // Rollback: delete the created canister
-            let _ = canister_management::delete_canister(canister_id).await;
-            return Err(LMSError::InternalError(format!("Failed to install template: {:?}", e)));
-        }
-    }
-    
-    // Create tenant record using the same tenant_id that was passed to the canister
-    let tenant = Tenant {
-        id: tenant_id.clone(),
-        name: university_name.clone(),
-        subdomain: subdomain.clone(),
-        canister_id: canister_id.to_string(),
-        admin_ids: vec![admin_principal.to_string()],
-        created_at: current_time(),
-        updated_at: current_time(),
-        is_active: true,
-        settings: TenantSettings {
-            max_students: 1000,
-            max_instructors: 100,
-            max_courses: 500,
-            allow_public_enrollment: false,
-            custom_branding: false,
-        },
-    };
-    
-    // Update routing table and tenant registry
-    with_routing_table(|table| {
-        table.borrow_mut().insert(subdomain, canister_id);
-    });
-    
-    with_tenant_registry(|registry| {
-        registry.borrow_mut().insert(tenant_id.clone(), tenant.clone());
-    });
-    
-    ic_cdk::println!("University registered: {} -> {} (using template: {})", 
-                     tenant.subdomain, canister_id, template_canister_id);
-    Ok(tenant)
+    // Each step is persisted so a failure can be resumed instead of leaking a canister
+    crate::provisioning::start_provisioning(subdomain, university_name, admin_principal, template_track).await
 }
 
 /// Legacy function for compatibility with existing tests
@@ -118,6 +53,9 @@ pub async fn register_tenant(
             allow_public_enrollment: false,
             custom_branding: false,
         },
+        template_version: None,
+        module_hash: None,
+        template_track: TemplateTrack::default(),
     };
     
     // Extract subdomain from domain
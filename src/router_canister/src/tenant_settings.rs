@@ -0,0 +1,135 @@
+use candid::Principal;
+use shared::{Tenant, TenantSettings, LMSError, LMSResult, current_time};
+use crate::types::TenantSettingsUpdate;
+use crate::storage::with_tenant_registry;
+use crate::lifecycle::{get_tenant, parse_canister_id};
+
+const MAX_NAME_LENGTH: usize = 100;
+const MAX_QUOTA: u32 = 1_000_000;
+
+/// Change a tenant's display name and push it to its canister.
+/// Admins are changed through `transfer_tenant_ownership`, which updates the tenant and audits it.
+pub async fn update_tenant_metadata(tenant_id: String, name: Option<String>) -> LMSResult<Tenant> {
+    let tenant = get_tenant(&tenant_id)?;
+    let Some(name) = name else { return Ok(tenant) };
+
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(LMSError::ValidationError(format!(
+            "Tenant name must be between 1 and {} characters", MAX_NAME_LENGTH
+        )));
+    }
+
+    // Suspended tenants only get the registry update; reactivation pushes it
+    if tenant.is_active {
+        let canister_id = parse_canister_id(&tenant)?;
+        push_name(canister_id, &name).await
+            .map_err(LMSError::InternalError)?;
+    }
+
+    // Re-read after the call so concurrent settings changes are kept
+    let mut tenant = get_tenant(&tenant_id)?;
+    tenant.name = name;
+    tenant.updated_at = current_time();
+    save_tenant(&tenant);
+
+    ic_cdk::println!("Updated tenant {} name to '{}'", tenant_id, tenant.name);
+    Ok(tenant)
+}
+
+/// Change a tenant's quotas and feature flags and push them to its canister.
+/// Suspended tenants only get the registry update; reactivation pushes it.
+pub async fn update_tenant_settings(tenant_id: String, update: TenantSettingsUpdate) -> LMSResult<Tenant> {
+    let tenant = get_tenant(&tenant_id)?;
+    let settings = apply_update(&tenant.settings, update)?;
+
+    // Push first so the registry never shows limits the tenant does not enforce
+    if tenant.is_active {
+        let canister_id = parse_canister_id(&tenant)?;
+        push_settings(canister_id, &settings).await
+            .map_err(LMSError::InternalError)?;
+    }
+
+    // Re-read after the call so concurrent metadata changes are kept
+    let mut tenant = get_tenant(&tenant_id)?;
+    tenant.settings = settings;
+    tenant.updated_at = current_time();
+    save_tenant(&tenant);
+
+    ic_cdk::println!("Updated tenant {} settings: {:?}", tenant_id, tenant.settings);
+    Ok(tenant)
+}
+
+/// Push the settings stored in the registry to the tenant canister, which enforces its quotas
+pub async fn sync_tenant_settings(tenant_id: String) -> LMSResult<()> {
+    let tenant = get_tenant(&tenant_id)?;
+    let canister_id = parse_canister_id(&tenant)?;
+    push_settings(canister_id, &tenant.settings).await
+        .map_err(LMSError::InternalError)?;
+
+    ic_cdk::println!("Pushed settings to tenant {} (canister: {})", tenant_id, canister_id);
+    Ok(())
+}
+
+pub(crate) async fn push_settings(canister_id: Principal, settings: &TenantSettings) -> Result<(), String> {
+    let (result,): (LMSResult<()>,) = ic_cdk::call(canister_id, "apply_tenant_settings", (settings.clone(),)).await
+        .map_err(|(code, msg)| format!("Settings call failed: {:?} - {}", code, msg))?;
+    result.map_err(|e| format!("Tenant rejected settings: {:?}", e))
+}
+
+pub(crate) async fn push_name(canister_id: Principal, name: &str) -> Result<(), String> {
+    let (result,): (LMSResult<()>,) = ic_cdk::call(canister_id, "set_tenant_name", (name.to_string(),)).await
+        .map_err(|(code, msg)| format!("Name call failed: {:?} - {}", code, msg))?;
+    result.map_err(|e| format!("Tenant rejected name: {:?}", e))
+}
+
+fn apply_update(current: &TenantSettings, update: TenantSettingsUpdate) -> LMSResult<TenantSettings> {
+    let settings = TenantSettings {
+        max_students: update.max_students.unwrap_or(current.max_students),
+        max_instructors: update.max_instructors.unwrap_or(current.max_instructors),
+        max_courses: update.max_courses.unwrap_or(current.max_courses),
+        allow_public_enrollment: update.allow_public_enrollment.unwrap_or(current.allow_public_enrollment),
+        custom_branding: update.custom_branding.unwrap_or(current.custom_branding),
+    };
+
+    for (name, value) in [
+        ("max_students", settings.max_students),
+        ("max_instructors", settings.max_instructors),
+        ("max_courses", settings.max_courses),
+    ] {
+        if value == 0 || value > MAX_QUOTA {
+            return Err(LMSError::ValidationError(format!("{} must be between 1 and {}", name, MAX_QUOTA)));
+        }
+    }
+
+    Ok(settings)
+}
+
+fn save_tenant(tenant: &Tenant) {
+    with_tenant_registry(|registry| {
+        registry.borrow_mut().insert(tenant.id.clone(), tenant.clone());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_update() {
+        let current = TenantSettings::default();
+        let update = TenantSettingsUpdate {
+            max_students: Some(50),
+            custom_branding: Some(true),
+            ..TenantSettingsUpdate::default()
+        };
+
+        let settings = apply_update(&current, update).unwrap();
+        assert_eq!(settings.max_students, 50);
+        assert!(settings.custom_branding);
+        assert_eq!(settings.max_courses, current.max_courses);
+
+        let zero = TenantSettingsUpdate { max_courses: Some(0), ..TenantSettingsUpdate::default() };
+        assert!(apply_update(&current, zero).is_err());
+    }
+}
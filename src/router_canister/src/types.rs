@@ -1,7 +1,6 @@
 use candid::Principal;
-use ic_stable_structures::{Storable, memory_manager::VirtualMemory, DefaultMemoryImpl};
-use std::borrow::Cow;
-use shared::Tenant;
+use ic_stable_structures::{storable::Bound, memory_manager::VirtualMemory, DefaultMemoryImpl};
+use shared::{Tenant, TenantSettings, TenantSummary, ReleaseChannel, TemplateTrack, BackupStore, StoreChecksum};
 
 pub type Memory = VirtualMemory<DefaultMemoryImpl>;
 
@@ -11,6 +10,10 @@ pub struct TemplateConfig {
     pub template_version: String,
     pub last_updated: u64,
     pub auto_update: bool,
+    /// SHA-256 of the active module in the WASM registry (None falls back to the embedded build)
+    pub active_module_hash: Option<String>,
+    /// Registry version tenants on the beta channel run; they follow stable while unset
+    pub beta_version: Option<String>,
 }
 
 impl Default for TemplateConfig {
@@ -20,30 +23,13 @@ impl Default for TemplateConfig {
             template_version: "1.0.0".to_string(),
             last_updated: 0,
             auto_update: true,
+            active_module_hash: None,
+            beta_version: None,
         }
     }
 }
 
-impl Storable for TemplateConfig {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        match candid::encode_one(self) {
-            Ok(bytes) => Cow::Owned(bytes),
-            Err(_) => Cow::Owned(vec![])
-        }
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        match candid::decode_one(&bytes) {
-            Ok(config) => config,
-            Err(_) => Self::default()
-        }
-    }
-
-    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded {
-        max_size: 1000,
-        is_fixed_size: false,
-    };
-}
+shared::versioned_storable!(TemplateConfig, bound = Bound::Bounded { max_size: 1000, is_fixed_size: false });
 
 #[derive(candid::CandidType, serde::Deserialize, serde::Serialize)]
 pub struct RouterStats {
@@ -86,3 +72,806 @@ pub struct FullSystemInspection {
     pub orphaned_tenants: Vec<(String, Tenant)>,
     pub data_consistency: bool,
 }
+
+/// Outcome of upgrading a single tenant canister during a fleet rollout
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum TenantUpgradeStatus {
+    Pending,
+    Succeeded,
+    Failed(String),
+    Skipped(String),
+    RolledBack,
+    RollbackFailed(String),
+}
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantUpgradeRecord {
+    pub tenant_id: String,
+    pub canister_id: Principal,
+    pub batch: u32,
+    pub status: TenantUpgradeStatus,
+    pub previous_module_hash: Option<String>,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum FleetUpgradeStatus {
+    Running,
+    Completed,
+    Halted(String),
+    RolledBack,
+}
+
+/// A fleet-wide rollout of the tenant WASM module
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct FleetUpgrade {
+    pub id: u64,
+    /// Channel whose tenants the rollout covers; rollouts from before channels existed are stable
+    pub channel: Option<ReleaseChannel>,
+    pub template_version: String,
+    pub module_hash: String,
+    pub canary_count: u32,
+    pub batch_size: u32,
+    pub halt_on_error: bool,
+    pub status: FleetUpgradeStatus,
+    pub records: Vec<TenantUpgradeRecord>,
+    pub started_by: Principal,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+shared::versioned_storable!(FleetUpgrade);
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum WasmUploadStatus {
+    Uploading,
+    Ready,
+    Failed(String),
+}
+
+/// A tenant WASM module version uploaded to the router
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct WasmModuleInfo {
+    pub version: String,
+    pub sha256: String,
+    pub total_size: u64,
+    pub uploaded_size: u64,
+    pub chunk_count: u32,
+    pub status: WasmUploadStatus,
+    pub uploaded_by: Principal,
+    pub created_at: u64,
+    pub completed_at: Option<u64>,
+}
+
+shared::versioned_storable!(WasmModuleInfo);
+
+/// Configuration for the automatic tenant cycles top-up monitor
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct CyclesMonitorConfig {
+    pub enabled: bool,
+    pub check_interval_seconds: u64,
+    /// Tenants below this balance are topped up
+    pub min_tenant_balance: u128,
+    pub top_up_amount: u128,
+    /// The router never tops up if doing so would leave it below this balance
+    pub router_reserve: u128,
+}
+
+impl Default for CyclesMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            check_interval_seconds: 6 * 60 * 60, // every 6 hours
+            min_tenant_balance: 300_000_000_000, // 300 billion cycles
+            top_up_amount: 500_000_000_000,      // 500 billion cycles
+            router_reserve: 1_000_000_000_000,   // 1 trillion cycles
+        }
+    }
+}
+
+shared::versioned_storable!(CyclesMonitorConfig, bound = Bound::Bounded { max_size: 500, is_fixed_size: false });
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct CyclesTopUp {
+    pub amount: u128,
+    pub balance_before: u128,
+    pub topped_up_at: u64,
+}
+
+/// Last known cycles state and top-up history of a tenant canister
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantCyclesStatus {
+    pub tenant_id: String,
+    pub canister_id: Principal,
+    pub last_balance: u128,
+    pub idle_cycles_burned_per_day: u128,
+    pub last_checked: u64,
+    pub last_error: Option<String>,
+    pub total_topped_up: u128,
+    pub top_ups: Vec<CyclesTopUp>,
+}
+
+shared::versioned_storable!(TenantCyclesStatus);
+
+/// Raised when a tenant is below the minimum balance and could not be topped up
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct CyclesAlert {
+    pub id: u64,
+    pub tenant_id: String,
+    pub canister_id: Principal,
+    pub balance: u128,
+    pub message: String,
+    pub raised_at: u64,
+    pub acknowledged: bool,
+}
+
+shared::versioned_storable!(CyclesAlert);
+
+/// Suspension record for a tenant (kept after reactivation for auditing)
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantSuspension {
+    pub tenant_id: String,
+    pub reason: String,
+    pub suspended_by: Principal,
+    pub suspended_at: u64,
+    pub reactivated_by: Option<Principal>,
+    pub reactivated_at: Option<u64>,
+}
+
+shared::versioned_storable!(TenantSuspension);
+
+/// Stage of a tenant decommission
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum DecommissionStatus {
+    Scheduled,
+    Purging,
+    Deleted,
+    Cancelled,
+    Failed(String),
+}
+
+/// Outcome of one step of a decommission
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct DecommissionStep {
+    pub step: String,
+    pub succeeded: bool,
+    pub message: String,
+    pub at: u64,
+}
+
+/// Decommission record for a tenant, kept as a tombstone once the canister is deleted
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantDecommission {
+    pub tenant: Tenant,
+    pub was_active: bool,
+    pub requested_by: Principal,
+    pub requested_at: u64,
+    pub purge_after: u64,
+    pub status: DecommissionStatus,
+    // Size and hash of the single-call export; only set by decommissions scheduled before backups
+    pub export_size: u64,
+    pub export_sha256: String,
+    pub cycles_recovered: u128,
+    pub steps: Vec<DecommissionStep>,
+    pub completed_at: Option<u64>,
+    // Backup the canister is checked against before it is deleted
+    pub backup_id: Option<u64>,
+    // When the canister was stopped after its backup completed
+    pub stopped_at: Option<u64>,
+}
+
+shared::versioned_storable!(TenantDecommission);
+
+/// Role of a platform operator on the router
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum OperatorRole {
+    // Full access to provisioning, upgrades and destructive endpoints
+    Operator,
+    // Day-to-day operations: alerts, cycles checks and diagnostics
+    Support,
+}
+
+/// Principal allowed to manage the platform through the router
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct PlatformOperator {
+    pub principal: Principal,
+    pub role: OperatorRole,
+    pub added_by: Principal,
+    pub added_at: u64,
+}
+
+shared::versioned_storable!(PlatformOperator);
+
+/// Verification state of a custom domain
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum CustomDomainStatus {
+    PendingVerification,
+    Verified,
+    Failed(String),
+}
+
+/// Full hostname served on behalf of a tenant, e.g. `learn.university.edu`
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct CustomDomain {
+    pub hostname: String,
+    pub tenant_id: String,
+    pub verification_token: String,
+    pub status: CustomDomainStatus,
+    pub created_by: Principal,
+    pub created_at: u64,
+    pub last_checked: Option<u64>,
+    pub verified_at: Option<u64>,
+}
+
+shared::versioned_storable!(CustomDomain);
+
+/// Platform domain under which tenants are served as subdomains
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct BaseDomain {
+    pub domain: String,
+    /// Ports accepted in the Host header; a host without a port is always accepted
+    pub ports: Vec<u16>,
+    /// Labels allowed in front of the domain; the one next to it is the tenant.
+    /// Zero marks a gateway domain (e.g. `icp0.io`) whose hosts never name a tenant.
+    pub wildcard_depth: u8,
+}
+
+/// Stable-stored list of platform base domains
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct BaseDomainConfig {
+    pub domains: Vec<BaseDomain>,
+    pub updated_at: u64,
+}
+
+impl Default for BaseDomainConfig {
+    fn default() -> Self {
+        let base_domain = |domain: &str, ports: Vec<u16>, wildcard_depth: u8| BaseDomain {
+            domain: domain.to_string(),
+            ports,
+            wildcard_depth,
+        };
+        Self {
+            domains: vec![
+                base_domain("lms.localhost", vec![4943, 8000], 1), // local development
+                base_domain("lms.app", vec![], 1),                 // production
+                base_domain("ic0.app", vec![], 0),
+                base_domain("icp0.io", vec![], 0),
+            ],
+            updated_at: 0,
+        }
+    }
+}
+
+shared::versioned_storable!(BaseDomainConfig);
+
+/// Former subdomain of a renamed tenant, answering redirects until it expires
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct SubdomainAlias {
+    pub subdomain: String,
+    pub tenant_id: String,
+    pub created_by: Principal,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+shared::versioned_storable!(SubdomainAlias);
+
+/// Rules applied to new tenant subdomains on top of DNS label validation
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct SubdomainPolicy {
+    pub min_length: u8,
+    /// Exact names that can never be used by a tenant
+    pub reserved_names: Vec<String>,
+    /// Words that may not appear anywhere in a new subdomain
+    pub blocked_words: Vec<String>,
+    pub updated_at: u64,
+}
+
+impl Default for SubdomainPolicy {
+    fn default() -> Self {
+        let reserved = [
+            "www", "admin", "api", "lms", "app", "apps", "auth", "login", "account", "dashboard",
+            "mail", "email", "smtp", "ftp", "ns1", "ns2", "dns", "cdn", "static", "assets",
+            "status", "support", "help", "docs", "blog", "billing", "router", "platform",
+            "tenant", "internal", "staging", "dev", "test", "localhost",
+        ];
+        Self {
+            min_length: 3,
+            reserved_names: reserved.iter().map(|name| name.to_string()).collect(),
+            blocked_words: Vec::new(),
+            updated_at: 0,
+        }
+    }
+}
+
+shared::versioned_storable!(SubdomainPolicy);
+
+/// Time bucket size for request metrics
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum MetricsGranularity {
+    Hourly,
+    Daily,
+}
+
+/// Request counters for one tenant over one time bucket
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+pub struct RequestMetrics {
+    pub requests: u64,
+    pub status_2xx: u64,
+    pub status_3xx: u64,
+    pub status_4xx: u64,
+    pub status_5xx: u64,
+    /// Requests the router could not deliver (unknown tenant, unreachable canister)
+    pub routing_failures: u64,
+}
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct MetricsBucket {
+    /// `None` for requests that did not resolve to a tenant
+    pub tenant_id: Option<String>,
+    pub granularity: MetricsGranularity,
+    pub bucket_start: u64,
+    pub metrics: RequestMetrics,
+}
+
+shared::versioned_storable!(MetricsBucket);
+
+/// Request counters summed over a period for one tenant
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantRequestTotals {
+    pub tenant_id: Option<String>,
+    pub since: u64,
+    pub metrics: RequestMetrics,
+}
+
+/// Fix proposed or applied by `repair_system`
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum RepairAction {
+    /// Tenant has no route for its subdomain
+    RecreateRoute { tenant_id: String, subdomain: String, canister_id: Principal },
+    /// Route points at a canister that no tenant uses, or at a tenant under a stale subdomain
+    RemoveRoute { subdomain: String, canister_id: Principal },
+    /// Tenant's canister no longer exists; the tenant is marked inactive
+    MarkCanisterMissing { tenant_id: String, canister_id: String, reason: String },
+}
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct RepairActionRecord {
+    pub action: RepairAction,
+    pub applied: bool,
+    pub error: Option<String>,
+}
+
+/// One run of `repair_system`, kept as an audit record
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct RepairRun {
+    pub id: u64,
+    pub dry_run: bool,
+    pub started_by: Principal,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub actions: Vec<RepairActionRecord>,
+}
+
+shared::versioned_storable!(RepairRun);
+
+/// Next step a tenant provisioning has to run
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum ProvisioningStep {
+    CreateCanister,
+    InstallCode,
+    ApplySettings,
+    Register,
+    Done,
+}
+
+/// State of a tenant provisioning
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum ProvisioningStatus {
+    InProgress,
+    Completed,
+    Failed(String),
+    Cancelled,
+}
+
+/// Persisted state machine for `register_university`, keyed by request ID
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantProvisioning {
+    pub request_id: u64,
+    pub tenant_id: String,
+    pub subdomain: String,
+    pub university_name: String,
+    pub admin_principal: Principal,
+    pub requested_by: Principal,
+    pub requested_at: u64,
+    pub next_step: ProvisioningStep,
+    pub status: ProvisioningStatus,
+    pub canister_id: Option<Principal>,
+    pub attempts: u32,
+    /// An attempt owns the record until this time, so concurrent resumes cannot run steps twice
+    pub lease_until: u64,
+    pub updated_at: u64,
+    pub completed_at: Option<u64>,
+    /// Track requested at registration; `None` follows the stable channel
+    pub template_track: Option<TemplateTrack>,
+    /// Template version and module hash installed by the `InstallCode` step
+    pub template_version: Option<String>,
+    pub module_hash: Option<String>,
+    /// Settings the tenant starts with; `None` uses the defaults
+    pub settings: Option<TenantSettings>,
+}
+
+shared::versioned_storable!(TenantProvisioning);
+
+/// Partial update of a tenant's settings; `None` fields keep their current value
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct TenantSettingsUpdate {
+    pub max_students: Option<u32>,
+    pub max_instructors: Option<u32>,
+    pub max_courses: Option<u32>,
+    pub allow_public_enrollment: Option<bool>,
+    pub custom_branding: Option<bool>,
+}
+
+/// A tenant summary stored under the collection run it belongs to
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantSummarySample {
+    pub bucket_start: u64,
+    pub summary: TenantSummary,
+}
+
+shared::versioned_storable!(TenantSummarySample);
+
+/// Platform-wide totals of one collection run
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct PlatformSummary {
+    pub bucket_start: u64,
+    pub tenants_reporting: u32,
+    pub students: u64,
+    pub instructors: u64,
+    pub admins: u64,
+    pub inactive_users: u64,
+    pub courses: u64,
+    pub published_courses: u64,
+    pub quiz_attempts: u64,
+    pub files: u64,
+    pub storage_bytes: u64,
+}
+
+/// Outcome of collecting summaries from every active tenant
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct SummaryCollection {
+    pub bucket_start: u64,
+    pub collected: u32,
+    pub failures: Vec<(String, String)>,
+}
+
+/// Public view of a tenant for the directory; admin principals and settings are left out
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct PublicTenant {
+    pub id: String,
+    pub name: String,
+    pub subdomain: String,
+    pub canister_id: String,
+    pub is_active: bool,
+    pub allow_public_enrollment: bool,
+    pub created_at: u64,
+}
+
+/// Filters and cursor for a page of the tenant directory
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct TenantDirectoryQuery {
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+    /// Matches a subdomain prefix or part of the name, case-insensitively
+    pub search: Option<String>,
+    pub active_only: bool,
+}
+
+/// One page of the tenant directory
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantDirectoryPage {
+    pub tenants: Vec<PublicTenant>,
+    pub next_cursor: Option<String>,
+}
+
+/// Prices applied to metered usage, in minor units of `currency` (e.g. cents)
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct PriceTable {
+    pub currency: String,
+    /// Charged once per tenant per statement
+    pub base_fee: u64,
+    pub per_trillion_cycles: u64,
+    /// Per GiB of canister memory held for a whole month
+    pub per_gib_memory_month: u64,
+    /// Per GiB of uploaded files held for a whole month
+    pub per_gib_storage_month: u64,
+    /// Per user at the month's peak of active users
+    pub per_active_user: u64,
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        Self {
+            currency: "USD".to_string(),
+            base_fee: 0,
+            per_trillion_cycles: 0,
+            per_gib_memory_month: 0,
+            per_gib_storage_month: 0,
+            per_active_user: 0,
+        }
+    }
+}
+
+shared::versioned_storable!(PriceTable);
+
+/// Usage metered for one tenant over one calendar month (UTC)
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+pub struct UsageMeter {
+    pub tenant_id: String,
+    /// "YYYY-MM"
+    pub period: String,
+    pub cycles_burned: u128,
+    /// Canister memory reported by `canister_status`, integrated over time
+    pub memory_byte_seconds: u128,
+    /// File storage reported by the tenant's latest summary, integrated over time
+    pub storage_byte_seconds: u128,
+    pub peak_active_users: u32,
+    pub samples: u32,
+    /// Readings of the last sample, carried into the next month as its baseline
+    pub last_cycles_balance: u128,
+    pub last_topped_up: u128,
+    pub last_memory_bytes: u64,
+    pub last_storage_bytes: u64,
+    pub last_sampled_at: u64,
+}
+
+shared::versioned_storable!(UsageMeter);
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum StatementStatus {
+    Issued,
+    Paid,
+}
+
+/// One charge on a statement
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct StatementLine {
+    pub description: String,
+    pub quantity: String,
+    pub amount: u64,
+}
+
+/// A tenant's charges for one month, priced when the month closed
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct BillingStatement {
+    /// "YYYY-MM#tenant_id"
+    pub id: String,
+    pub tenant_id: String,
+    pub tenant_name: String,
+    pub period: String,
+    pub currency: String,
+    pub lines: Vec<StatementLine>,
+    pub total: u64,
+    pub usage: UsageMeter,
+    pub issued_at: u64,
+    pub status: StatementStatus,
+    pub paid_at: Option<u64>,
+    pub payment_reference: Option<String>,
+}
+
+shared::versioned_storable!(BillingStatement);
+
+/// Filters for listing and exporting statements
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct StatementQuery {
+    pub period: Option<String>,
+    pub tenant_id: Option<String>,
+    pub unpaid_only: bool,
+}
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum StatementFormat {
+    Csv,
+    Json,
+}
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum ApplicationStatus {
+    /// Waiting for an operator until `reserved_until`; the subdomain is not held meanwhile
+    Pending,
+    /// Provisioning was started; its progress is on the provisioning record
+    Approved,
+    Rejected(String),
+    /// Not decided before `reserved_until`
+    Expired,
+}
+
+/// A university's request to join the platform, submitted through public signup
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantApplication {
+    pub id: u64,
+    pub university_name: String,
+    pub subdomain: String,
+    pub contact_name: String,
+    pub contact_email: String,
+    pub admin_principal: Principal,
+    pub submitted_by: Principal,
+    pub submitted_at: u64,
+    pub reserved_until: u64,
+    pub status: ApplicationStatus,
+    pub decided_by: Option<Principal>,
+    pub decided_at: Option<u64>,
+    pub provisioning_id: Option<u64>,
+    pub tenant_id: Option<String>,
+}
+
+shared::versioned_storable!(TenantApplication);
+
+/// Fields an applicant fills in; the admin defaults to the submitting principal
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantApplicationRequest {
+    pub university_name: String,
+    pub subdomain: String,
+    pub contact_name: String,
+    pub contact_email: String,
+    pub admin_principal: Option<Principal>,
+}
+
+/// A change to who controls or administers a tenant
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum OwnershipChange {
+    ControllerAdded(Principal),
+    ControllerRemoved(Principal),
+    AdminTransferred { from: Principal, to: Principal },
+}
+
+/// Audit record of an ownership change made through the router
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct OwnershipAuditEntry {
+    pub id: u64,
+    pub tenant_id: String,
+    pub change: OwnershipChange,
+    pub changed_by: Principal,
+    pub changed_at: u64,
+    /// Follow-up work that did not complete, e.g. a controller left in place
+    pub note: Option<String>,
+}
+
+shared::versioned_storable!(OwnershipAuditEntry);
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum BackupStatus {
+    Running,
+    Completed,
+    Failed(String),
+}
+
+/// A snapshot of a tenant's stable stores, kept on the router as exported batches
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantBackup {
+    pub id: u64,
+    pub tenant_id: String,
+    pub canister_id: Principal,
+    /// Admin a restore provisions with unless another is given
+    pub admin_principal: Option<Principal>,
+    pub status: BackupStatus,
+    /// Store being exported and the key to continue after; `None` once every store is done
+    pub current_store: Option<BackupStore>,
+    pub cursor: Option<String>,
+    pub batch_count: u32,
+    pub stores: Vec<StoreChecksum>,
+    pub started_by: Principal,
+    pub started_at: u64,
+    pub completed_at: Option<u64>,
+    /// Quotas and flags of the tenant when the backup started; a restore starts with them
+    pub settings: Option<TenantSettings>,
+}
+
+shared::versioned_storable!(TenantBackup);
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub enum RestoreStatus {
+    /// Waiting for the new tenant canister; retried until provisioning completes or is cancelled
+    Provisioning,
+    Replaying,
+    /// Re-exporting the restored stores to compare them with the backup
+    Verifying,
+    Verified,
+    Mismatched(Vec<String>),
+    Failed(String),
+}
+
+/// Restoring a backup into a freshly provisioned tenant canister
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantRestore {
+    pub id: u64,
+    pub backup_id: u64,
+    pub provisioning_id: u64,
+    pub tenant_id: String,
+    pub canister_id: Option<Principal>,
+    pub status: RestoreStatus,
+    pub last_error: Option<String>,
+    /// Next backup batch to replay
+    pub next_batch: u32,
+    pub verify_store: Option<BackupStore>,
+    pub verify_cursor: Option<String>,
+    pub restored: Vec<StoreChecksum>,
+    pub started_by: Principal,
+    pub started_at: u64,
+    pub completed_at: Option<u64>,
+    /// Admin the tenant was provisioned with; its user is created by the tenant, not replayed
+    pub admin_principal: Option<Principal>,
+    /// Backed-up admin replaced by `admin_principal`, revoked once the replay finishes
+    pub revoked_admin: Option<Principal>,
+}
+
+shared::versioned_storable!(TenantRestore);
+
+/// Tenant health monitor settings
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct HealthMonitorConfig {
+    pub enabled: bool,
+    pub check_interval_seconds: u64,
+    /// Consecutive failed checks after which a tenant is flagged unhealthy
+    pub failure_threshold: u32,
+}
+
+impl Default for HealthMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            check_interval_seconds: 5 * 60, // every 5 minutes
+            failure_threshold: 3,
+        }
+    }
+}
+
+shared::versioned_storable!(HealthMonitorConfig, bound = Bound::Bounded { max_size: 200, is_fixed_size: false });
+
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum HealthState {
+    Healthy,
+    /// Failing, but fewer times in a row than the failure threshold
+    Degraded,
+    Unhealthy,
+    /// Suspended tenants have their canister stopped and are not probed
+    Suspended,
+}
+
+/// Outcome of the latest health check of a tenant canister
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TenantHealth {
+    pub tenant_id: String,
+    pub subdomain: String,
+    pub canister_id: Principal,
+    pub state: HealthState,
+    pub consecutive_failures: u32,
+    /// Round trip of the `health_check` call, measured in consensus rounds so only roughly in ms
+    pub latency_ms: Option<u64>,
+    pub memory_size: Option<u128>,
+    pub cycles: Option<u128>,
+    pub module_hash: Option<String>,
+    pub last_error: Option<String>,
+    pub last_checked: u64,
+    pub last_healthy: Option<u64>,
+    /// When the tenant was flagged unhealthy; cleared once it passes a check
+    pub unhealthy_since: Option<u64>,
+}
+
+shared::versioned_storable!(TenantHealth);
+
+/// Health of every monitored tenant, worst first
+#[derive(candid::CandidType, serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct HealthDashboard {
+    pub healthy: u64,
+    pub degraded: u64,
+    pub unhealthy: u64,
+    pub suspended: u64,
+    /// Registered tenants that have not been checked yet
+    pub unchecked: u64,
+    pub last_checked: Option<u64>,
+    pub tenants: Vec<TenantHealth>,
+}
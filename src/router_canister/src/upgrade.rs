@@ -0,0 +1,277 @@
+use candid::Principal;
+use ic_cdk::caller;
+use shared::{ReleaseChannel, TemplateTrack, LMSError, LMSResult, current_time};
+use crate::types::{FleetUpgrade, FleetUpgradeStatus, TenantUpgradeRecord, TenantUpgradeStatus};
+use crate::storage::{with_fleet_upgrades, with_tenant_registry};
+use crate::template::{load_release_module, record_installed_template, resolve_track, version_for_hash};
+use crate::canister_management;
+
+/// Roll a channel's current release out to every tenant following it (stable when `channel`
+/// is `None`); pinned tenants and tenants on other channels are left alone.
+/// The first `canary_count` tenants form a canary batch; any canary failure halts the rollout.
+/// Remaining tenants are upgraded in batches of `batch_size`.
+pub async fn start_fleet_upgrade(
+    canary_count: u32,
+    batch_size: u32,
+    halt_on_error: bool,
+    channel: Option<ReleaseChannel>,
+) -> LMSResult<FleetUpgrade> {
+    if batch_size == 0 {
+        return Err(LMSError::ValidationError("Batch size must be greater than zero".to_string()));
+    }
+
+    let running = with_fleet_upgrades(|upgrades| {
+        upgrades.borrow().iter().any(|(_, upgrade)| upgrade.status == FleetUpgradeStatus::Running)
+    });
+    if running {
+        return Err(LMSError::AlreadyExists("A fleet upgrade is already running".to_string()));
+    }
+
+    let channel = channel.unwrap_or(ReleaseChannel::Stable);
+    let track = TemplateTrack::Channel(channel);
+    let release = resolve_track(&track)?;
+    let wasm_module = load_release_module(&release)?;
+    let module_hash = canister_management::store_tenant_module(&wasm_module);
+
+    let tenants: Vec<(String, String)> = with_tenant_registry(|registry| {
+        registry.borrow()
+            .iter()
+            .filter(|(_, tenant)| tenant.template_track == track)
+            .map(|(id, tenant)| (id, tenant.canister_id))
+            .collect()
+    });
+    let batches = plan_batches(tenants.len(), canary_count, batch_size);
+
+    let now = current_time();
+    let records = tenants.into_iter().zip(batches.iter()).map(|((tenant_id, canister_id), batch)| {
+        let (canister_id, status) = match Principal::from_text(&canister_id) {
+            Ok(principal) => (principal, TenantUpgradeStatus::Pending),
+            Err(_) => (Principal::anonymous(), TenantUpgradeStatus::Skipped("Invalid canister ID".to_string())),
+        };
+        TenantUpgradeRecord {
+            tenant_id,
+            canister_id,
+            batch: *batch,
+            status,
+            previous_module_hash: None,
+            started_at: now,
+            finished_at: None,
+        }
+    }).collect();
+
+    let mut upgrade = FleetUpgrade {
+        id: next_upgrade_id(),
+        channel: Some(channel),
+        template_version: release.version.clone(),
+        module_hash: module_hash.clone(),
+        canary_count,
+        batch_size,
+        halt_on_error,
+        status: FleetUpgradeStatus::Running,
+        records,
+        started_by: caller(),
+        started_at: now,
+        finished_at: None,
+    };
+    save_upgrade(&upgrade);
+
+    ic_cdk::println!("Starting fleet upgrade {} to {:?} version {} ({}) across {} tenants",
+                     upgrade.id, channel, release.version, module_hash, upgrade.records.len());
+
+    let batch_count = batches.iter().max().map(|last| last + 1).unwrap_or(0);
+    for batch in 0..batch_count {
+        let mut batch_failed = false;
+
+        for index in 0..upgrade.records.len() {
+            if upgrade.records[index].batch != batch || upgrade.records[index].status != TenantUpgradeStatus::Pending {
+                continue;
+            }
+            // An operator may have aborted the rollout while the previous tenant was upgrading
+            let stored = get_fleet_upgrade(upgrade.id)?;
+            if stored.status != FleetUpgradeStatus::Running {
+                return Ok(stored);
+            }
+
+            let canister_id = upgrade.records[index].canister_id;
+            upgrade.records[index].started_at = current_time();
+
+            let status = match canister_management::get_module_hash(canister_id).await {
+                Ok(Some(previous)) if previous == module_hash => {
+                    upgrade.records[index].previous_module_hash = Some(previous);
+                    TenantUpgradeStatus::Skipped("Already running target module".to_string())
+                },
+                Ok(previous) => {
+                    upgrade.records[index].previous_module_hash = previous;
+                    match canister_management::upgrade_canister_code(canister_id, wasm_module.clone()).await {
+                        Ok(_) => {
+                            crate::schema::finish_tenant_migrations(canister_id).await;
+                            TenantUpgradeStatus::Succeeded
+                        },
+                        Err(e) => TenantUpgradeStatus::Failed(e),
+                    }
+                },
+                Err(e) => TenantUpgradeStatus::Failed(e),
+            };
+
+            match status {
+                TenantUpgradeStatus::Failed(_) => batch_failed = true,
+                TenantUpgradeStatus::Succeeded | TenantUpgradeStatus::Skipped(_) => record_installed_template(
+                    &upgrade.records[index].tenant_id, Some(release.version.clone()), module_hash.clone()
+                ),
+                _ => {}
+            }
+            upgrade.records[index].status = status;
+            upgrade.records[index].finished_at = Some(current_time());
+            save_upgrade(&upgrade);
+        }
+
+        let is_canary = batch == 0 && canary_count > 0;
+        if batch_failed && (is_canary || halt_on_error) {
+            let reason = if is_canary {
+                "Canary batch failed".to_string()
+            } else {
+                format!("Batch {} failed", batch)
+            };
+            halt_upgrade(&mut upgrade, reason);
+            return Ok(upgrade);
+        }
+    }
+
+    upgrade.status = FleetUpgradeStatus::Completed;
+    upgrade.finished_at = Some(current_time());
+    save_upgrade(&upgrade);
+
+    ic_cdk::println!("Fleet upgrade {} completed", upgrade.id);
+    Ok(upgrade)
+}
+
+/// Stop a running rollout, for instance one left running when its call trapped or the router
+/// was upgraded mid-rollout. Tenants not upgraded yet are skipped; upgraded ones keep the
+/// new module and can be rolled back.
+pub fn abort_fleet_upgrade(upgrade_id: u64) -> LMSResult<FleetUpgrade> {
+    let mut upgrade = get_fleet_upgrade(upgrade_id)?;
+    if upgrade.status != FleetUpgradeStatus::Running {
+        return Err(LMSError::ValidationError(format!("Fleet upgrade {} is not running", upgrade_id)));
+    }
+
+    halt_upgrade(&mut upgrade, format!("Aborted by {}", caller()));
+    Ok(upgrade)
+}
+
+/// Reinstall the previous module on every tenant that was upgraded by the given rollout.
+/// Previous modules are kept by hash; a tenant whose module was installed by a router build
+/// that did not keep it cannot be rolled back and is marked `RollbackFailed`.
+pub async fn rollback_fleet_upgrade(upgrade_id: u64) -> LMSResult<FleetUpgrade> {
+    let mut upgrade = get_fleet_upgrade(upgrade_id)?;
+
+    match upgrade.status {
+        FleetUpgradeStatus::Running => {
+            return Err(LMSError::ValidationError("Cannot roll back a running fleet upgrade".to_string()));
+        },
+        FleetUpgradeStatus::RolledBack => {
+            return Err(LMSError::ValidationError("Fleet upgrade already rolled back".to_string()));
+        },
+        _ => {}
+    }
+
+    for index in 0..upgrade.records.len() {
+        if upgrade.records[index].status != TenantUpgradeStatus::Succeeded {
+            continue;
+        }
+
+        let record = &upgrade.records[index];
+        let previous_module = record.previous_module_hash.clone()
+            .and_then(|hash| canister_management::get_stored_tenant_module(&hash).map(|module| (hash, module)));
+
+        let status = match previous_module {
+            Some((hash, module)) => match canister_management::upgrade_canister_code(record.canister_id, module).await {
+                Ok(_) => {
+                    record_installed_template(&record.tenant_id, version_for_hash(&hash), hash);
+                    TenantUpgradeStatus::RolledBack
+                },
+                Err(e) => TenantUpgradeStatus::RollbackFailed(e),
+            },
+            None => TenantUpgradeStatus::RollbackFailed("Previous module is not available".to_string()),
+        };
+
+        upgrade.records[index].status = status;
+        upgrade.records[index].finished_at = Some(current_time());
+        save_upgrade(&upgrade);
+    }
+
+    upgrade.status = FleetUpgradeStatus::RolledBack;
+    upgrade.finished_at = Some(current_time());
+    save_upgrade(&upgrade);
+
+    ic_cdk::println!("Fleet upgrade {} rolled back", upgrade.id);
+    Ok(upgrade)
+}
+
+/// Get a fleet upgrade by ID
+pub fn get_fleet_upgrade(upgrade_id: u64) -> LMSResult<FleetUpgrade> {
+    with_fleet_upgrades(|upgrades| {
+        upgrades.borrow()
+            .get(&upgrade_id)
+            .ok_or_else(|| LMSError::NotFound(format!("Fleet upgrade {} not found", upgrade_id)))
+    })
+}
+
+/// List all fleet upgrades, oldest first
+pub fn list_fleet_upgrades() -> Vec<FleetUpgrade> {
+    with_fleet_upgrades(|upgrades| {
+        upgrades.borrow().iter().map(|(_, upgrade)| upgrade).collect()
+    })
+}
+
+/// Assign each tenant (by position) to a batch: canaries first, then fixed-size batches
+fn plan_batches(tenant_count: usize, canary_count: u32, batch_size: u32) -> Vec<u32> {
+    let canary_count = canary_count as usize;
+    let batch_size = batch_size.max(1) as usize;
+    let first_batch = if canary_count > 0 { 1 } else { 0 };
+
+    (0..tenant_count).map(|position| {
+        if position < canary_count {
+            0
+        } else {
+            (first_batch + (position - canary_count) / batch_size) as u32
+        }
+    }).collect()
+}
+
+fn halt_upgrade(upgrade: &mut FleetUpgrade, reason: String) {
+    for record in upgrade.records.iter_mut() {
+        if record.status == TenantUpgradeStatus::Pending {
+            record.status = TenantUpgradeStatus::Skipped("Rollout halted".to_string());
+        }
+    }
+
+    ic_cdk::println!("Fleet upgrade {} halted: {}", upgrade.id, reason);
+    upgrade.status = FleetUpgradeStatus::Halted(reason);
+    upgrade.finished_at = Some(current_time());
+    save_upgrade(upgrade);
+}
+
+fn next_upgrade_id() -> u64 {
+    with_fleet_upgrades(|upgrades| {
+        upgrades.borrow().last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
+    })
+}
+
+fn save_upgrade(upgrade: &FleetUpgrade) {
+    with_fleet_upgrades(|upgrades| {
+        upgrades.borrow_mut().insert(upgrade.id, upgrade.clone());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::plan_batches;
+
+    #[test]
+    fn test_plan_batches_with_canaries() {
+        assert_eq!(plan_batches(6, 1, 2), vec![0, 1, 1, 2, 2, 3]);
+        assert_eq!(plan_batches(5, 0, 2), vec![0, 0, 1, 1, 2]);
+        assert_eq!(plan_batches(2, 5, 3), vec![0, 0]);
+        assert!(plan_batches(0, 1, 1).is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+use ic_cdk::caller;
+use shared::{LMSError, LMSResult, current_time};
+use crate::types::{WasmModuleInfo, WasmUploadStatus};
+use crate::storage::{with_template_config, with_wasm_chunks, with_wasm_registry};
+use crate::canister_management;
+
+/// Start a chunked upload of a tenant WASM module
+pub fn begin_wasm_upload(version: String, sha256: String, total_size: u64) -> LMSResult<WasmModuleInfo> {
+    if parse_semver(&version).is_none() {
+        return Err(LMSError::ValidationError(format!("Invalid semantic version '{}'", version)));
+    }
+
+    let sha256 = sha256.to_lowercase();
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(LMSError::ValidationError("SHA-256 hash must be 64 hex characters".to_string()));
+    }
+
+    if total_size == 0 {
+        return Err(LMSError::ValidationError("Module size must be greater than zero".to_string()));
+    }
+
+    let existing = with_wasm_registry(|registry| registry.borrow().get(&version));
+    if let Some(existing) = existing {
+        if existing.status == WasmUploadStatus::Ready {
+            return Err(LMSError::AlreadyExists(format!("WASM version {} already uploaded", version)));
+        }
+        // Restart an unfinished or failed upload from scratch
+        clear_chunks(&version);
+    }
+
+    let info = WasmModuleInfo {
+        version: version.clone(),
+        sha256,
+        total_size,
+        uploaded_size: 0,
+        chunk_count: 0,
+        status: WasmUploadStatus::Uploading,
+        uploaded_by: caller(),
+        created_at: current_time(),
+        completed_at: None,
+    };
+
+    with_wasm_registry(|registry| {
+        registry.borrow_mut().insert(version.clone(), info.clone());
+    });
+
+    ic_cdk::println!("Started WASM upload for version {} ({} bytes)", version, info.total_size);
+    Ok(info)
+}
+
+/// Append the next chunk of an in-progress upload (chunks must arrive in order)
+pub fn upload_wasm_chunk(version: String, chunk_index: u32, data: Vec<u8>) -> LMSResult<WasmModuleInfo> {
+    let mut info = get_uploading(&version)?;
+
+    if chunk_index != info.chunk_count {
+        return Err(LMSError::ValidationError(format!(
+            "Expected chunk {} but received chunk {}", info.chunk_count, chunk_index
+        )));
+    }
+
+    if data.is_empty() {
+        return Err(LMSError::ValidationError("Chunk data is empty".to_string()));
+    }
+
+    if info.uploaded_size + data.len() as u64 > info.total_size {
+        return Err(LMSError::ValidationError("Chunk exceeds declared module size".to_string()));
+    }
+
+    info.uploaded_size += data.len() as u64;
+    info.chunk_count += 1;
+
+    with_wasm_chunks(|chunks| {
+        chunks.borrow_mut().insert(chunk_key(&version, chunk_index), data);
+    });
+    with_wasm_registry(|registry| {
+        registry.borrow_mut().insert(version, info.clone());
+    });
+
+    Ok(info)
+}
+
+/// Assemble the uploaded chunks and verify size and hash
+pub fn finalize_wasm_upload(version: String) -> LMSResult<WasmModuleInfo> {
+    let mut info = get_uploading(&version)?;
+
+    if info.uploaded_size != info.total_size {
+        return Err(LMSError::ValidationError(format!(
+            "Upload incomplete: {} of {} bytes received", info.uploaded_size, info.total_size
+        )));
+    }
+
+    let module: Vec<u8> = with_wasm_chunks(|chunks| {
+        let chunks = chunks.borrow();
+        (0..info.chunk_count)
+            .filter_map(|index| chunks.get(&chunk_key(&version, index)))
+            .flatten()
+            .collect()
+    });
+    clear_chunks(&version);
+
+    let actual_hash = canister_management::module_hash_hex(&module);
+    if actual_hash != info.sha256 {
+        info.status = WasmUploadStatus::Failed(format!("Hash mismatch: computed {}", actual_hash));
+    } else if canister_management::is_placeholder_module(&module) {
+        info.status = WasmUploadStatus::Failed("Module is not a valid WASM binary".to_string());
+    } else {
+        canister_management::store_tenant_module(&module);
+        info.status = WasmUploadStatus::Ready;
+    }
+    info.completed_at = Some(current_time());
+
+    with_wasm_registry(|registry| {
+        registry.borrow_mut().insert(version.clone(), info.clone());
+    });
+
+    match &info.status {
+        WasmUploadStatus::Failed(reason) => Err(LMSError::ValidationError(reason.clone())),
+        _ => {
+            ic_cdk::println!("WASM version {} ready ({})", version, info.sha256);
+            Ok(info)
+        }
+    }
+}
+
+/// Make an uploaded version the module used for provisioning and upgrades
+pub fn activate_wasm_version(version: String) -> LMSResult<WasmModuleInfo> {
+    let info = get_wasm_version(version.clone())?;
+    if info.status != WasmUploadStatus::Ready {
+        return Err(LMSError::ValidationError(format!("WASM version {} is not ready", version)));
+    }
+
+    with_template_config(|template| {
+        let mut config = template.borrow().get().clone();
+        config.template_version = info.version.clone();
+        config.active_module_hash = Some(info.sha256.clone());
+        config.last_updated = current_time();
+        template.borrow_mut().set(config).map_err(|_| {
+            LMSError::InternalError("Failed to store template configuration".to_string())
+        })
+    })?;
+
+    ic_cdk::println!("Activated tenant WASM version {}", version);
+    Ok(info)
+}
+
+/// Point the beta channel at an uploaded version, or clear it so beta follows stable.
+/// Beta tenants move when the next beta fleet upgrade runs.
+pub fn set_beta_version(version: Option<String>) -> LMSResult<Option<String>> {
+    if let Some(version) = &version {
+        if find_ready_version(version).is_none() {
+            return Err(LMSError::ValidationError(format!("WASM version {} is not ready", version)));
+        }
+    }
+
+    with_template_config(|template| {
+        let mut config = template.borrow().get().clone();
+        config.beta_version = version.clone();
+        config.last_updated = current_time();
+        template.borrow_mut().set(config).map_err(|_| {
+            LMSError::InternalError("Failed to store template configuration".to_string())
+        })
+    })?;
+
+    ic_cdk::println!("Beta channel set to {:?}", version);
+    Ok(version)
+}
+
+/// Get an uploaded WASM version
+pub fn get_wasm_version(version: String) -> LMSResult<WasmModuleInfo> {
+    with_wasm_registry(|registry| {
+        registry.borrow()
+            .get(&version)
+            .ok_or_else(|| LMSError::NotFound(format!("WASM version {} not found", version)))
+    })
+}
+
+/// List all uploaded WASM versions, newest version first
+pub fn list_wasm_versions() -> Vec<WasmModuleInfo> {
+    let mut versions: Vec<WasmModuleInfo> = with_wasm_registry(|registry| {
+        registry.borrow().iter().map(|(_, info)| info).collect()
+    });
+    versions.sort_by_key(|info| std::cmp::Reverse(parse_semver(&info.version)));
+    versions
+}
+
+/// Find the registry entry for a version that is ready to install
+pub fn find_ready_version(version: &str) -> Option<WasmModuleInfo> {
+    with_wasm_registry(|registry| registry.borrow().get(&version.to_string()))
+        .filter(|info| info.status == WasmUploadStatus::Ready)
+}
+
+fn get_uploading(version: &str) -> LMSResult<WasmModuleInfo> {
+    let info = get_wasm_version(version.to_string())?;
+    if info.status != WasmUploadStatus::Uploading {
+        return Err(LMSError::ValidationError(format!("WASM version {} is not being uploaded", version)));
+    }
+    Ok(info)
+}
+
+fn chunk_key(version: &str, chunk_index: u32) -> String {
+    format!("{}#{:08}", version, chunk_index)
+}
+
+fn clear_chunks(version: &str) {
+    let prefix = format!("{}#", version);
+    with_wasm_chunks(|chunks| {
+        let keys: Vec<String> = chunks.borrow()
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, _)| key)
+            .collect();
+        let mut chunks = chunks.borrow_mut();
+        for key in keys {
+            chunks.remove(&key);
+        }
+    });
+}
+
+/// Parse a `MAJOR.MINOR.PATCH` version with an optional `-prerelease` or `+build` suffix
+pub fn parse_semver(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.split(['-', '+']).next()?;
+    let parts: Vec<&str> = core.split('.').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let mut numbers = [0u64; 3];
+    for (slot, part) in numbers.iter_mut().zip(parts) {
+        if part.is_empty() || (part.len() > 1 && part.starts_with('0')) {
+            return None;
+        }
+        *slot = part.parse().ok()?;
+    }
+
+    Some((numbers[0], numbers[1], numbers[2]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_semver;
+
+    #[test]
+    fn test_parse_semver() {
+        assert_eq!(parse_semver("1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_semver("2.0.0-beta.1"), Some((2, 0, 0)));
+        assert_eq!(parse_semver("0.10.1+build5"), Some((0, 10, 1)));
+        assert_eq!(parse_semver("1.2"), None);
+        assert_eq!(parse_semver("01.2.3"), None);
+        assert_eq!(parse_semver("1.x.3"), None);
+    }
+}
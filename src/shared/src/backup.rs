@@ -0,0 +1,128 @@
+// Tenant backups streamed as batches of stored entries
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A tenant stable store included in backups
+#[derive(CandidType, Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum BackupStore {
+    Users,
+    Courses,
+    Lessons,
+    Quizzes,
+    QuizAttempts,
+    Grades,
+    PreProvisionedUsers,
+    FileMetadata,
+    FileChunks,
+}
+
+impl BackupStore {
+    /// Every backed-up store, in export order
+    pub const ALL: [BackupStore; 9] = [
+        BackupStore::Users,
+        BackupStore::Courses,
+        BackupStore::Lessons,
+        BackupStore::Quizzes,
+        BackupStore::QuizAttempts,
+        BackupStore::Grades,
+        BackupStore::PreProvisionedUsers,
+        BackupStore::FileMetadata,
+        BackupStore::FileChunks,
+    ];
+
+    /// The store exported after this one
+    pub fn next(self) -> Option<BackupStore> {
+        let position = Self::ALL.iter().position(|store| *store == self)?;
+        Self::ALL.get(position + 1).copied()
+    }
+}
+
+/// One map entry; `value` is the versioned envelope the tenant stores, so older
+/// backups are upgraded when they are restored into a newer build
+#[derive(CandidType, Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BackupEntry {
+    pub key: String,
+    pub value: Vec<u8>,
+}
+
+/// Consecutive entries of one store, exported in key order
+#[derive(CandidType, Deserialize, Serialize, Debug, Clone)]
+pub struct BackupBatch {
+    pub store: BackupStore,
+    /// Schema version the exporting build writes this store's values at
+    pub schema_version: u16,
+    pub entries: Vec<BackupEntry>,
+    /// Key to continue the export after; `None` once the store is done
+    pub next_cursor: Option<String>,
+}
+
+/// Entry count, size and checksum of one store's entries
+#[derive(CandidType, Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct StoreChecksum {
+    pub store: BackupStore,
+    pub schema_version: u16,
+    pub entries: u64,
+    pub bytes: u64,
+    /// Wrapping sum of the entries' digests, so it can be accumulated batch by batch
+    pub checksum: u128,
+}
+
+impl StoreChecksum {
+    pub fn new(store: BackupStore, schema_version: u16) -> Self {
+        Self { store, schema_version, entries: 0, bytes: 0, checksum: 0 }
+    }
+
+    /// Fold a batch of this store into the totals
+    pub fn add(&mut self, entries: &[BackupEntry]) {
+        for entry in entries {
+            self.entries += 1;
+            self.bytes += (entry.key.len() + entry.value.len()) as u64;
+            self.checksum = self.checksum.wrapping_add(entry_digest(entry));
+        }
+    }
+}
+
+/// First 16 bytes of the SHA-256 of an entry's key and value
+pub fn entry_digest(entry: &BackupEntry) -> u128 {
+    let mut hasher = Sha256::new();
+    hasher.update((entry.key.len() as u64).to_le_bytes());
+    hasher.update(entry.key.as_bytes());
+    hasher.update(&entry.value);
+    let digest = hasher.finalize();
+    let mut truncated = [0u8; 16];
+    truncated.copy_from_slice(&digest[..16]);
+    u128::from_le_bytes(truncated)
+}
+
+#[cfg(feature = "stable-storage")]
+versioned_storable!(BackupBatch);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_checksums() {
+        let entry = |key: &str, value: &[u8]| BackupEntry { key: key.to_string(), value: value.to_vec() };
+        let entries = vec![entry("a", b"1"), entry("b", b"2"), entry("c", b"3")];
+
+        // Accumulating batch by batch matches one pass over every entry
+        let mut whole = StoreChecksum::new(BackupStore::Users, 1);
+        whole.add(&entries);
+        let mut batched = StoreChecksum::new(BackupStore::Users, 1);
+        batched.add(&entries[..1]);
+        batched.add(&entries[1..]);
+        assert_eq!(whole, batched);
+        assert_eq!(whole.entries, 3);
+        assert_eq!(whole.bytes, 6);
+
+        let mut changed = StoreChecksum::new(BackupStore::Users, 1);
+        changed.add(&[entry("a", b"1"), entry("b", b"2"), entry("c", b"4")]);
+        assert_ne!(whole.checksum, changed.checksum);
+
+        assert_eq!(BackupStore::Users.next(), Some(BackupStore::Courses));
+        assert_eq!(BackupStore::FileChunks.next(), None);
+    }
+}
@@ -18,6 +18,7 @@ pub enum LMSError {
     UserNotAuthenticated(String),
     RoleNotFound(String),
     InvalidRoleAssignment(String),
+    QuotaExceeded(String),
 }
 
 /// Result type alias for common LMS operations
@@ -60,6 +61,7 @@ impl fmt::Display for LMSError {
             LMSError::UserNotAuthenticated(msg) => write!(f, "User not authenticated: {}", msg),
             LMSError::RoleNotFound(msg) => write!(f, "Role not found: {}", msg),
             LMSError::InvalidRoleAssignment(msg) => write!(f, "Invalid role assignment: {}", msg),
+            LMSError::QuotaExceeded(msg) => write!(f, "Quota exceeded: {}", msg),
         }
     }
 }
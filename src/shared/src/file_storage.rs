@@ -2,11 +2,10 @@
 // Handles chunked uploads, streaming downloads, and metadata management
 
 #[cfg(feature = "stable-storage")]
-use ic_stable_structures::{Storable, storable::Bound};
+use ic_stable_structures::storable::Bound;
 
 use candid::{CandidType, Deserialize};
 use serde::Serialize;
-use std::borrow::Cow;
 use crate::error::{LMSError, LMSResult};
 use crate::utils::current_time;
 
@@ -132,80 +131,16 @@ pub const DOWNLOAD_STREAM_TIMEOUT: u64 = 1800; // 30 minutes
 pub const MAX_FILES_PER_USER: u64 = 10000;
 pub const MAX_STORAGE_PER_USER: u64 = 10_000_000_000; // 10GB per user
 
-impl Storable for FileMetadata {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
-    }
-
-    const BOUND: Bound = Bound::Bounded {
-        max_size: 2048,
-        is_fixed_size: false,
-    };
-}
-
-impl Storable for FileChunk {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
-    }
-
-    const BOUND: Bound = Bound::Bounded {
-        max_size: 1_048_576,
-        is_fixed_size: false,
-    };
-}
-
-impl Storable for UploadSession {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
-    }
-
-    const BOUND: Bound = Bound::Bounded {
-        max_size: 4096,
-        is_fixed_size: false,
-    };
-}
-
-impl Storable for DownloadStream {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
-    }
-
-    const BOUND: Bound = Bound::Bounded {
-        max_size: 1024,
-        is_fixed_size: false,
-    };
-}
-
-impl Storable for FileStats {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
-    }
-
-    const BOUND: Bound = Bound::Bounded {
-        max_size: 8192,
-        is_fixed_size: false,
-    };
-}
+#[cfg(feature = "stable-storage")]
+versioned_storable!(FileMetadata, bound = Bound::Bounded { max_size: 2048, is_fixed_size: false });
+#[cfg(feature = "stable-storage")]
+versioned_storable!(FileChunk, bound = Bound::Bounded { max_size: 1_048_576, is_fixed_size: false });
+#[cfg(feature = "stable-storage")]
+versioned_storable!(UploadSession, bound = Bound::Bounded { max_size: 4096, is_fixed_size: false });
+#[cfg(feature = "stable-storage")]
+versioned_storable!(DownloadStream, bound = Bound::Bounded { max_size: 1024, is_fixed_size: false });
+#[cfg(feature = "stable-storage")]
+versioned_storable!(FileStats, bound = Bound::Bounded { max_size: 8192, is_fixed_size: false });
 
 impl FileMetadata {
     /// Create new file metadata
@@ -1,10 +1,6 @@
 use candid::{CandidType, Deserialize};
 use serde::Serialize;
 
-#[cfg(feature = "stable-storage")]
-use ic_stable_structures::Storable;
-#[cfg(feature = "stable-storage")]
-use std::borrow::Cow;
 
 /// Grade representation
 #[derive(CandidType, Deserialize, Serialize, Debug, Clone, PartialEq)]
@@ -38,14 +34,4 @@ pub enum GradeType {
 
 // Stable storage implementations
 #[cfg(feature = "stable-storage")]
-impl Storable for Grade {
-    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
-    
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
-    }
-}
+versioned_storable!(Grade);
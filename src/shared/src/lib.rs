@@ -1,6 +1,8 @@
 // Modular shared library for Decentralized LMS
 // Re-export all modules to maintain backward compatibility
 
+#[macro_use]
+pub mod versioned;
 pub mod error;
 pub mod user;
 pub mod course;
@@ -9,13 +11,16 @@ pub mod grade;
 pub mod utils;
 pub mod pre_provision;
 pub mod file_storage;
+pub mod tenant_export;
+pub mod tenant_summary;
+pub mod backup;
 
 #[cfg(test)]
 pub mod tests;
 
 // Re-export types for backward compatibility
 pub use error::{LMSError, LMSResult};
-pub use user::{User, UserRole, Tenant, TenantSettings};
+pub use user::{User, UserRole, Tenant, TenantSettings, ReleaseChannel, TemplateTrack, QuotaUsage, TenantQuotaUsage};
 pub use course::{Course, Lesson, LessonType};
 pub use quiz::{Quiz, Question, QuestionType, QuizAttempt, Answer};
 pub use grade::{Grade, GradeType};
@@ -27,4 +32,8 @@ pub use file_storage::{
     FileMetadata, FileChunk, UploadSession, DownloadStream, FileOperationResult,
     FileStats, PrivacyLevel, OwnerType
 };
+pub use tenant_export::TenantDataExport;
+pub use tenant_summary::TenantSummary;
+pub use backup::{BackupStore, BackupEntry, BackupBatch, StoreChecksum};
+pub use versioned::{Versioned, SchemaState, StoreMigration};
 pub use utils::*;
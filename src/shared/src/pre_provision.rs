@@ -4,11 +4,6 @@
 use candid::CandidType;
 use serde::{Deserialize, Serialize};
 
-#[cfg(feature = "stable-storage")]
-use ic_stable_structures::Storable;
-#[cfg(feature = "stable-storage")]
-use std::borrow::Cow;
-
 use crate::{UserRole, LMSResult, LMSError};
 
 /// Pre-provisioned user record (before II linking)
@@ -215,17 +210,7 @@ impl PreProvisionedUser {
 }
 
 #[cfg(feature = "stable-storage")]
-impl Storable for PreProvisionedUser {
-    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
-
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
-    }
-}
+versioned_storable!(PreProvisionedUser);
 
 #[cfg(test)]
 mod tests {
@@ -1,11 +1,6 @@
 use candid::CandidType;
 use serde::{Deserialize, Serialize};
 
-#[cfg(feature = "stable-storage")]
-use ic_stable_structures::Storable;
-#[cfg(feature = "stable-storage")]
-use std::borrow::Cow;
-
 /// Quiz representation
 #[derive(CandidType, Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Quiz {
@@ -63,27 +58,6 @@ pub struct Answer {
 
 // Stable storage implementations
 #[cfg(feature = "stable-storage")]
-impl Storable for Quiz {
-    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
-    
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
-    }
-}
-
+versioned_storable!(Quiz);
 #[cfg(feature = "stable-storage")]
-impl Storable for QuizAttempt {
-    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
-    
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
-    }
-}
+versioned_storable!(QuizAttempt);
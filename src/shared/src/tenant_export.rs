@@ -0,0 +1,22 @@
+// Full data export of a tenant canister, used when decommissioning a university
+
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::{User, Course, Lesson, Quiz, QuizAttempt, Grade, PreProvisionedUser, FileMetadata};
+
+/// Snapshot of every record held by a tenant canister
+#[derive(CandidType, Deserialize, Serialize, Debug, Clone)]
+pub struct TenantDataExport {
+    pub tenant_id: String,
+    pub admin_principal: Principal,
+    pub exported_at: u64,
+    pub users: Vec<User>,
+    pub courses: Vec<Course>,
+    pub lessons: Vec<Lesson>,
+    pub quizzes: Vec<Quiz>,
+    pub quiz_attempts: Vec<QuizAttempt>,
+    pub grades: Vec<Grade>,
+    pub pre_provisioned_users: Vec<PreProvisionedUser>,
+    pub files: Vec<FileMetadata>,
+}
@@ -0,0 +1,20 @@
+// Usage summary reported by each tenant canister for platform analytics
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// Counts a tenant canister reports to the router; user counts only include active accounts
+#[derive(CandidType, Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+pub struct TenantSummary {
+    pub tenant_id: String,
+    pub collected_at: u64,
+    pub students: u32,
+    pub instructors: u32,
+    pub admins: u32,
+    pub inactive_users: u32,
+    pub courses: u32,
+    pub published_courses: u32,
+    pub quiz_attempts: u64,
+    pub files: u64,
+    pub storage_bytes: u64,
+}
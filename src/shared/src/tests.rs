@@ -103,6 +103,9 @@ mod tests {
             ],
             time_limit_minutes: Some(30),
             max_attempts: 3,
+            start_date: 1234567890,
+            end_date: 1234567890 + 7 * 24 * 60 * 60 * 1_000_000_000,
+            duration_minutes: 30,
             created_at: 1234567890,
             updated_at: 1234567890,
         };
@@ -1,11 +1,6 @@
 use candid::CandidType;
 use serde::{Deserialize, Serialize};
 
-#[cfg(feature = "stable-storage")]
-use ic_stable_structures::Storable;
-#[cfg(feature = "stable-storage")]
-use std::borrow::Cow;
-
 /// User representation shared across canisters
 #[derive(CandidType, Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct User {
@@ -132,6 +127,33 @@ pub struct Tenant {
     pub is_active: bool,
     #[serde(default)]
     pub settings: TenantSettings,
+    /// Template version and module hash the router last installed on the tenant's canister
+    #[serde(default)]
+    pub template_version: Option<String>,
+    #[serde(default)]
+    pub module_hash: Option<String>,
+    #[serde(default)]
+    pub template_track: TemplateTrack,
+}
+
+/// A line of tenant template releases
+#[derive(Debug, Clone, Copy, PartialEq, CandidType, Serialize, Deserialize)]
+pub enum ReleaseChannel {
+    Stable,
+    Beta,
+}
+
+/// Which template version a tenant runs: whatever its channel points at, or a pinned version
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+pub enum TemplateTrack {
+    Channel(ReleaseChannel),
+    Pinned(String),
+}
+
+impl Default for TemplateTrack {
+    fn default() -> Self {
+        TemplateTrack::Channel(ReleaseChannel::Stable)
+    }
 }
 
 fn default_updated_at() -> u64 {
@@ -159,62 +181,145 @@ impl Default for TenantSettings {
     }
 }
 
+/// Usage of one quota; `pending` counts pre-provisioned users who hold a seat but have not linked yet
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub used: u32,
+    pub pending: u32,
+    pub limit: u32,
+}
+
+/// Usage of each `TenantSettings` quota in a tenant canister
+#[derive(Debug, Clone, PartialEq, CandidType, Serialize, Deserialize)]
+pub struct TenantQuotaUsage {
+    pub students: QuotaUsage,
+    pub instructors: QuotaUsage,
+    pub courses: QuotaUsage,
+}
+
 // Stable storage implementations
 #[cfg(feature = "stable-storage")]
-impl Storable for User {
-    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
-    
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
+versioned_storable!(User);
 
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
-    }
+#[cfg(feature = "stable-storage")]
+versioned_storable!(Tenant, upgrades = [upgrade_tenant_v0, upgrade_tenant_v1]);
+
+/// Tenant layout of schema version 1, before template tracking
+#[cfg(feature = "stable-storage")]
+#[derive(CandidType, Deserialize)]
+struct TenantV1 {
+    id: String,
+    name: String,
+    subdomain: String,
+    canister_id: String,
+    admin_ids: Vec<String>,
+    created_at: u64,
+    #[serde(default = "default_updated_at")]
+    updated_at: u64,
+    is_active: bool,
+    #[serde(default)]
+    settings: TenantSettings,
 }
 
+/// Version 0 covers every layout written before envelopes: the version 1 struct and two
+/// older ones. All are normalised to the version 1 layout.
 #[cfg(feature = "stable-storage")]
-impl Storable for Tenant {
-    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
-    
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
+fn upgrade_tenant_v0(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    #[derive(CandidType, Deserialize)]
+    struct OldTenant {
+        id: String,
+        name: String,
+        domain: String,
+        canister_id: candid::Principal,
+        admin_principal: candid::Principal,
+        created_at: u64,
+        is_active: bool,
     }
 
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        // Try to decode with new format first
-        if let Ok(tenant) = candid::decode_one::<Tenant>(&bytes) {
-            return tenant;
-        }
-        
-        // If that fails, try to decode with old format and migrate
-        #[derive(CandidType, Deserialize)]
-        struct OldTenant {
-            id: String,
-            name: String,
-            domain: String,
-            canister_id: candid::Principal,
-            admin_principal: candid::Principal,
-            created_at: u64,
-            is_active: bool,
+    // Already with String canister_id, but without settings or updated_at
+    #[derive(CandidType, Deserialize)]
+    struct OldTenant2 {
+        id: String,
+        name: String,
+        subdomain: String,
+        canister_id: String,
+        admin_ids: Vec<String>,
+        created_at: u64,
+        is_active: bool,
+    }
+
+    let mut tenant = if let Ok(tenant) = candid::decode_one::<TenantV1>(bytes) {
+        tenant
+    } else if let Ok(old_tenant) = candid::decode_one::<OldTenant>(bytes) {
+        TenantV1 {
+            id: old_tenant.id,
+            name: old_tenant.name,
+            subdomain: old_tenant.domain.split('.').next().unwrap_or(&old_tenant.domain).to_string(),
+            canister_id: old_tenant.canister_id.to_string(),
+            admin_ids: vec![old_tenant.admin_principal.to_string()],
+            created_at: old_tenant.created_at,
+            updated_at: old_tenant.created_at,
+            is_active: old_tenant.is_active,
+            settings: TenantSettings::default(),
         }
-        
-        if let Ok(old_tenant) = candid::decode_one::<OldTenant>(&bytes) {
-            // Migrate old format to new format
-            return Tenant {
-                id: old_tenant.id,
-                name: old_tenant.name,
-                subdomain: old_tenant.domain.split('.').next().unwrap_or(&old_tenant.domain).to_string(),
-                canister_id: old_tenant.canister_id.to_string(),
-                admin_ids: vec![old_tenant.admin_principal.to_string()],
-                created_at: old_tenant.created_at,
-                updated_at: old_tenant.created_at,
-                is_active: old_tenant.is_active,
-                settings: TenantSettings::default(),
-            };
+    } else if let Ok(old_tenant2) = candid::decode_one::<OldTenant2>(bytes) {
+        TenantV1 {
+            id: old_tenant2.id,
+            name: old_tenant2.name,
+            subdomain: old_tenant2.subdomain,
+            canister_id: old_tenant2.canister_id,
+            admin_ids: old_tenant2.admin_ids,
+            created_at: old_tenant2.created_at,
+            updated_at: old_tenant2.created_at,
+            is_active: old_tenant2.is_active,
+            settings: TenantSettings::default(),
         }
-        
-        // If both fail, try another potential old format (already with String canister_id)
+    } else {
+        return Err("unrecognised Tenant layout".to_string());
+    };
+
+    // Early registrations stored zeroed settings and no update time
+    if tenant.settings.max_students == 0 {
+        tenant.settings = TenantSettings::default();
+    }
+    if tenant.updated_at == 0 {
+        tenant.updated_at = tenant.created_at;
+    }
+    candid::encode_one(&tenant).map_err(|e| e.to_string())
+}
+
+/// Tenants written before template tracking follow the stable channel; their installed
+/// version is unknown until the router next installs or upgrades their canister
+#[cfg(feature = "stable-storage")]
+fn upgrade_tenant_v1(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let tenant: TenantV1 = candid::decode_one(bytes).map_err(|e| e.to_string())?;
+    candid::encode_one(Tenant {
+        id: tenant.id,
+        name: tenant.name,
+        subdomain: tenant.subdomain,
+        canister_id: tenant.canister_id,
+        admin_ids: tenant.admin_ids,
+        created_at: tenant.created_at,
+        updated_at: tenant.updated_at,
+        is_active: tenant.is_active,
+        settings: tenant.settings,
+        template_version: None,
+        module_hash: None,
+        template_track: TemplateTrack::default(),
+    }).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "stable-storage")]
+versioned_storable!(TenantSettings);
+
+#[cfg(all(test, feature = "stable-storage"))]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use crate::versioned::{Storable, Versioned, stored_version};
+
+    #[test]
+    fn test_legacy_tenant_upgrades() {
         #[derive(CandidType, Deserialize)]
         struct OldTenant2 {
             id: String,
@@ -225,21 +330,25 @@ impl Storable for Tenant {
             created_at: u64,
             is_active: bool,
         }
-        
-        if let Ok(old_tenant2) = candid::decode_one::<OldTenant2>(&bytes) {
-            return Tenant {
-                id: old_tenant2.id,
-                name: old_tenant2.name,
-                subdomain: old_tenant2.subdomain,
-                canister_id: old_tenant2.canister_id,
-                admin_ids: old_tenant2.admin_ids,
-                created_at: old_tenant2.created_at,
-                updated_at: old_tenant2.created_at,
-                is_active: old_tenant2.is_active,
-                settings: TenantSettings::default(),
-            };
-        }
-        
-        panic!("Failed to decode Tenant from stored bytes");
+        let legacy = candid::encode_one(OldTenant2 {
+            id: "t1".to_string(),
+            name: "Harvard".to_string(),
+            subdomain: "harvard".to_string(),
+            canister_id: "aaaaa-aa".to_string(),
+            admin_ids: vec![],
+            created_at: 7,
+            is_active: true,
+        }).unwrap();
+        assert_eq!(stored_version(&legacy), 0);
+
+        let tenant = Tenant::from_bytes(Cow::Owned(legacy));
+        assert_eq!(tenant.updated_at, 7);
+        assert_eq!(tenant.settings.max_students, 1000);
+        assert_eq!(tenant.template_track, TemplateTrack::default());
+        assert_eq!(tenant.module_hash, None);
+
+        let bytes = tenant.to_bytes().into_owned();
+        assert_eq!(stored_version(&bytes), Tenant::schema_version());
+        assert_eq!(Tenant::from_bytes(Cow::Owned(bytes)).subdomain, "harvard");
     }
 }
@@ -1,10 +1,21 @@
+#[cfg(target_arch = "wasm32")]
 use ic_cdk::api::time;
 
 /// Get current timestamp in nanoseconds
+#[cfg(target_arch = "wasm32")]
 pub fn current_time() -> u64 {
     time()
 }
 
+/// Get current timestamp in nanoseconds; native builds such as unit tests have no IC clock
+#[cfg(not(target_arch = "wasm32"))]
+pub fn current_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
 /// Generate a simple ID (in production, use more robust ID generation)
 pub fn generate_id(prefix: &str) -> String {
     format!("{}_{}", prefix, current_time())
@@ -36,10 +47,26 @@ pub fn is_valid_email(email: &str) -> bool {
     email.contains('@') && email.contains('.')
 }
 
-/// Validate subdomain format
+/// Validate subdomain format against DNS label rules:
+/// 1-63 lowercase ASCII letters, digits or hyphens, not starting or ending with a hyphen
 pub fn is_valid_subdomain(subdomain: &str) -> bool {
-    subdomain.len() > 0 
-        && subdomain.chars().all(|c| c.is_alphanumeric() || c == '-')
+    !subdomain.is_empty()
+        && subdomain.len() <= 63
+        && subdomain.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
         && !subdomain.starts_with('-')
         && !subdomain.ends_with('-')
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subdomain_dns_label_rules() {
+        assert!(is_valid_subdomain("my-university2"));
+        assert!(is_valid_subdomain(&"a".repeat(63)));
+        assert!(!is_valid_subdomain("University"));
+        assert!(!is_valid_subdomain("université"));
+        assert!(!is_valid_subdomain(&"a".repeat(64)));
+    }
+}
@@ -0,0 +1,350 @@
+// Versioned envelopes and schema migrations for values kept in stable memory
+//
+// Every stored value is written as `MAGIC`, a little-endian u16 schema version and the
+// candid encoding of the value. Bytes without the magic were written before envelopes
+// existed and are version 0. Reading an older version runs the type's registered upgrades
+// in order, so a canister can always read what an earlier build wrote; `SchemaState`
+// then rewrites whole stores in the background so old upgrades can eventually be dropped.
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "stable-storage")]
+pub use ic_stable_structures::{Storable, storable::Bound};
+#[cfg(feature = "stable-storage")]
+use ic_stable_structures::{Memory, StableBTreeMap, StableCell};
+#[cfg(feature = "stable-storage")]
+use std::borrow::Cow;
+
+// Candid payloads start with "DIDL", so legacy bytes can never be mistaken for an envelope
+const MAGIC: &[u8; 4] = b"LMSV";
+const HEADER_LEN: usize = 6;
+
+/// Turns the candid payload of one schema version into the payload of the next
+pub type UpgradeFn = fn(&[u8]) -> Result<Vec<u8>, String>;
+
+/// A type stored in stable memory under an explicit schema version
+pub trait Versioned: CandidType + DeserializeOwned {
+    /// `UPGRADES[n]` converts a version `n` payload to version `n + 1`;
+    /// the current version is the number of upgrades
+    const UPGRADES: &'static [UpgradeFn] = &[];
+
+    fn schema_version() -> u16 {
+        Self::UPGRADES.len() as u16
+    }
+}
+
+/// Encode a value in an envelope tagged with its current schema version
+pub fn encode_versioned<T: Versioned>(value: &T) -> Vec<u8> {
+    let payload = candid::encode_one(value).expect("Failed to encode stored value");
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&T::schema_version().to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+/// Decode a value written at any schema version up to the current one
+pub fn decode_versioned<T: Versioned>(bytes: &[u8]) -> Result<T, String> {
+    let (version, payload) = split_envelope(bytes);
+    let current = T::schema_version();
+    if version > current {
+        return Err(format!(
+            "stored at schema version {} but this build only knows versions up to {}", version, current
+        ));
+    }
+
+    if version == current {
+        return candid::decode_one(payload).map_err(|e| e.to_string());
+    }
+    let mut payload = payload.to_vec();
+    for (from, upgrade) in T::UPGRADES.iter().enumerate().skip(version as usize) {
+        payload = upgrade(&payload)
+            .map_err(|e| format!("upgrade from schema version {} failed: {}", from, e))?;
+    }
+    candid::decode_one(&payload).map_err(|e| e.to_string())
+}
+
+/// Schema version of stored bytes; 0 for bytes written before envelopes
+pub fn stored_version(bytes: &[u8]) -> u16 {
+    split_envelope(bytes).0
+}
+
+fn split_envelope(bytes: &[u8]) -> (u16, &[u8]) {
+    if bytes.len() >= HEADER_LEN && bytes.starts_with(MAGIC) {
+        (u16::from_le_bytes([bytes[4], bytes[5]]), &bytes[HEADER_LEN..])
+    } else {
+        (0, bytes)
+    }
+}
+
+/// Implement `Versioned` and an enveloped `Storable` for a type.
+///
+/// `upgrades` lists the type's `UpgradeFn`s oldest first; `bound` defaults to `Unbounded`
+/// and must stay what the store was created with.
+#[cfg(feature = "stable-storage")]
+#[macro_export]
+macro_rules! versioned_storable {
+    ($type:ty) => {
+        $crate::versioned_storable!($type, upgrades = [], bound = $crate::versioned::Bound::Unbounded);
+    };
+    ($type:ty, upgrades = [$($upgrade:expr),* $(,)?]) => {
+        $crate::versioned_storable!($type, upgrades = [$($upgrade),*], bound = $crate::versioned::Bound::Unbounded);
+    };
+    ($type:ty, bound = $bound:expr) => {
+        $crate::versioned_storable!($type, upgrades = [], bound = $bound);
+    };
+    ($type:ty, upgrades = [$($upgrade:expr),* $(,)?], bound = $bound:expr) => {
+        impl $crate::versioned::Versioned for $type {
+            const UPGRADES: &'static [$crate::versioned::UpgradeFn] = &[$($upgrade),*];
+        }
+
+        impl $crate::versioned::Storable for $type {
+            const BOUND: $crate::versioned::Bound = $bound;
+
+            fn to_bytes(&self) -> ::std::borrow::Cow<'_, [u8]> {
+                ::std::borrow::Cow::Owned($crate::versioned::encode_versioned(self))
+            }
+
+            fn from_bytes(bytes: ::std::borrow::Cow<'_, [u8]>) -> Self {
+                $crate::versioned::decode_versioned(&bytes).unwrap_or_else(|e| {
+                    panic!("Failed to decode {}: {}", stringify!($type), e)
+                })
+            }
+        }
+    };
+}
+
+/// Progress of rewriting one store at its current schema version
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct StoreMigration {
+    pub store: String,
+    pub from_version: u16,
+    pub to_version: u16,
+    /// Entries in the store when the migration started
+    pub total: u64,
+    pub migrated: u64,
+    /// Key of the last entry rewritten, as stored bytes
+    pub cursor: Option<Vec<u8>>,
+    pub started_at: u64,
+    pub completed_at: Option<u64>,
+}
+
+/// Schema version each store was last migrated to, and the latest migration of each store
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct SchemaState {
+    pub versions: Vec<(String, u16)>,
+    pub migrations: Vec<StoreMigration>,
+}
+
+#[cfg(feature = "stable-storage")]
+versioned_storable!(SchemaState);
+
+/// Re-saves up to `limit` entries after the cursor key. Returns how many were re-saved
+/// and the cursor to continue from, or `None` once the store is done.
+pub type RewriteFn = fn(Option<Vec<u8>>, usize) -> (u64, Option<Vec<u8>>);
+
+/// A stable store registered with its canister's migration runner
+pub struct SchemaStore {
+    pub name: &'static str,
+    /// Current schema version of the store's values
+    pub version: u16,
+    pub len: fn() -> u64,
+    pub rewrite: RewriteFn,
+}
+
+impl SchemaState {
+    /// Version a store was last migrated to; stores never recorded predate envelopes
+    pub fn version_of(&self, store: &str) -> u16 {
+        self.versions.iter()
+            .find(|(name, _)| name == store)
+            .map(|(_, version)| *version)
+            .unwrap_or(0)
+    }
+
+    /// Whether any store still has entries to rewrite
+    pub fn is_migrating(&self) -> bool {
+        self.migrations.iter().any(|migration| migration.completed_at.is_none())
+    }
+
+    /// Record every store at its current version. For fresh installs, whose stores hold nothing older.
+    pub fn record_current(&mut self, stores: &[SchemaStore]) {
+        for store in stores {
+            self.set_version(store.name, store.version);
+        }
+    }
+
+    /// Start a migration for each store whose schema version rose since it was recorded.
+    /// Fails if any store was written by a newer build, since its values cannot be read.
+    pub fn plan(&mut self, stores: &[SchemaStore], now: u64) -> Result<usize, String> {
+        if let Some(store) = stores.iter().find(|store| self.version_of(store.name) > store.version) {
+            return Err(format!(
+                "Store '{}' is at schema version {} but this build only supports {}",
+                store.name, self.version_of(store.name), store.version
+            ));
+        }
+
+        let mut started = 0;
+        for store in stores {
+            let recorded = self.version_of(store.name);
+            if recorded == store.version {
+                self.set_version(store.name, recorded);
+                continue;
+            }
+            let underway = self.migrations.iter().any(|migration| {
+                migration.store == store.name && migration.to_version == store.version && migration.completed_at.is_none()
+            });
+            if underway {
+                continue;
+            }
+
+            // A migration interrupted by an upgrade to a newer version restarts towards that version
+            self.migrations.retain(|migration| migration.store != store.name);
+            self.migrations.push(StoreMigration {
+                store: store.name.to_string(),
+                from_version: recorded,
+                to_version: store.version,
+                total: (store.len)(),
+                migrated: 0,
+                cursor: None,
+                started_at: now,
+                completed_at: None,
+            });
+            started += 1;
+        }
+        Ok(started)
+    }
+
+    /// Rewrite pending stores in batches until all are done or `out_of_budget` returns true.
+    /// Returns whether every migration has completed.
+    pub fn run(
+        &mut self,
+        stores: &[SchemaStore],
+        batch_size: usize,
+        now: u64,
+        out_of_budget: impl Fn() -> bool,
+    ) -> bool {
+        let mut completed = Vec::new();
+        for migration in self.migrations.iter_mut().filter(|migration| migration.completed_at.is_none()) {
+            // A store no longer registered has nothing left to rewrite
+            let Some(store) = stores.iter().find(|store| store.name == migration.store) else {
+                migration.completed_at = Some(now);
+                continue;
+            };
+
+            loop {
+                if out_of_budget() {
+                    break;
+                }
+                let (count, cursor) = (store.rewrite)(migration.cursor.take(), batch_size);
+                migration.migrated += count;
+                migration.cursor = cursor;
+                if migration.cursor.is_none() {
+                    migration.completed_at = Some(now);
+                    completed.push((store.name, migration.to_version));
+                    break;
+                }
+            }
+        }
+
+        for (store, version) in completed {
+            self.set_version(store, version);
+        }
+        !self.is_migrating()
+    }
+
+    fn set_version(&mut self, store: &str, version: u16) {
+        match self.versions.iter_mut().find(|(name, _)| name == store) {
+            Some(entry) => entry.1 = version,
+            None => self.versions.push((store.to_string(), version)),
+        }
+    }
+}
+
+/// Re-save up to `limit` map entries after `cursor` so they are stored at the current version
+#[cfg(feature = "stable-storage")]
+pub fn rewrite_entries<K, V, M>(
+    map: &mut StableBTreeMap<K, V, M>,
+    cursor: Option<Vec<u8>>,
+    limit: usize,
+) -> (u64, Option<Vec<u8>>)
+where
+    K: Storable + Ord + Clone,
+    V: Storable,
+    M: Memory,
+{
+    use std::ops::Bound as RangeBound;
+
+    let start = match cursor {
+        Some(bytes) => RangeBound::Excluded(K::from_bytes(Cow::Owned(bytes))),
+        None => RangeBound::Unbounded,
+    };
+    let batch: Vec<(K, V)> = map.range((start, RangeBound::Unbounded)).take(limit).collect();
+    let count = batch.len() as u64;
+    let last = batch.last().map(|(key, _)| key.to_bytes().into_owned());
+    for (key, value) in batch {
+        map.insert(key, value);
+    }
+
+    // A short batch reached the end of the map
+    (count, if count > 0 && count == limit as u64 { last } else { None })
+}
+
+/// Re-save a cell's value so it is stored at the current version
+#[cfg(feature = "stable-storage")]
+pub fn rewrite_cell<T, M>(cell: &mut StableCell<T, M>) -> (u64, Option<Vec<u8>>)
+where
+    T: Storable + Clone,
+    M: Memory,
+{
+    let value = cell.get().clone();
+    cell.set(value).expect("Failed to rewrite stable cell");
+    (1, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static REWRITTEN: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // Five entries keyed 1..=5, rewritten by recording their keys
+    fn fake_rewrite(cursor: Option<Vec<u8>>, limit: usize) -> (u64, Option<Vec<u8>>) {
+        let after = cursor.map(|bytes| bytes[0] as u64).unwrap_or(0);
+        let batch: Vec<u64> = (after + 1..=5).take(limit).collect();
+        REWRITTEN.with(|keys| keys.borrow_mut().extend(&batch));
+        let cursor = (batch.len() == limit).then(|| vec![*batch.last().unwrap() as u8]);
+        (batch.len() as u64, cursor)
+    }
+
+    #[test]
+    fn test_plan_and_run_migrations() {
+        let stores = vec![
+            SchemaStore { name: "current", version: 0, len: || 3, rewrite: |_, _| unreachable!() },
+            SchemaStore { name: "upgraded", version: 2, len: || 5, rewrite: fake_rewrite },
+        ];
+        let mut state = SchemaState::default();
+        assert_eq!(state.plan(&stores, 1), Ok(1));
+        assert_eq!(state.version_of("upgraded"), 0);
+
+        // Budget for a single batch of two
+        let batches = RefCell::new(0);
+        let out_of_budget = || { *batches.borrow_mut() += 1; *batches.borrow() > 1 };
+        assert!(!state.run(&stores, 2, 2, out_of_budget));
+        assert_eq!(state.migrations[0].migrated, 2);
+
+        assert!(state.run(&stores, 2, 3, || false));
+        assert_eq!(REWRITTEN.with(|keys| keys.borrow().clone()), vec![1, 2, 3, 4, 5]);
+        assert_eq!(state.version_of("upgraded"), 2);
+        assert_eq!(state.migrations[0].completed_at, Some(3));
+        assert_eq!(state.plan(&stores, 4), Ok(0));
+
+        // A build with an older schema cannot read the stores
+        let downgraded = vec![SchemaStore { name: "upgraded", version: 1, len: || 5, rewrite: fake_rewrite }];
+        assert!(state.plan(&downgraded, 5).is_err());
+    }
+}
@@ -6,6 +6,7 @@ pub mod courses;
 pub mod grades;
 pub mod quizzes;
 pub mod system;
+pub mod platform;
 
 // Re-export all public API functions for backward compatibility
 pub use users::*;
@@ -13,6 +14,7 @@ pub use courses::*;
 pub use grades::*;
 pub use quizzes::*;
 pub use system::*;
+pub use platform::*;
 
 // Re-export pre-provisioning functions
 pub use crate::pre_provision::*;
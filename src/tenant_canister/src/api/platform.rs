@@ -0,0 +1,230 @@
+use candid::{candid_method, Principal};
+use ic_cdk::{query, update, caller};
+use shared::{
+    LMSResult, LMSError, TenantDataExport, TenantSettings, TenantSummary, SchemaState, User, UserRole,
+    BackupStore, BackupBatch, utils
+};
+use ic_stable_structures::StableBTreeMap;
+use crate::storage::{Memory, TENANT_DATA, USERS, COURSES, LESSONS, QUIZZES, QUIZ_ATTEMPTS, GRADES, PRE_PROVISIONED_USERS};
+use crate::{backup, quota, rbac, schema};
+
+// Platform API - called by the router canister, which controls every tenant canister
+
+/// Export this tenant's records in one reply, without file contents.
+/// Large tenants exceed the reply limit; full backups use `export_backup_batch`.
+#[query]
+#[candid_method(query)]
+pub fn export_tenant_data() -> LMSResult<TenantDataExport> {
+    rbac::require_controller()?;
+    
+    let tenant_data = TENANT_DATA.with(|data| data.borrow().get().clone())
+        .ok_or_else(|| LMSError::InitializationError("Tenant not initialized".to_string()))?;
+    
+    Ok(TenantDataExport {
+        tenant_id: tenant_data.tenant_id,
+        admin_principal: tenant_data.admin_principal,
+        exported_at: utils::current_time(),
+        users: USERS.with(|users| users.borrow().iter().map(|(_, user)| user).collect()),
+        courses: COURSES.with(|courses| courses.borrow().iter().map(|(_, course)| course).collect()),
+        lessons: LESSONS.with(|lessons| lessons.borrow().iter().map(|(_, lesson)| lesson).collect()),
+        quizzes: QUIZZES.with(|quizzes| quizzes.borrow().iter().map(|(_, quiz)| quiz).collect()),
+        quiz_attempts: QUIZ_ATTEMPTS.with(|attempts| attempts.borrow().iter().map(|(_, attempt)| attempt).collect()),
+        grades: GRADES.with(|grades| grades.borrow().iter().map(|(_, grade)| grade).collect()),
+        pre_provisioned_users: PRE_PROVISIONED_USERS.with(|users| users.borrow().iter().map(|(_, user)| user).collect()),
+        files: crate::file_storage::list_all_file_metadata(),
+    })
+}
+
+/// Usage counts collected by the router for platform analytics
+#[query]
+#[candid_method(query)]
+pub fn get_tenant_summary() -> LMSResult<TenantSummary> {
+    rbac::require_controller()?;
+    
+    let mut summary = TenantSummary {
+        tenant_id: crate::storage::get_tenant_id()?,
+        collected_at: utils::current_time(),
+        ..TenantSummary::default()
+    };
+    
+    USERS.with(|users| {
+        for (_, user) in users.borrow().iter() {
+            if !user.is_active {
+                summary.inactive_users += 1;
+                continue;
+            }
+            match user.role {
+                UserRole::Student => summary.students += 1,
+                UserRole::Instructor => summary.instructors += 1,
+                UserRole::Admin | UserRole::TenantAdmin => summary.admins += 1,
+            }
+        }
+    });
+    COURSES.with(|courses| {
+        for (_, course) in courses.borrow().iter() {
+            summary.courses += 1;
+            if course.is_published {
+                summary.published_courses += 1;
+            }
+        }
+    });
+    summary.quiz_attempts = QUIZ_ATTEMPTS.with(|attempts| attempts.borrow().len());
+    (summary.files, summary.storage_bytes) = crate::file_storage::storage_totals();
+    
+    Ok(summary)
+}
+
+/// Send this canister's cycles, minus `reserve`, back to the calling controller
+#[update]
+#[candid_method(update)]
+pub async fn return_cycles(reserve: u128) -> LMSResult<u128> {
+    let controller = rbac::require_controller()?;
+    
+    let amount = ic_cdk::api::canister_balance128().saturating_sub(reserve);
+    if amount == 0 {
+        return Ok(0);
+    }
+    
+    let (accepted,): (u128,) = ic_cdk::api::call::call_with_payment128(controller, "receive_cycles", (), amount)
+        .await
+        .map_err(|(code, msg)| LMSError::InternalError(format!("Failed to return cycles: {:?} - {}", code, msg)))?;
+    
+    ic_cdk::println!("Returned {} cycles to {}", accepted, caller());
+    Ok(accepted)
+}
+
+/// Store the quotas and flags the router holds for this tenant
+#[update]
+#[candid_method(update)]
+pub fn apply_tenant_settings(settings: TenantSettings) -> LMSResult<()> {
+    rbac::require_controller()?;
+    quota::apply_settings(settings)
+}
+
+/// Store the display name the router holds for this tenant
+#[update]
+#[candid_method(update)]
+pub fn set_tenant_name(name: String) -> LMSResult<()> {
+    rbac::require_controller()?;
+    let mut tenant_data = TENANT_DATA.with(|data| data.borrow().get().clone())
+        .ok_or_else(|| LMSError::InitializationError("Tenant not initialized".to_string()))?;
+    tenant_data.name = Some(name);
+    TENANT_DATA.with(|data| {
+        data.borrow_mut().set(Some(tenant_data)).expect("Failed to store tenant data");
+    });
+    Ok(())
+}
+
+/// Schema version of each stable store and progress of its latest migration
+#[query]
+#[candid_method(query)]
+pub fn get_schema_status() -> LMSResult<SchemaState> {
+    rbac::require_controller()?;
+    Ok(schema::get_schema_status())
+}
+
+/// Continue schema migrations the last upgrade did not finish
+#[update]
+#[candid_method(update)]
+pub fn run_schema_migration() -> LMSResult<SchemaState> {
+    rbac::require_controller()?;
+    Ok(schema::continue_migrations())
+}
+
+/// Make another principal the tenant admin, returning the previous one. The admin principal and
+/// TenantAdmin user change together; the previous admin's user is demoted to Admin when
+/// `keep_previous` is set and deactivated otherwise.
+#[update]
+#[candid_method(update)]
+pub fn transfer_tenant_admin(new_admin: Principal, keep_previous: bool) -> LMSResult<Principal> {
+    rbac::require_controller()?;
+    if new_admin == Principal::anonymous() {
+        return Err(LMSError::ValidationError("The anonymous principal cannot be an admin".to_string()));
+    }
+    
+    let mut tenant_data = TENANT_DATA.with(|data| data.borrow().get().clone())
+        .ok_or_else(|| LMSError::InitializationError("Tenant not initialized".to_string()))?;
+    let previous = tenant_data.admin_principal;
+    if previous == new_admin {
+        return Ok(previous);
+    }
+    
+    let now = utils::current_time();
+    USERS.with(|users| {
+        let mut users = users.borrow_mut();
+        demote_admin_user(&mut users, previous, keep_previous, now);
+        
+        // An existing user keeps their profile and is promoted
+        let admin_user = match users.get(&new_admin.to_string()) {
+            Some(user) => User { role: UserRole::TenantAdmin, is_active: true, updated_at: now, ..user },
+            None => User {
+                id: new_admin.to_string(),
+                name: "TenantAdmin".to_string(),
+                email: format!("admin@{}.edu", tenant_data.tenant_id),
+                role: UserRole::TenantAdmin,
+                tenant_id: tenant_data.tenant_id.clone(),
+                created_at: now,
+                updated_at: now,
+                is_active: true,
+            },
+        };
+        users.insert(admin_user.id.clone(), admin_user);
+    });
+    
+    tenant_data.admin_principal = new_admin;
+    TENANT_DATA.with(|data| {
+        data.borrow_mut().set(Some(tenant_data)).expect("Failed to store tenant data");
+    });
+    
+    ic_cdk::println!("Tenant admin transferred from {} to {} by {}", previous, new_admin, caller());
+    Ok(previous)
+}
+
+/// Deactivate a TenantAdmin user that is not the current admin principal, such as the
+/// previous admin a restore into another admin replays from its backup
+#[update]
+#[candid_method(update)]
+pub fn revoke_admin_user(user: Principal) -> LMSResult<()> {
+    rbac::require_controller()?;
+    let admin_principal = TENANT_DATA.with(|data| data.borrow().get().clone())
+        .ok_or_else(|| LMSError::InitializationError("Tenant not initialized".to_string()))?
+        .admin_principal;
+    if user == admin_principal {
+        return Err(LMSError::ValidationError("The current admin cannot be revoked".to_string()));
+    }
+    
+    USERS.with(|users| demote_admin_user(&mut users.borrow_mut(), user, false, utils::current_time()));
+    ic_cdk::println!("Revoked tenant admin user {} by {}", user, caller());
+    Ok(())
+}
+
+/// Demote a TenantAdmin user to Admin, or deactivate it when it is not kept
+fn demote_admin_user(users: &mut StableBTreeMap<String, User, Memory>, principal: Principal, keep: bool, now: u64) {
+    if let Some(mut user) = users.get(&principal.to_string()) {
+        if user.role == UserRole::TenantAdmin {
+            if keep {
+                user.role = UserRole::Admin;
+            } else {
+                user.is_active = false;
+            }
+            user.updated_at = now;
+            users.insert(user.id.clone(), user);
+        }
+    }
+}
+
+/// Export the next batch of a stable store for a backup, continuing after `cursor`
+#[query]
+#[candid_method(query)]
+pub fn export_backup_batch(store: BackupStore, cursor: Option<String>) -> LMSResult<BackupBatch> {
+    rbac::require_controller()?;
+    Ok(backup::export_batch(store, cursor))
+}
+
+/// Replay a backup batch into this canister; used to restore into a freshly provisioned tenant
+#[update]
+#[candid_method(update)]
+pub fn import_backup_batch(batch: BackupBatch) -> LMSResult<u64> {
+    rbac::require_controller()?;
+    backup::import_batch(batch)
+}
@@ -1,9 +1,9 @@
 use candid::candid_method;
 use ic_cdk::{query, update, caller};
-use shared::{User, UserRole, LMSResult, LMSError};
+use shared::{User, UserRole, LMSResult, LMSError, TenantSettings, TenantQuotaUsage};
 use crate::types::TenantData;
 use crate::storage::{TENANT_DATA, USERS};
-use crate::{user_management, rbac};
+use crate::{user_management, rbac, quota};
 
 // System API
 
@@ -24,6 +24,22 @@ pub fn get_tenant_info() -> LMSResult<TenantData> {
     })
 }
 
+/// Get the quotas and flags set by the router
+#[query]
+#[candid_method(query)]
+pub fn get_tenant_settings() -> LMSResult<TenantSettings> {
+    rbac::require_admin()?;
+    Ok(quota::get_settings())
+}
+
+/// Get usage against each quota (admin only)
+#[query]
+#[candid_method(query)]
+pub fn get_quota_usage() -> LMSResult<TenantQuotaUsage> {
+    rbac::require_admin()?;
+    Ok(quota::get_quota_usage())
+}
+
 /// Recover tenant data from existing users (migration helper)
 #[update]
 #[candid_method(update)]
@@ -47,6 +63,7 @@ pub fn recover_tenant_data() -> LMSResult<TenantData> {
         admin_principal: caller(),
         created_at: shared::utils::current_time(),
         is_initialized: true,
+        name: None,
     };
     
     TENANT_DATA.with(|data| {
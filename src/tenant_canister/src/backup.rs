@@ -0,0 +1,108 @@
+// Backup Module
+// Streams the tenant's stable maps to the router in batches and replays them on restore
+
+use std::ops::Bound;
+use ic_stable_structures::{StableBTreeMap, Storable};
+use shared::versioned::{Versioned, decode_versioned};
+use shared::{BackupBatch, BackupEntry, BackupStore, FileMetadata, LMSError, LMSResult};
+use crate::storage::{
+    Memory, USERS, COURSES, LESSONS, QUIZZES, QUIZ_ATTEMPTS, GRADES, PRE_PROVISIONED_USERS
+};
+use crate::file_storage::{FILE_METADATA, FILE_CHUNKS};
+
+// Replies are capped at 2MB; a single file chunk (up to 1MB) always fits on its own
+// and is never paired with enough data to cross the cap
+const BATCH_BYTES: usize = 1_500_000;
+const BATCH_ENTRIES: usize = 500;
+
+/// Export the entries of a store that follow `cursor`
+pub fn export_batch(store: BackupStore, cursor: Option<String>) -> BackupBatch {
+    let (schema_version, (entries, next_cursor)) = match store {
+        BackupStore::Users => USERS.with(|map| export_map(&map.borrow(), cursor)),
+        BackupStore::Courses => COURSES.with(|map| export_map(&map.borrow(), cursor)),
+        BackupStore::Lessons => LESSONS.with(|map| export_map(&map.borrow(), cursor)),
+        BackupStore::Quizzes => QUIZZES.with(|map| export_map(&map.borrow(), cursor)),
+        BackupStore::QuizAttempts => QUIZ_ATTEMPTS.with(|map| export_map(&map.borrow(), cursor)),
+        BackupStore::Grades => GRADES.with(|map| export_map(&map.borrow(), cursor)),
+        BackupStore::PreProvisionedUsers => PRE_PROVISIONED_USERS.with(|map| export_map(&map.borrow(), cursor)),
+        BackupStore::FileMetadata => FILE_METADATA.with(|map| export_map(&map.borrow(), cursor)),
+        BackupStore::FileChunks => FILE_CHUNKS.with(|map| export_map(&map.borrow(), cursor)),
+    };
+    BackupBatch { store, schema_version, entries, next_cursor }
+}
+
+/// Replay a batch into this canister, upgrading values written by an older build.
+/// The whole batch is decoded before anything is written, so a bad entry changes nothing.
+pub fn import_batch(batch: BackupBatch) -> LMSResult<u64> {
+    let count = batch.entries.len() as u64;
+    match batch.store {
+        BackupStore::FileMetadata => {
+            let files: Vec<FileMetadata> = FILE_METADATA.with(|map| import_map(&mut map.borrow_mut(), batch.entries))?;
+            // Storage totals are kept incrementally, so restored files are counted as they arrive
+            for file in files {
+                crate::file_storage::update_storage_stats(&file.uploader_id, file.file_size as i64);
+            }
+        },
+        BackupStore::Users => { USERS.with(|map| import_map(&mut map.borrow_mut(), batch.entries))?; },
+        BackupStore::Courses => { COURSES.with(|map| import_map(&mut map.borrow_mut(), batch.entries))?; },
+        BackupStore::Lessons => { LESSONS.with(|map| import_map(&mut map.borrow_mut(), batch.entries))?; },
+        BackupStore::Quizzes => { QUIZZES.with(|map| import_map(&mut map.borrow_mut(), batch.entries))?; },
+        BackupStore::QuizAttempts => { QUIZ_ATTEMPTS.with(|map| import_map(&mut map.borrow_mut(), batch.entries))?; },
+        BackupStore::Grades => { GRADES.with(|map| import_map(&mut map.borrow_mut(), batch.entries))?; },
+        BackupStore::PreProvisionedUsers => {
+            PRE_PROVISIONED_USERS.with(|map| import_map(&mut map.borrow_mut(), batch.entries))?;
+        },
+        BackupStore::FileChunks => { FILE_CHUNKS.with(|map| import_map(&mut map.borrow_mut(), batch.entries))?; },
+    }
+
+    ic_cdk::println!("Restored {} {:?} entries", count, batch.store);
+    Ok(count)
+}
+
+fn export_map<V: Versioned + Storable>(
+    map: &StableBTreeMap<String, V, Memory>,
+    cursor: Option<String>,
+) -> (u16, (Vec<BackupEntry>, Option<String>)) {
+    let start = match cursor {
+        Some(key) => Bound::Excluded(key),
+        None => Bound::Unbounded,
+    };
+
+    let mut entries: Vec<BackupEntry> = Vec::new();
+    let mut bytes = 0;
+    for (key, value) in map.range((start, Bound::Unbounded)) {
+        let value = value.to_bytes().into_owned();
+        let size = key.len() + value.len();
+        // A batch always takes at least one entry, however large
+        if !entries.is_empty() && (entries.len() >= BATCH_ENTRIES || bytes + size > BATCH_BYTES) {
+            let next_cursor = entries.last().map(|entry| entry.key.clone());
+            return (V::schema_version(), (entries, next_cursor));
+        }
+        bytes += size;
+        entries.push(BackupEntry { key, value });
+    }
+    (V::schema_version(), (entries, None))
+}
+
+/// Insert every entry, returning the values that were not already present
+fn import_map<V: Versioned + Storable + Clone>(
+    map: &mut StableBTreeMap<String, V, Memory>,
+    entries: Vec<BackupEntry>,
+) -> LMSResult<Vec<V>> {
+    let decoded = entries.into_iter()
+        .map(|entry| {
+            decode_versioned::<V>(&entry.value)
+                .map(|value| (entry.key.clone(), value))
+                .map_err(|e| LMSError::ValidationError(format!("Backup entry '{}' is unreadable: {}", entry.key, e)))
+        })
+        .collect::<LMSResult<Vec<(String, V)>>>()?;
+
+    let mut added = Vec::new();
+    for (key, value) in decoded {
+        if map.insert(key, value.clone()).is_none() {
+            added.push(value);
+        }
+    }
+    Ok(added)
+}
+
@@ -19,6 +19,8 @@ pub fn create_course(id: String, title: String, description: String) -> LMSResul
     let tenant_id = get_tenant_id()?;
     let caller = caller();
     
+    crate::quota::check_course_quota()?;
+    
     COURSES.with(|courses| {
         let mut courses_map = courses.borrow_mut();
         
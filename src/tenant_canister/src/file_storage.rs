@@ -16,22 +16,22 @@ use crate::storage::{MEMORY_MANAGER, Memory};
 
 // File storage maps using the existing memory manager
 thread_local! {
-    static FILE_METADATA: RefCell<StableBTreeMap<String, FileMetadata, Memory>> = 
+    pub(crate) static FILE_METADATA: RefCell<StableBTreeMap<String, FileMetadata, Memory>> = 
         RefCell::new(StableBTreeMap::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(ic_stable_structures::memory_manager::MemoryId::new(8)))
         ));
     
-    static FILE_CHUNKS: RefCell<StableBTreeMap<String, FileChunk, Memory>> = 
+    pub(crate) static FILE_CHUNKS: RefCell<StableBTreeMap<String, FileChunk, Memory>> = 
         RefCell::new(StableBTreeMap::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(ic_stable_structures::memory_manager::MemoryId::new(9)))
         ));
     
-    static UPLOAD_SESSIONS: RefCell<StableBTreeMap<String, UploadSession, Memory>> = 
+    pub(crate) static UPLOAD_SESSIONS: RefCell<StableBTreeMap<String, UploadSession, Memory>> = 
         RefCell::new(StableBTreeMap::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(ic_stable_structures::memory_manager::MemoryId::new(10)))
         ));
     
-    static DOWNLOAD_STREAMS: RefCell<StableBTreeMap<String, DownloadStream, Memory>> = 
+    pub(crate) static DOWNLOAD_STREAMS: RefCell<StableBTreeMap<String, DownloadStream, Memory>> = 
         RefCell::new(StableBTreeMap::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(ic_stable_structures::memory_manager::MemoryId::new(11)))
         ));
@@ -41,7 +41,7 @@ thread_local! {
             MEMORY_MANAGER.with(|m| m.borrow().get(ic_stable_structures::memory_manager::MemoryId::new(12)))
         ));
     
-    static STORAGE_STATS: RefCell<ic_stable_structures::StableCell<FileStats, Memory>> = 
+    pub(crate) static STORAGE_STATS: RefCell<ic_stable_structures::StableCell<FileStats, Memory>> = 
         RefCell::new(ic_stable_structures::StableCell::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(ic_stable_structures::memory_manager::MemoryId::new(13))),
             FileStats {
@@ -480,6 +480,21 @@ pub fn get_storage_stats() -> LMSResult<FileStats> {
     })
 }
 
+/// List metadata of every stored file (used for tenant data export)
+pub fn list_all_file_metadata() -> Vec<FileMetadata> {
+    FILE_METADATA.with(|metadata| {
+        metadata.borrow().iter().map(|(_, file)| file).collect()
+    })
+}
+
+/// Number of stored files and their total size in bytes
+pub fn storage_totals() -> (u64, u64) {
+    STORAGE_STATS.with(|stats| {
+        let stats = stats.borrow();
+        (stats.get().total_files, stats.get().total_size)
+    })
+}
+
 /// Update file tags and description
 #[update]
 #[candid_method(update)]
@@ -628,7 +643,7 @@ fn update_cache_stats(cache_hit: bool) {
 }
 
 /// Update storage statistics
-fn update_storage_stats(user_id: &str, size_delta: i64) {
+pub(crate) fn update_storage_stats(user_id: &str, size_delta: i64) {
     STORAGE_STATS.with(|stats| {
         let mut current_stats = stats.borrow().get().clone();
         
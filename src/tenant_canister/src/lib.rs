@@ -12,17 +12,21 @@ mod grade_management;  // Re-export facade for grade management
 mod quiz_management;   // Re-export facade for quiz management
 mod pre_provision;     // Pre-provisioning for university authentication
 mod file_storage;      // File storage and asset management
+mod quota;             // TenantSettings quota enforcement
+mod schema;            // Stable store schema migrations
+mod backup;            // Backup export and restore batches
 mod api;         // Modularized API endpoints
 mod http;        // Modularized HTTP handling
 mod http_handler;
 
-use ic_cdk::init;
+use ic_cdk::{init, post_upgrade};
 use candid::Principal;
 use std::collections::HashMap;
 use shared::{
     User, UserRole, utils, LMSResult, Course, Grade, GradeType, Quiz, QuizAttempt, Question, Answer,
     PreProvisionedUser, PreProvisionStatus, UniversityImportRecord, ImportStats, EmailVerificationRequest,
-    FileMetadata, FileChunk, UploadSession, FileOperationResult, FileStats, PrivacyLevel, OwnerType
+    FileMetadata, FileChunk, UploadSession, FileOperationResult, FileStats, PrivacyLevel, OwnerType,
+    TenantDataExport, TenantSettings, TenantQuotaUsage, TenantSummary, SchemaState, BackupStore, BackupBatch
 };
 use crate::types::TenantData;
 use crate::storage::{TENANT_DATA, USERS};
@@ -79,6 +83,7 @@ fn init(init_args: Option<(Option<String>, Option<Principal>)>) {
                 admin_principal,
                 is_initialized: true,
                 created_at: utils::current_time(),
+                name: None,
             }
         } else {
             // Fresh initialization with provided tenant_id
@@ -87,6 +92,7 @@ fn init(init_args: Option<(Option<String>, Option<Principal>)>) {
                 admin_principal,
                 is_initialized: true,
                 created_at: utils::current_time(),
+                name: None,
             };
             
             // Only create admin user if we have a real admin principal (not anonymous)
@@ -118,6 +124,13 @@ fn init(init_args: Option<(Option<String>, Option<Principal>)>) {
         data.borrow_mut().set(Some(tenant_data.clone())).expect("Failed to store tenant data");
         ic_cdk::println!("Tenant canister initialized with admin: {} and tenant_id: {}", admin_principal, tenant_data.tenant_id);
     });
+    schema::init_schema();
+}
+
+/// Migrate stable stores whose schema changed in the new build
+#[post_upgrade]
+fn post_upgrade() {
+    schema::run_upgrade_migrations();
 }
 
 // Generate Candid interface
@@ -10,6 +10,7 @@ use shared::{
 };
 use crate::storage::{PRE_PROVISIONED_USERS, USERS, COURSES, get_tenant_id};
 use crate::rbac::require_admin;
+use crate::quota::{self, SeatReservations};
 
 /// Import university records in bulk
 #[update]
@@ -27,6 +28,9 @@ pub fn import_university_records(records: Vec<UniversityImportRecord>) -> LMSRes
         timestamp: utils::current_time(),
     };
     
+    // Every imported record holds a seat until it is linked or deleted
+    let mut seats = SeatReservations::load();
+    
     PRE_PROVISIONED_USERS.with(|users| {
         let mut users_map = users.borrow_mut();
         
@@ -60,6 +64,11 @@ pub fn import_university_records(records: Vec<UniversityImportRecord>) -> LMSRes
                         continue;
                     }
                     
+                    if let Err(e) = seats.reserve(&user.role) {
+                        stats.errors.push(format!("Skipped {}: {}", user.university_id, e));
+                        continue;
+                    }
+                    
                     // Count by role
                     match user.role {
                         UserRole::Student => stats.students_imported += 1,
@@ -103,6 +112,8 @@ pub fn import_single_record(mut record: UniversityImportRecord) -> LMSResult<Str
     
     let user = PreProvisionedUser::from_import_record(record.clone(), &tenant_id)?;
     
+    SeatReservations::load().reserve(&user.role)?;
+    
     PRE_PROVISIONED_USERS.with(|users| {
         let mut users_map = users.borrow_mut();
         
@@ -233,6 +244,14 @@ pub fn link_internet_identity(university_id: String, email: String) -> LMSResult
         Ok(())
     })?;
     
+    // The seat was reserved at import, but the limit may have been lowered since
+    let reserved_role = PRE_PROVISIONED_USERS.with(|pre_users| {
+        pre_users.borrow().get(&university_id).map(|pre_user| pre_user.role)
+    });
+    if let Some(role) = reserved_role {
+        quota::check_linking_user_quota(&role)?;
+    }
+    
     PRE_PROVISIONED_USERS.with(|pre_users| {
         let mut pre_users_map = pre_users.borrow_mut();
         
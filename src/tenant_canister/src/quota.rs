@@ -0,0 +1,198 @@
+// Quota Enforcement Module
+// Checks TenantSettings limits before users and courses are created
+
+use shared::{
+    TenantSettings, TenantQuotaUsage, QuotaUsage, UserRole, PreProvisionStatus,
+    LMSResult, LMSError
+};
+use crate::storage::{TENANT_SETTINGS, USERS, COURSES, PRE_PROVISIONED_USERS};
+
+/// Get the settings last pushed by the router
+pub fn get_settings() -> TenantSettings {
+    TENANT_SETTINGS.with(|settings| settings.borrow().get().clone())
+}
+
+/// Store settings pushed by the router. Lowering a limit below current usage
+/// keeps existing users and courses but blocks new ones.
+pub fn apply_settings(settings: TenantSettings) -> LMSResult<()> {
+    TENANT_SETTINGS.with(|stored| {
+        stored.borrow_mut().set(settings.clone()).map_err(|_| {
+            LMSError::InternalError("Failed to store tenant settings".to_string())
+        })
+    })?;
+
+    ic_cdk::println!("Tenant settings applied: {} students, {} instructors, {} courses",
+                     settings.max_students, settings.max_instructors, settings.max_courses);
+    Ok(())
+}
+
+/// Check that one more active account with `role` fits in its quota.
+/// Seats reserved by pre-provisioned users who have not linked yet count as taken.
+pub fn check_user_quota(role: &UserRole) -> LMSResult<()> {
+    check_seats(role, 0)
+}
+
+/// Check the quota for a pre-provisioned user who is linking; the seat they
+/// reserved at import becomes their active seat, so it is not counted twice.
+/// Call before borrowing the pre-provisioned users mutably.
+pub fn check_linking_user_quota(role: &UserRole) -> LMSResult<()> {
+    check_seats(role, 1)
+}
+
+/// Check that one more course fits in the course quota
+pub fn check_course_quota() -> LMSResult<()> {
+    let courses = COURSES.with(|courses| courses.borrow().len());
+    ensure_capacity("course", courses, get_settings().max_courses)
+}
+
+/// Seats taken per quota, counting pre-provisioned users who have not linked yet.
+/// Used by imports, which hold a seat for every record they add.
+pub struct SeatReservations {
+    settings: TenantSettings,
+    students: u64,
+    instructors: u64,
+}
+
+impl SeatReservations {
+    /// Count current seats; call before borrowing the pre-provisioned users mutably
+    pub fn load() -> Self {
+        Self {
+            settings: get_settings(),
+            students: active_users(&UserRole::Student) + pending_users(&UserRole::Student),
+            instructors: active_users(&UserRole::Instructor) + pending_users(&UserRole::Instructor),
+        }
+    }
+
+    /// Take a seat for `role`, or fail when its quota is full
+    pub fn reserve(&mut self, role: &UserRole) -> LMSResult<()> {
+        let Some((kind, limit)) = user_limit(&self.settings, role) else {
+            return Ok(());
+        };
+        let taken = match role {
+            UserRole::Student => &mut self.students,
+            _ => &mut self.instructors,
+        };
+        ensure_capacity(kind, *taken, limit)?;
+        *taken += 1;
+        Ok(())
+    }
+}
+
+/// Usage of every quota
+pub fn get_quota_usage() -> TenantQuotaUsage {
+    let settings = get_settings();
+    let courses = COURSES.with(|courses| courses.borrow().len());
+
+    TenantQuotaUsage {
+        students: QuotaUsage {
+            used: active_users(&UserRole::Student) as u32,
+            pending: pending_users(&UserRole::Student) as u32,
+            limit: settings.max_students,
+        },
+        instructors: QuotaUsage {
+            used: active_users(&UserRole::Instructor) as u32,
+            pending: pending_users(&UserRole::Instructor) as u32,
+            limit: settings.max_instructors,
+        },
+        courses: QuotaUsage {
+            used: courses as u32,
+            pending: 0,
+            limit: settings.max_courses,
+        },
+    }
+}
+
+/// Quota that applies to a role; administrators are not limited
+fn user_limit(settings: &TenantSettings, role: &UserRole) -> Option<(&'static str, u32)> {
+    match role {
+        UserRole::Student => Some(("student", settings.max_students)),
+        UserRole::Instructor => Some(("instructor", settings.max_instructors)),
+        UserRole::Admin | UserRole::TenantAdmin => None,
+    }
+}
+
+fn check_seats(role: &UserRole, own_reservations: u64) -> LMSResult<()> {
+    match user_limit(&get_settings(), role) {
+        Some((kind, limit)) => {
+            let taken = (active_users(role) + pending_users(role)).saturating_sub(own_reservations);
+            ensure_capacity(kind, taken, limit)
+        }
+        None => Ok(()),
+    }
+}
+
+fn active_users(role: &UserRole) -> u64 {
+    USERS.with(|users| {
+        users.borrow()
+            .iter()
+            .filter(|(_, user)| user.is_active && &user.role == role)
+            .count() as u64
+    })
+}
+
+fn pending_users(role: &UserRole) -> u64 {
+    PRE_PROVISIONED_USERS.with(|users| {
+        users.borrow()
+            .iter()
+            .filter(|(_, user)| &user.role == role && user.status != PreProvisionStatus::Linked)
+            .count() as u64
+    })
+}
+
+fn ensure_capacity(kind: &str, used: u64, limit: u32) -> LMSResult<()> {
+    if used >= limit as u64 {
+        return Err(LMSError::QuotaExceeded(format!(
+            "This tenant has reached its limit of {} {}s", limit, kind
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::PreProvisionedUser;
+
+    #[test]
+    fn test_reserve_seats() {
+        let mut seats = SeatReservations {
+            settings: TenantSettings { max_students: 2, max_instructors: 0, ..TenantSettings::default() },
+            students: 1,
+            instructors: 0,
+        };
+
+        assert!(seats.reserve(&UserRole::Student).is_ok());
+        assert!(matches!(seats.reserve(&UserRole::Student), Err(LMSError::QuotaExceeded(_))));
+        assert!(seats.reserve(&UserRole::Instructor).is_err());
+        assert!(seats.reserve(&UserRole::Admin).is_ok());
+    }
+
+    #[test]
+    fn test_reserved_seats_block_direct_registration() {
+        TENANT_SETTINGS.with(|stored| {
+            stored.borrow_mut()
+                .set(TenantSettings { max_students: 1, ..TenantSettings::default() })
+                .unwrap();
+        });
+        PRE_PROVISIONED_USERS.with(|users| {
+            users.borrow_mut().insert("S001".to_string(), PreProvisionedUser {
+                university_id: "S001".to_string(),
+                email: "s001@uni.edu".to_string(),
+                name: "Reserved Student".to_string(),
+                role: UserRole::Student,
+                department: None,
+                year_of_study: None,
+                course_codes: vec![],
+                created_at: 0,
+                ii_principal: None,
+                is_verified: true,
+                verification_code: None,
+                verification_expires: None,
+                status: PreProvisionStatus::Verified,
+            });
+        });
+
+        assert!(matches!(check_user_quota(&UserRole::Student), Err(LMSError::QuotaExceeded(_))));
+        assert!(check_linking_user_quota(&UserRole::Student).is_ok());
+    }
+}
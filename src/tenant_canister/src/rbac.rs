@@ -244,6 +244,18 @@ pub fn require_student() -> LMSResult<User> {
     }
 }
 
+/// Guard wrapper that checks if caller controls this canister (the router or a platform operator)
+pub fn require_controller() -> LMSResult<Principal> {
+    let caller_principal = caller();
+    if ic_cdk::api::is_controller(&caller_principal) {
+        Ok(caller_principal)
+    } else {
+        Err(LMSError::AccessDenied(
+            "Only canister controllers can perform this action".to_string()
+        ))
+    }
+}
+
 /// Check if caller can access specific user data
 pub fn can_access_user_data(target_user_id: &str) -> LMSResult<()> {
     let caller_user = get_caller_user()?;
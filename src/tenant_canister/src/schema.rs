@@ -0,0 +1,110 @@
+// Schema Migration Module
+// Rewrites stable stores whose schema version changed in a new build
+
+use ic_cdk::api::instruction_counter;
+use shared::versioned::{SchemaStore, Versioned, rewrite_cell, rewrite_entries};
+use shared::{
+    Course, User, Grade, Lesson, Quiz, QuizAttempt, PreProvisionedUser, TenantSettings,
+    FileMetadata, FileChunk, UploadSession, DownloadStream, FileStats, SchemaState, current_time
+};
+use crate::types::TenantData;
+use crate::storage::{
+    TENANT_DATA, USERS, COURSES, GRADES, LESSONS, QUIZZES, QUIZ_ATTEMPTS, PRE_PROVISIONED_USERS,
+    TENANT_SETTINGS, SCHEMA_STATE
+};
+use crate::file_storage::{FILE_METADATA, FILE_CHUNKS, UPLOAD_SESSIONS, DOWNLOAD_STREAMS, STORAGE_STATS};
+
+const BATCH_SIZE: usize = 100;
+// Headroom below the instruction limits of an upgrade (300B) and of an update (40B).
+// The tenant has no timers, so whatever the upgrade leaves is continued by the router.
+const UPGRADE_INSTRUCTION_BUDGET: u64 = 200_000_000_000;
+const MESSAGE_INSTRUCTION_BUDGET: u64 = 10_000_000_000;
+
+macro_rules! map_store {
+    ($name:literal, $value:ty, $store:ident) => {
+        SchemaStore {
+            name: $name,
+            version: <$value as Versioned>::schema_version(),
+            len: || $store.with(|map| map.borrow().len()),
+            rewrite: |cursor, limit| $store.with(|map| rewrite_entries(&mut map.borrow_mut(), cursor, limit)),
+        }
+    };
+}
+
+macro_rules! cell_store {
+    ($name:literal, $value:ty, $store:ident) => {
+        SchemaStore {
+            name: $name,
+            version: <$value as Versioned>::schema_version(),
+            len: || 1,
+            rewrite: |_, _| $store.with(|cell| rewrite_cell(&mut cell.borrow_mut())),
+        }
+    };
+}
+
+/// Every store whose values are versioned; the file cache holds raw bytes and has no schema
+fn stores() -> Vec<SchemaStore> {
+    vec![
+        cell_store!("tenant_data", TenantData, TENANT_DATA),
+        map_store!("users", User, USERS),
+        map_store!("courses", Course, COURSES),
+        map_store!("grades", Grade, GRADES),
+        map_store!("lessons", Lesson, LESSONS),
+        map_store!("quizzes", Quiz, QUIZZES),
+        map_store!("quiz_attempts", QuizAttempt, QUIZ_ATTEMPTS),
+        map_store!("pre_provisioned_users", PreProvisionedUser, PRE_PROVISIONED_USERS),
+        cell_store!("tenant_settings", TenantSettings, TENANT_SETTINGS),
+        map_store!("file_metadata", FileMetadata, FILE_METADATA),
+        map_store!("file_chunks", FileChunk, FILE_CHUNKS),
+        map_store!("upload_sessions", UploadSession, UPLOAD_SESSIONS),
+        map_store!("download_streams", DownloadStream, DOWNLOAD_STREAMS),
+        cell_store!("storage_stats", FileStats, STORAGE_STATS),
+    ]
+}
+
+/// Record every store at its current version on a fresh install
+pub fn init_schema() {
+    let mut state = get_schema_status();
+    state.record_current(&stores());
+    save_schema_state(state);
+}
+
+/// Start migrations for stores whose schema changed and run as much as fits in the upgrade.
+/// Traps when the stored schema is newer than this build, so a downgrade is rolled back.
+pub fn run_upgrade_migrations() {
+    let stores = stores();
+    let mut state = get_schema_status();
+    let started = state.plan(&stores, current_time()).unwrap_or_else(|reason| ic_cdk::trap(&reason));
+    let finished = state.run(&stores, BATCH_SIZE, current_time(), || {
+        instruction_counter() > UPGRADE_INSTRUCTION_BUDGET
+    });
+    save_schema_state(state);
+
+    if started > 0 {
+        ic_cdk::println!("Schema migrations started for {} stores ({})",
+                         started, if finished { "finished" } else { "waiting for run_schema_migration" });
+    }
+}
+
+/// Continue unfinished migrations within one message's instruction budget
+pub fn continue_migrations() -> SchemaState {
+    let mut state = get_schema_status();
+    if state.is_migrating() {
+        state.run(&stores(), BATCH_SIZE, current_time(), || {
+            instruction_counter() > MESSAGE_INSTRUCTION_BUDGET
+        });
+        save_schema_state(state.clone());
+    }
+    state
+}
+
+/// Recorded store versions and the latest migration of each store
+pub fn get_schema_status() -> SchemaState {
+    SCHEMA_STATE.with(|state| state.borrow().get().clone())
+}
+
+fn save_schema_state(state: SchemaState) {
+    SCHEMA_STATE.with(|cell| {
+        cell.borrow_mut().set(state).expect("Failed to store schema state");
+    });
+}
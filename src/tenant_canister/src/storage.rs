@@ -5,7 +5,7 @@ use ic_stable_structures::{
     memory_manager::{MemoryId, MemoryManager, VirtualMemory}
 };
 use std::cell::RefCell;
-use shared::{Course, User, Grade, Lesson, Quiz, QuizAttempt, PreProvisionedUser};
+use shared::{Course, User, Grade, Lesson, Quiz, QuizAttempt, PreProvisionedUser, TenantSettings, SchemaState};
 use crate::types::TenantData;
 
 pub type Memory = VirtualMemory<DefaultMemoryImpl>;
@@ -66,6 +66,22 @@ thread_local! {
             MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(7)))
         )
     );
+    
+    // Quotas and flags pushed by the router; defaults apply until the first push
+    pub static TENANT_SETTINGS: RefCell<StableCell<TenantSettings, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(14))),
+            TenantSettings::default()
+        ).expect("Failed to initialize tenant settings")
+    );
+    
+    // Schema versions of the stores above and progress of their migrations
+    pub static SCHEMA_STATE: RefCell<StableCell<SchemaState, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(15))),
+            SchemaState::default()
+        ).expect("Failed to initialize schema state")
+    );
 }
 
 /// Get the current tenant ID
@@ -1,7 +1,5 @@
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
-use ic_stable_structures::Storable;
-use std::borrow::Cow;
 
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
 pub struct TenantData {
@@ -9,16 +7,8 @@ pub struct TenantData {
     pub admin_principal: Principal,
     pub is_initialized: bool,
     pub created_at: u64,
+    /// Display name held by the router, pushed with `set_tenant_name`
+    pub name: Option<String>,
 }
 
-impl Storable for TenantData {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
-    }
-
-    fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(bytes.as_ref()).unwrap()
-    }
-
-    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
-}
+shared::versioned_storable!(TenantData);
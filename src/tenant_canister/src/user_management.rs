@@ -1,6 +1,7 @@
 use shared::{User, UserRole, LMSResult, LMSError, utils};
 use crate::storage::{USERS, get_tenant_id};
 use crate::rbac::is_admin_compat;
+use crate::quota;
 
 /// Register a new user in the tenant
 pub fn register_user(id: String, name: String, email: String, role: UserRole, tenant_id: String) -> LMSResult<User> {
@@ -17,6 +18,8 @@ pub fn register_user(id: String, name: String, email: String, role: UserRole, te
         return Err(LMSError::ValidationError("Tenant ID mismatch".to_string()));
     }
     
+    quota::check_user_quota(&role)?;
+    
     USERS.with(|users| {
         let mut users_map = users.borrow_mut();
         
@@ -62,6 +65,14 @@ pub fn get_user(user_id: String) -> LMSResult<User> {
 pub fn update_user(user_id: String, name: Option<String>, email: Option<String>, is_active: Option<bool>) -> LMSResult<User> {
     is_admin_compat()?;
     
+    // Reactivating an account takes a seat again
+    if is_active == Some(true) {
+        let user = get_user(user_id.clone())?;
+        if !user.is_active {
+            quota::check_user_quota(&user.role)?;
+        }
+    }
+    
     USERS.with(|users| {
         let mut users_map = users.borrow_mut();
         
@@ -93,6 +104,11 @@ pub fn update_user(user_id: String, name: Option<String>, email: Option<String>,
 pub fn update_user_role(user_id: String, new_role: UserRole) -> LMSResult<User> {
     is_admin_compat()?;
     
+    let user = get_user(user_id.clone())?;
+    if user.is_active && user.role != new_role {
+        quota::check_user_quota(&new_role)?;
+    }
+    
     USERS.with(|users| {
         let mut users_map = users.borrow_mut();
         